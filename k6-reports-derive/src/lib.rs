@@ -0,0 +1,326 @@
+//! `#[derive(K6Metric)]` for the metric types of `k6-reports`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+//! #[k6(measurement = "vus")]
+//! pub struct VusMetric {
+//!     pub time: DateTime<Utc>,
+//!     #[k6(field)]
+//!     pub value: f64,
+//! }
+//! ```
+//!
+//! The `time` field is the timestamp of the data point. Every other field has
+//! to be marked either as a tag or as a field, so that the queried columns
+//! always match the struct. Columns are named after the fields, or after
+//! `#[serde(rename = "...")]` when it's given, the same as in the exported
+//! files.
+//!
+//! The generated code refers to `::k6_reports`, so the macro is used through
+//! the `k6_reports::metrics::K6Metric` re-export.
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, LitStr, PathArguments,
+    Result, Type,
+};
+
+const TIME_FIELD: &str = "time";
+
+#[proc_macro_derive(K6Metric, attributes(k6))]
+pub fn derive_k6_metric(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Time,
+    Tag,
+    Field,
+}
+
+struct Column {
+    name: String,
+    kind: ColumnKind,
+    data_type: TokenStream2,
+    nullable: bool,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+    let measurement = parse_measurement(&input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "K6Metric can be derived only for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "K6Metric can be derived only for structs",
+            ))
+        }
+    };
+
+    let mut columns: Vec<Column> = vec![];
+    reject_rename_all(&input)?;
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let kind = parse_column_kind(field, &ident)?;
+        let (data_type, nullable) = column_type(&field.ty)?;
+        columns.push(Column {
+            name: parse_serde_rename(field)?.unwrap_or(ident),
+            kind,
+            data_type,
+            nullable,
+        });
+    }
+    for (index, column) in columns.iter().enumerate() {
+        if columns[..index]
+            .iter()
+            .any(|other| other.name == column.name)
+        {
+            return Err(Error::new_spanned(
+                ident,
+                format!("the `{}` column is declared more than once", column.name),
+            ));
+        }
+    }
+    if !columns.iter().any(|column| column.kind == ColumnKind::Time) {
+        return Err(Error::new_spanned(
+            ident,
+            "K6Metric requires the `time: DateTime<Utc>` field",
+        ));
+    }
+    if !columns
+        .iter()
+        .any(|column| column.kind == ColumnKind::Field)
+    {
+        return Err(Error::new_spanned(
+            ident,
+            "K6Metric requires at least one `#[k6(field)]` field",
+        ));
+    }
+
+    // Tags are quoted, since some of them (e.g. `group`) are reserved
+    // keywords of InfluxQL
+    let query_fields = columns.iter().map(|column| match column.kind {
+        ColumnKind::Tag => format!("\"{}\"", column.name),
+        _ => column.name.clone(),
+    });
+    let schema = columns.iter().map(|column| {
+        let name = &column.name;
+        let kind = match column.kind {
+            ColumnKind::Time => quote!(::k6_reports::metrics::ColumnKind::Time),
+            ColumnKind::Tag => quote!(::k6_reports::metrics::ColumnKind::Tag),
+            ColumnKind::Field => quote!(::k6_reports::metrics::ColumnKind::Field),
+        };
+        let data_type = &column.data_type;
+        let nullable = column.nullable;
+        quote! {
+            ::k6_reports::metrics::Column {
+                name: #name,
+                kind: #kind,
+                data_type: #data_type,
+                nullable: #nullable,
+            }
+        }
+    });
+    let time_field = syn::Ident::new(TIME_FIELD, Span::call_site());
+
+    Ok(quote! {
+        impl ::k6_reports::metrics::K6Metric for #ident {
+            fn metric_table_name() -> &'static str {
+                #measurement
+            }
+
+            fn query_fields() -> &'static [&'static str] {
+                &[#(#query_fields),*]
+            }
+
+            fn schema() -> &'static [::k6_reports::metrics::Column] {
+                &[#(#schema),*]
+            }
+
+            fn time(&self) -> ::k6_reports::__private::DateTime<::k6_reports::__private::Utc> {
+                self.#time_field
+            }
+        }
+    })
+}
+
+fn parse_measurement(input: &DeriveInput) -> Result<String> {
+    let mut measurement = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("k6")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("measurement") {
+                let value: LitStr = meta.value()?.parse()?;
+                measurement = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `measurement = \"...\"`"))
+            }
+        })?;
+    }
+
+    measurement.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "K6Metric requires the `#[k6(measurement = \"...\")]` attribute",
+        )
+    })
+}
+
+/// Reads `#[serde(rename = "...")]` of the field, so that the column has the
+/// same name as in the serialized rows.
+fn parse_serde_rename(field: &syn::Field) -> Result<Option<String>> {
+    let mut rename = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(syn::token::Paren) {
+                    return Err(meta.error(
+                        "K6Metric supports only `rename = \"...\"`, since the column has the same name in both directions",
+                    ));
+                }
+                let value: LitStr = meta.value()?.parse()?;
+                rename = Some(value.value());
+            } else if meta.input.peek(syn::Token![=]) {
+                // Skip the values of other attributes, e.g. `default = "..."`
+                let _: syn::Expr = meta.value()?.parse()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|nested| {
+                    if nested.input.peek(syn::Token![=]) {
+                        let _: syn::Expr = nested.value()?.parse()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(rename)
+}
+
+/// Renaming all fields would need the serde rules to be repeated here, so
+/// the fields are renamed one by one instead.
+fn reject_rename_all(input: &DeriveInput) -> Result<()> {
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        let mut result = Ok(());
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                result = Err(meta.error(
+                    "K6Metric doesn't support `rename_all`, rename the fields with `#[serde(rename = \"...\")]`",
+                ));
+            }
+            if meta.input.peek(syn::Token![=]) {
+                let _: syn::Expr = meta.value()?.parse()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|nested| {
+                    if nested.input.peek(syn::Token![=]) {
+                        let _: syn::Expr = nested.value()?.parse()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+        result?;
+    }
+    Ok(())
+}
+
+fn parse_column_kind(field: &syn::Field, name: &str) -> Result<ColumnKind> {
+    let mut kind = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("k6")) {
+        attr.parse_nested_meta(|meta| {
+            let parsed = if meta.path.is_ident("tag") {
+                ColumnKind::Tag
+            } else if meta.path.is_ident("field") {
+                ColumnKind::Field
+            } else {
+                return Err(meta.error("expected `tag` or `field`"));
+            };
+            match kind.replace(parsed) {
+                Some(_) => Err(meta.error("the column is already marked as a tag or a field")),
+                None => Ok(()),
+            }
+        })?;
+    }
+
+    match (name == TIME_FIELD, kind) {
+        (true, None) => Ok(ColumnKind::Time),
+        (true, Some(_)) => Err(Error::new_spanned(
+            field,
+            "the `time` field is the timestamp and can't be a tag or a field",
+        )),
+        (false, Some(kind)) => Ok(kind),
+        (false, None) => Err(Error::new_spanned(
+            field,
+            "mark the column with `#[k6(tag)]` or `#[k6(field)]`",
+        )),
+    }
+}
+
+/// Maps the Rust type of the column onto its data type in the schema.
+fn column_type(ty: &Type) -> Result<(TokenStream2, bool)> {
+    if let Some(inner) = option_type(ty) {
+        let (data_type, _) = column_type(inner)?;
+        return Ok((data_type, true));
+    }
+
+    let name = match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    };
+    let data_type = match name.as_deref() {
+        Some("DateTime") => quote!(::k6_reports::metrics::DataType::Timestamp),
+        Some("String") => quote!(::k6_reports::metrics::DataType::String),
+        Some("bool") => quote!(::k6_reports::metrics::DataType::Boolean),
+        Some("u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64") => {
+            quote!(::k6_reports::metrics::DataType::Integer)
+        }
+        Some("f32" | "f64") => quote!(::k6_reports::metrics::DataType::Float),
+        _ => return Err(Error::new_spanned(ty, "unsupported column type")),
+    };
+    Ok((data_type, false))
+}
+
+fn option_type(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => match arguments.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
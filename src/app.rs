@@ -1,5 +1,30 @@
-﻿use crate::cli::Command;
-use crate::report::K6Report;
+﻿use std::path::Path;
+
+use chrono::{Duration, Utc};
+
+use k6_reports::checks::ChecksReport;
+use k6_reports::comparison::{Comparison, ComparisonOptions};
+use k6_reports::error::Result;
+use k6_reports::groups::GroupReport;
+use k6_reports::import::{RunImporter, TimeShift};
+use k6_reports::influx::InfluxSource;
+use k6_reports::k6_output::K6OutputSource;
+use k6_reports::layout::ExportedRun;
+use k6_reports::live::LiveDashboard;
+use k6_reports::output::write_output;
+use k6_reports::postgres::{PostgresHistory, PostgresSource};
+use k6_reports::prometheus::PrometheusSource;
+use k6_reports::report::K6Report;
+use k6_reports::saturation::Saturation;
+use k6_reports::server::ReportServer;
+use k6_reports::slo::{SloConfig, SloReport};
+use k6_reports::source::MetricSource;
+use k6_reports::statuses::StatusReport;
+use k6_reports::timeline::Timeline;
+use k6_reports::trend::{Trend, TrendOptions};
+use k6_reports::waterfall::Waterfall;
+
+use crate::cli::Command;
 
 pub struct App;
 
@@ -8,37 +33,246 @@ impl App {
         App {}
     }
 
-    pub async fn run(&self, command: &Command) {
+    pub async fn run(&self, command: &Command) -> Result<()> {
         match command {
             Command::Export {
-                host,
-                port,
-                database,
-                username,
-                password,
-                https,
+                connection,
+                input,
+                prometheus_url,
+                prometheus_step,
+                prometheus_trend_stat,
+                postgres_url,
+                postgres_table,
                 from,
                 exclude_setup_steps,
                 exclude_teardown_steps,
                 output_directory,
+                layout_template,
+                testid,
+                profile,
+                git_sha,
+                force,
+                trim,
+                slo_config,
+                follow,
+                poll_interval,
+                idle_timeout,
+                follow_lag,
+                formats,
+                compression,
+                archive_path,
             } => {
-                let k6_report = K6Report::new(
-                    host,
-                    port,
-                    database,
-                    username,
-                    password,
-                    https,
-                    from,
-                    exclude_setup_steps,
-                    exclude_teardown_steps,
-                    output_directory,
-                );
-                match k6_report.extract_metrics().await {
-                    Ok(_) => println!("Export finished successfully"),
-                    Err(error) => println!("Export can't be executed. Reason: {:?}", error),
+                let slo_config = match slo_config {
+                    Some(path) => Some(SloConfig::load(Path::new(path)).await?),
+                    None => None,
+                };
+                let source: Box<dyn MetricSource> = match (input, prometheus_url, postgres_url) {
+                    (Some(path), _, _) => Box::new(K6OutputSource::load(path).await?),
+                    (None, Some(url), _) => Box::new(
+                        PrometheusSource::new(url)
+                            .with_step(*prometheus_step)
+                            .with_trend_stat(prometheus_trend_stat),
+                    ),
+                    (None, None, Some(url)) => Box::new(
+                        PostgresSource::connect(url)
+                            .await?
+                            .with_table(postgres_table),
+                    ),
+                    (None, None, None) => Box::new(InfluxSource::new(connection)),
+                };
+                let mut builder = K6Report::builder(source)
+                    .output_directory(output_directory)
+                    .layout_template(layout_template.clone())
+                    .testid(testid.clone())
+                    .profile(profile.clone())
+                    .git_sha(git_sha.clone())
+                    .force(*force)
+                    .from(*from)
+                    .exclude_setup_steps(*exclude_setup_steps)
+                    .exclude_teardown_steps(*exclude_teardown_steps)
+                    .trim(trim.clone())
+                    .slo_config(slo_config)
+                    .compression(*compression)
+                    .archive_path(archive_path.clone());
+                for format in formats {
+                    builder = builder.exporter(format.exporter(*compression));
+                }
+                let k6_report = builder.build()?;
+                let summary = match follow {
+                    true => {
+                        k6_report
+                            .follow_metrics(*poll_interval, *idle_timeout, *follow_lag)
+                            .await?
+                    }
+                    false => k6_report.extract_metrics().await?,
+                };
+                println!("Export finished successfully");
+                if let Some(slo) = &summary.slo {
+                    slo.check()?;
                 }
             }
+            Command::Live {
+                connection,
+                refresh_interval,
+                window,
+                bucket,
+                testid,
+            } => {
+                let dashboard =
+                    LiveDashboard::new(connection, refresh_interval, window, bucket, testid);
+                dashboard.run().await?;
+            }
+            Command::Serve { directory, address } => {
+                let server = ReportServer::new(directory, address);
+                server.run().await?;
+            }
+            Command::Trend {
+                format,
+                output_path,
+                last,
+                significant_z_score,
+                min_history_size,
+                postgres_url,
+                influx_windows,
+                testid,
+                connection,
+                directories,
+            } => {
+                let mut runs = Trend::load_directories(directories).await?;
+                if let Some(postgres_url) = postgres_url {
+                    let history = PostgresHistory::connect(postgres_url).await?;
+                    runs.extend(Trend::load_history(&history).await?);
+                }
+                runs.extend(Trend::load_influx_windows(connection, influx_windows, testid).await?);
+
+                let options = TrendOptions {
+                    significant_z_score: *significant_z_score,
+                    min_history_size: *min_history_size,
+                };
+                let trend = Trend::from_runs(runs, last, &options);
+                write_output(&trend.render(*format)?, output_path).await?;
+            }
+            Command::Compare {
+                format,
+                output_path,
+                significance_level,
+                resamples,
+                base,
+                target,
+            } => {
+                let options = ComparisonOptions {
+                    significance_level: *significance_level,
+                    resamples: *resamples,
+                };
+                let comparison = Comparison::load(
+                    &ExportedRun::open(base).await?,
+                    &ExportedRun::open(target).await?,
+                    &options,
+                )
+                .await?;
+                write_output(&comparison.render(*format)?, output_path).await?;
+            }
+            Command::Timeline {
+                format,
+                output_path,
+                interval,
+                directory,
+            } => {
+                let timeline = Timeline::from_directory(Path::new(directory), *interval).await?;
+                write_output(&timeline.render(*format)?, output_path).await?;
+            }
+            Command::Saturation {
+                format,
+                output_path,
+                step,
+                directory,
+            } => {
+                let saturation = Saturation::from_directory(Path::new(directory), step).await?;
+                write_output(&saturation.render(*format)?, output_path).await?;
+            }
+            Command::Statuses {
+                format,
+                output_path,
+                interval,
+                top,
+                directory,
+            } => {
+                let report =
+                    StatusReport::from_directory(Path::new(directory), *interval, *top).await?;
+                write_output(&report.render(*format)?, output_path).await?;
+            }
+            Command::Checks {
+                format,
+                output_path,
+                directory,
+            } => {
+                let report = ChecksReport::from_directory(Path::new(directory)).await?;
+                write_output(&report.render(*format)?, output_path).await?;
+            }
+            Command::Groups {
+                format,
+                output_path,
+                directory,
+            } => {
+                let report = GroupReport::from_directory(Path::new(directory)).await?;
+                write_output(&report.render(*format)?, output_path).await?;
+            }
+            Command::Slo {
+                format,
+                output_path,
+                config,
+                directory,
+            } => {
+                let config = match config {
+                    Some(path) => SloConfig::load(Path::new(path)).await?,
+                    None => SloConfig::default(),
+                };
+                let report = SloReport::from_directory(Path::new(directory), &config).await?;
+                write_output(&report.render(*format)?, output_path).await?;
+                report.check()?;
+            }
+            Command::Waterfall {
+                format,
+                output_path,
+                directory,
+            } => {
+                let waterfall = Waterfall::from_directory(Path::new(directory)).await?;
+                write_output(&waterfall.render(*format)?, output_path).await?;
+            }
+            Command::Store {
+                postgres_url,
+                directory,
+            } => {
+                let run = ExportedRun::open(directory).await?;
+                let mut history = PostgresHistory::connect(postgres_url).await?;
+                let points = history.store_run(&run).await?;
+                println!("Stored {} data points of `{}`", points, run.name);
+            }
+            Command::Import {
+                connection,
+                time_shift,
+                shift_to_now,
+                testid,
+                directory,
+            } => {
+                let time_shift = match (time_shift, shift_to_now) {
+                    (_, true) => Some(TimeShift::StartAt(Utc::now())),
+                    (Some(seconds), false) => Some(TimeShift::Offset(Duration::seconds(*seconds))),
+                    (None, false) => None,
+                };
+                let importer = RunImporter::new(connection)
+                    .with_time_shift(time_shift)
+                    .with_testid(testid.clone());
+                let points = importer.import(Path::new(directory)).await?;
+                println!(
+                    "Imported {} data points into {}/{}",
+                    points,
+                    importer.connection_url(),
+                    connection.database
+                );
+            }
         }
+
+        Ok(())
     }
 }
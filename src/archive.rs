@@ -0,0 +1,200 @@
+use std::fs::{read_dir, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use tokio::task::spawn_blocking;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    TarGzip,
+    TarZstd,
+    Zip,
+}
+
+impl ArchiveFormat {
+    fn from_path(path: &Path) -> Result<Self> {
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if filename.ends_with(".tar.zst") || filename.ends_with(".tzst") {
+            Ok(ArchiveFormat::TarZstd)
+        } else if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+            Ok(ArchiveFormat::TarGzip)
+        } else if filename.ends_with(".zip") {
+            Ok(ArchiveFormat::Zip)
+        } else {
+            Err(Error::Archive(format!(
+                "can't detect the archive format of `{}`; use the .tar.zst, .tar.gz or .zip extension",
+                path.display()
+            )))
+        }
+    }
+}
+
+/// Packages all files of the output directory (including the manifest) into
+/// a single archive. The files are stored under the directory name.
+pub async fn create_archive(directory: &Path, archive_path: &Path) -> Result<()> {
+    let format = ArchiveFormat::from_path(archive_path)?;
+    let directory = directory.to_path_buf();
+    let archive_path = archive_path.to_path_buf();
+
+    spawn_blocking(move || write_archive(format, &directory, &archive_path))
+        .await
+        .map_err(|error| Error::Archive(error.to_string()))?
+}
+
+/// Fails when the input is an archive created with `--archive`, since the
+/// reports read the exported files right from a directory.
+pub fn ensure_not_archive(path: &Path) -> Result<()> {
+    match path.is_file() && ArchiveFormat::from_path(path).is_ok() {
+        true => Err(Error::ArchiveInput(path.to_path_buf())),
+        false => Ok(()),
+    }
+}
+
+fn write_archive(format: ArchiveFormat, directory: &Path, archive_path: &Path) -> Result<()> {
+    let prefix = directory
+        .canonicalize()?
+        .file_name()
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let files = list_files(directory, archive_path)?;
+    let output = File::create(archive_path)?;
+
+    match format {
+        ArchiveFormat::TarGzip => {
+            let encoder = GzEncoder::new(output, flate2::Compression::default());
+            write_tar(encoder, &prefix, &files)?.finish()?;
+        }
+        ArchiveFormat::TarZstd => {
+            let encoder = zstd::Encoder::new(output, 0)?;
+            write_tar(encoder, &prefix, &files)?.finish()?;
+        }
+        ArchiveFormat::Zip => {
+            let mut zip_writer = ZipWriter::new(output);
+            for file in &files {
+                let name = prefix.join(file.file_name().unwrap_or_default());
+                zip_writer
+                    .start_file(name.to_string_lossy(), FileOptions::default())
+                    .map_err(|error| Error::Archive(error.to_string()))?;
+                io::copy(&mut File::open(file)?, &mut zip_writer)?;
+            }
+            zip_writer
+                .finish()
+                .map_err(|error| Error::Archive(error.to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, prefix: &Path, files: &[PathBuf]) -> Result<W> {
+    let mut tar_builder = tar::Builder::new(writer);
+    for file in files {
+        let name = prefix.join(file.file_name().unwrap_or_default());
+        tar_builder.append_path_with_name(file, name)?;
+    }
+    Ok(tar_builder.into_inner()?)
+}
+
+fn list_files(directory: &Path, archive_path: &Path) -> Result<Vec<PathBuf>> {
+    // The archive can be created inside of the packaged directory
+    let archive_path = archive_path
+        .canonicalize()
+        .unwrap_or_else(|_| archive_path.to_path_buf());
+
+    let mut files = vec![];
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_file() && path.canonicalize()? != archive_path {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::test_support::test_directory;
+
+    /// Writes an exported run into the `run` subdirectory.
+    fn write_run(directory: &Path) -> PathBuf {
+        let run_directory = directory.join("run");
+        std::fs::create_dir_all(&run_directory).unwrap();
+        std::fs::write(run_directory.join("manifest.json"), "{}").unwrap();
+        std::fs::write(run_directory.join("vus.csv"), "time,value\n").unwrap();
+        run_directory
+    }
+
+    fn expected_files() -> Vec<(String, String)> {
+        vec![
+            (String::from("run/manifest.json"), String::from("{}")),
+            (String::from("run/vus.csv"), String::from("time,value\n")),
+        ]
+    }
+
+    #[tokio::test]
+    async fn tar_zstd_archives_contain_the_run() {
+        let directory = test_directory("archive", "tar");
+        let run_directory = write_run(&directory);
+        // Created inside of the packaged directory, but not packaged itself
+        let archive_path = run_directory.join("run.tar.zst");
+        create_archive(&run_directory, &archive_path).await.unwrap();
+
+        let decoder = zstd::Decoder::new(File::open(&archive_path).unwrap()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        let mut files = vec![];
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            files.push((entry.path().unwrap().to_string_lossy().to_string(), content));
+        }
+        assert_eq!(files, expected_files());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn zip_archives_contain_the_run() {
+        let directory = test_directory("archive", "zip");
+        let run_directory = write_run(&directory);
+        let archive_path = run_directory.join("run.zip");
+        create_archive(&run_directory, &archive_path).await.unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+        let mut files = vec![];
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).unwrap();
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            files.push((file.name().to_string(), content));
+        }
+        assert_eq!(files, expected_files());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_archive_formats_are_refused() {
+        let directory = test_directory("archive", "unknown");
+        let run_directory = write_run(&directory);
+
+        let result = create_archive(&run_directory, &directory.join("run.rar")).await;
+        assert!(matches!(result, Err(Error::Archive(_))));
+        assert!(ensure_not_archive(&run_directory).is_ok());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt::Write;
+
+use crate::html::escape;
+
+const WIDTH: f64 = 760.0;
+const HEIGHT: f64 = 280.0;
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 30.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+const MAX_X_LABELS: usize = 8;
+const PALETTE: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// Line of the chart. Missing values leave gaps, highlighted points are
+/// circled in red.
+pub struct ChartSeries {
+    pub name: String,
+    pub values: Vec<Option<f64>>,
+    pub highlighted: Vec<bool>,
+}
+
+impl ChartSeries {
+    pub fn new(name: &str, values: Vec<Option<f64>>) -> Self {
+        let highlighted = vec![false; values.len()];
+        ChartSeries {
+            name: name.to_string(),
+            values,
+            highlighted,
+        }
+    }
+}
+
+/// Renders an inline SVG line chart, so that HTML reports have no external
+/// dependencies.
+pub fn line_chart(title: &str, labels: &[String], series: &[ChartSeries], unit: &str) -> String {
+    let max_value = series
+        .iter()
+        .flat_map(|line| line.values.iter().flatten())
+        .fold(0.0_f64, |max, value| max.max(*value));
+    let y_max = nice_ceiling(max_value);
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let x_step = match labels.len() > 1 {
+        true => plot_width / (labels.len() - 1) as f64,
+        false => 0.0,
+    };
+    let x_position = |index: usize| match labels.len() > 1 {
+        true => MARGIN_LEFT + index as f64 * x_step,
+        false => MARGIN_LEFT + plot_width / 2.0,
+    };
+    let y_position = |value: f64| MARGIN_TOP + plot_height - value / y_max * plot_height;
+
+    let legend_height = 18.0 * series.len() as f64;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" font-family=\"sans-serif\" font-size=\"11\">",
+        WIDTH,
+        HEIGHT + legend_height
+    );
+    let _ = writeln!(
+        svg,
+        "<text x=\"{0}\" y=\"18\" font-size=\"13\">{1}</text>",
+        MARGIN_LEFT,
+        escape(title)
+    );
+
+    for step in 0..=4 {
+        let value = y_max / 4.0 * step as f64;
+        let y = y_position(value);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{0}\" y1=\"{1:.1}\" x2=\"{2}\" y2=\"{1:.1}\" stroke=\"#e0e0e0\"/><text x=\"{3}\" y=\"{4:.1}\" text-anchor=\"end\">{5}{6}</text>",
+            MARGIN_LEFT,
+            y,
+            WIDTH - MARGIN_RIGHT,
+            MARGIN_LEFT - 6.0,
+            y + 4.0,
+            format_tick(value),
+            escape(unit)
+        );
+    }
+
+    let label_step = (labels.len() / MAX_X_LABELS).max(1);
+    for (index, label) in labels.iter().enumerate() {
+        if index % label_step == 0 || index == labels.len() - 1 {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{0:.1}\" y=\"{1}\" text-anchor=\"middle\">{2}</text>",
+                x_position(index),
+                HEIGHT - MARGIN_BOTTOM + 16.0,
+                escape(label)
+            );
+        }
+    }
+
+    for (series_index, line) in series.iter().enumerate() {
+        let color = PALETTE[series_index % PALETTE.len()];
+        let mut segments: Vec<Vec<(f64, f64)>> = vec![vec![]];
+        for (index, value) in line.values.iter().enumerate() {
+            match value {
+                Some(value) => segments
+                    .last_mut()
+                    .unwrap()
+                    .push((x_position(index), y_position(*value))),
+                None => segments.push(vec![]),
+            }
+        }
+
+        for segment in segments.iter().filter(|segment| !segment.is_empty()) {
+            let points: Vec<String> = segment
+                .iter()
+                .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                .collect();
+            let _ = writeln!(
+                svg,
+                "<polyline fill=\"none\" stroke=\"{0}\" stroke-width=\"2\" points=\"{1}\"/>",
+                color,
+                points.join(" ")
+            );
+            if segment.len() == 1 {
+                let (x, y) = segment[0];
+                let _ = writeln!(
+                    svg,
+                    "<circle cx=\"{0:.1}\" cy=\"{1:.1}\" r=\"3\" fill=\"{2}\"/>",
+                    x, y, color
+                );
+            }
+        }
+
+        for (index, value) in line.values.iter().enumerate() {
+            if let (Some(value), true) = (value, line.highlighted.get(index) == Some(&true)) {
+                let _ = writeln!(
+                    svg,
+                    "<circle cx=\"{0:.1}\" cy=\"{1:.1}\" r=\"6\" fill=\"none\" stroke=\"#d62728\" stroke-width=\"2\"/>",
+                    x_position(index),
+                    y_position(*value)
+                );
+            }
+        }
+
+        let legend_y = HEIGHT + 18.0 * series_index as f64;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{0}\" y=\"{1:.1}\" width=\"12\" height=\"4\" fill=\"{2}\"/><text x=\"{3}\" y=\"{4:.1}\">{5}</text>",
+            MARGIN_LEFT,
+            legend_y - 6.0,
+            color,
+            MARGIN_LEFT + 18.0,
+            legend_y,
+            escape(&line.name)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Part of each bar of a stacked bar chart.
+pub struct BarSegment {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/// Renders an inline SVG chart with a horizontal bar per label. Segments of
+/// each bar are stacked in the given order and scaled to the longest bar.
+pub fn stacked_bar_chart(
+    title: &str,
+    labels: &[String],
+    segments: &[BarSegment],
+    unit: &str,
+) -> String {
+    let bar_height = 18.0;
+    let row_height = 26.0;
+    let label_width = 220.0;
+    let plot_width = WIDTH - label_width - MARGIN_RIGHT;
+    let totals: Vec<f64> = (0..labels.len())
+        .map(|index| {
+            segments
+                .iter()
+                .map(|segment| segment.values.get(index).copied().unwrap_or(0.0))
+                .sum()
+        })
+        .collect();
+    let x_max = nice_ceiling(totals.iter().copied().fold(0.0, f64::max));
+    let bars_height = row_height * labels.len() as f64;
+    let height = MARGIN_TOP + bars_height + 24.0 + 18.0 * segments.len() as f64;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" font-family=\"sans-serif\" font-size=\"11\">",
+        WIDTH, height
+    );
+    let _ = writeln!(
+        svg,
+        "<text x=\"{0}\" y=\"18\" font-size=\"13\">{1}</text>",
+        label_width,
+        escape(title)
+    );
+
+    for step in 0..=4 {
+        let value = x_max / 4.0 * step as f64;
+        let x = label_width + value / x_max * plot_width;
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"#e0e0e0\"/><text x=\"{0:.1}\" y=\"{3}\" text-anchor=\"middle\">{4}{5}</text>",
+            x,
+            MARGIN_TOP,
+            MARGIN_TOP + bars_height,
+            MARGIN_TOP + bars_height + 14.0,
+            format_tick(value),
+            escape(unit)
+        );
+    }
+
+    for (index, label) in labels.iter().enumerate() {
+        let y = MARGIN_TOP + row_height * index as f64 + (row_height - bar_height) / 2.0;
+        let _ = writeln!(
+            svg,
+            "<text x=\"{0}\" y=\"{1:.1}\" text-anchor=\"end\">{2}</text>",
+            label_width - 8.0,
+            y + bar_height / 2.0 + 4.0,
+            escape(label)
+        );
+
+        let mut x = label_width;
+        for (segment_index, segment) in segments.iter().enumerate() {
+            let value = segment.values.get(index).copied().unwrap_or(0.0);
+            let width = value / x_max * plot_width;
+            if width > 0.0 {
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{0:.1}\" y=\"{1:.1}\" width=\"{2:.1}\" height=\"{3}\" fill=\"{4}\"><title>{5}: {6:.2}{7}</title></rect>",
+                    x,
+                    y,
+                    width,
+                    bar_height,
+                    PALETTE[segment_index % PALETTE.len()],
+                    escape(&segment.name),
+                    value,
+                    escape(unit)
+                );
+            }
+            x += width;
+        }
+    }
+
+    for (segment_index, segment) in segments.iter().enumerate() {
+        let legend_y = MARGIN_TOP + bars_height + 36.0 + 18.0 * segment_index as f64;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{0}\" y=\"{1:.1}\" width=\"12\" height=\"8\" fill=\"{2}\"/><text x=\"{3}\" y=\"{4:.1}\">{5}</text>",
+            label_width,
+            legend_y - 8.0,
+            PALETTE[segment_index % PALETTE.len()],
+            label_width + 18.0,
+            legend_y,
+            escape(&segment.name)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Rounds the maximum value up to a number that makes readable axis ticks.
+fn nice_ceiling(value: f64) -> f64 {
+    if value <= 0.0 {
+        return 1.0;
+    }
+
+    let magnitude = 10_f64.powf(value.log10().floor());
+    let normalized = value / magnitude;
+    let nice = [1.0, 2.0, 2.5, 5.0, 10.0]
+        .into_iter()
+        .find(|step| normalized <= *step)
+        .unwrap_or(10.0);
+    nice * magnitude
+}
+
+fn format_tick(value: f64) -> String {
+    match value.fract() == 0.0 {
+        true => format!("{:.0}", value),
+        false => format!("{:.2}", value),
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::groups::{collect_rows, find_or_insert_group, GroupNode};
+use crate::html::{format_rate, render_checks};
+use crate::metrics::ChecksMetric;
+use crate::output::OutputFormat;
+use crate::table::TextTable;
+
+/// Results of all checks, nested under the groups they were run in.
+#[derive(Serialize, Debug, Clone)]
+pub struct ChecksReport {
+    pub passes: u64,
+    pub fails: u64,
+    pub pass_rate: f64,
+    /// Checks outside of any group and the top-level groups.
+    pub root: GroupChecks,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct GroupChecks {
+    pub name: String,
+    /// Full group path as stored in the `group` tag.
+    pub path: String,
+    /// Totals of the group, including its subgroups.
+    pub passes: u64,
+    pub fails: u64,
+    pub checks: Vec<CheckResult>,
+    pub groups: Vec<GroupChecks>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub passes: u64,
+    pub fails: u64,
+    pub pass_rate: f64,
+    pub first_failed_at: Option<DateTime<Utc>>,
+    pub scenarios: Vec<ScenarioCheckResult>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScenarioCheckResult {
+    pub scenario: String,
+    pub passes: u64,
+    pub fails: u64,
+    pub pass_rate: f64,
+    pub first_failed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Counter {
+    passes: u64,
+    fails: u64,
+    first_failed_at: Option<DateTime<Utc>>,
+}
+
+impl Counter {
+    fn add(&mut self, record: &ChecksMetric) {
+        match record.value > 0.0 {
+            true => self.passes += 1,
+            false => {
+                self.fails += 1;
+                self.first_failed_at = Some(
+                    self.first_failed_at
+                        .map_or(record.time, |time| time.min(record.time)),
+                );
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Counter) {
+        self.passes += other.passes;
+        self.fails += other.fails;
+        self.first_failed_at = match (self.first_failed_at, other.first_failed_at) {
+            (Some(left), Some(right)) => Some(left.min(right)),
+            (left, right) => left.or(right),
+        };
+    }
+}
+
+pub fn pass_rate(passes: u64, fails: u64) -> f64 {
+    match passes + fails {
+        0 => 0.0,
+        total => passes as f64 / total as f64,
+    }
+}
+
+/// Results of the check per scenario.
+type ScenarioCounters = BTreeMap<String, Counter>;
+
+impl ChecksReport {
+    pub async fn from_directory(directory: &Path) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the checks")?;
+        let checks = dataset.load_metric::<ChecksMetric>().await?;
+
+        let mut groups: BTreeMap<String, BTreeMap<String, ScenarioCounters>> = BTreeMap::new();
+        for record in &checks {
+            groups
+                .entry(record.group.clone().unwrap_or_default())
+                .or_default()
+                .entry(record.check.clone().unwrap_or_default())
+                .or_default()
+                .entry(record.scenario.clone().unwrap_or_default())
+                .or_default()
+                .add(record);
+        }
+
+        let mut root = GroupChecks::default();
+        for (path, checks) in groups {
+            let group = find_or_insert_group(&mut root, &path);
+            group.checks = checks
+                .into_iter()
+                .map(|(name, scenarios)| summarize_check(name, scenarios))
+                .collect();
+        }
+        count_totals(&mut root);
+
+        Ok(ChecksReport {
+            passes: root.passes,
+            fails: root.fails,
+            pass_rate: pass_rate(root.passes, root.fails),
+            root,
+        })
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_checks(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// Rows of the report in the depth-first order along with their depth.
+    pub fn rows(&self) -> Vec<ChecksRow> {
+        let mut rows = vec![];
+        collect_rows(&self.root, 0, &mut rows);
+        rows
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let indent = match format {
+            OutputFormat::Markdown => "&nbsp;&nbsp;",
+            _ => "  ",
+        };
+        let mut table = TextTable::new(&[
+            "Group / check",
+            "Scenario",
+            "Passes",
+            "Fails",
+            "Pass rate",
+            "First failure",
+        ]);
+        for row in self.rows() {
+            table.add_row(vec![
+                format!("{}{}", indent.repeat(row.depth), row.label),
+                row.scenario,
+                row.passes.to_string(),
+                row.fails.to_string(),
+                format_rate(pass_rate(row.passes, row.fails)),
+                row.first_failed_at
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+            ]);
+        }
+
+        let totals = format!(
+            "{} checks passed, {} failed ({})",
+            self.passes,
+            self.fails,
+            format_rate(self.pass_rate)
+        );
+        match format {
+            OutputFormat::Markdown => {
+                format!("## Checks\n\n{}\n\n{}", totals, table.to_markdown())
+            }
+            _ => format!("Checks\n{}\n\n{}", totals, table.to_console()),
+        }
+    }
+}
+
+/// Flattened line of the report used by the text and HTML renderers.
+pub struct ChecksRow {
+    pub depth: usize,
+    pub is_group: bool,
+    pub label: String,
+    pub scenario: String,
+    pub passes: u64,
+    pub fails: u64,
+    pub first_failed_at: Option<DateTime<Utc>>,
+}
+
+impl GroupNode for GroupChecks {
+    type Row = ChecksRow;
+
+    fn new_group(name: String, path: String) -> Self {
+        GroupChecks {
+            name,
+            path,
+            ..GroupChecks::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn groups(&self) -> &[Self] {
+        &self.groups
+    }
+
+    fn groups_mut(&mut self) -> &mut Vec<Self> {
+        &mut self.groups
+    }
+
+    fn group_row(&self, depth: usize) -> ChecksRow {
+        ChecksRow {
+            depth,
+            is_group: true,
+            label: self.path.clone(),
+            scenario: String::new(),
+            passes: self.passes,
+            fails: self.fails,
+            first_failed_at: None,
+        }
+    }
+
+    fn push_item_rows(&self, depth: usize, rows: &mut Vec<ChecksRow>) {
+        for check in &self.checks {
+            let single_scenario = match check.scenarios.as_slice() {
+                [scenario] => Some(scenario.scenario.clone()),
+                _ => None,
+            };
+            rows.push(ChecksRow {
+                depth,
+                is_group: false,
+                label: check.name.clone(),
+                scenario: single_scenario
+                    .clone()
+                    .unwrap_or_else(|| String::from("all")),
+                passes: check.passes,
+                fails: check.fails,
+                first_failed_at: check.first_failed_at,
+            });
+            if single_scenario.is_none() {
+                for scenario in &check.scenarios {
+                    rows.push(ChecksRow {
+                        depth: depth + 1,
+                        is_group: false,
+                        label: String::new(),
+                        scenario: scenario.scenario.clone(),
+                        passes: scenario.passes,
+                        fails: scenario.fails,
+                        first_failed_at: scenario.first_failed_at,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn count_totals(group: &mut GroupChecks) {
+    let mut passes = group.checks.iter().map(|check| check.passes).sum();
+    let mut fails = group.checks.iter().map(|check| check.fails).sum();
+    for subgroup in group.groups.iter_mut() {
+        count_totals(subgroup);
+        passes += subgroup.passes;
+        fails += subgroup.fails;
+    }
+    group.passes = passes;
+    group.fails = fails;
+}
+
+fn summarize_check(name: String, scenarios: ScenarioCounters) -> CheckResult {
+    let mut total = Counter::default();
+    for counter in scenarios.values() {
+        total.merge(counter);
+    }
+
+    CheckResult {
+        name,
+        passes: total.passes,
+        fails: total.fails,
+        pass_rate: pass_rate(total.passes, total.fails),
+        first_failed_at: total.first_failed_at,
+        scenarios: scenarios
+            .into_iter()
+            .map(|(scenario, counter)| ScenarioCheckResult {
+                scenario,
+                passes: counter.passes,
+                fails: counter.fails,
+                pass_rate: pass_rate(counter.passes, counter.fails),
+                first_failed_at: counter.first_failed_at,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_directory;
+
+    fn write_checks(directory: &Path) {
+        let mut content = String::from("time,check,group,method,scenario,value\n");
+        for (time, check, group, scenario, value) in [
+            ("2024-05-01T12:00:01Z", "status is 200", "", "browse", 1),
+            ("2024-05-01T12:00:09Z", "status is 200", "", "browse", 0),
+            ("2024-05-01T12:00:05Z", "status is 200", "", "browse", 0),
+            (
+                "2024-05-01T12:00:02Z",
+                "paid",
+                "::checkout::payment",
+                "buy",
+                1,
+            ),
+            (
+                "2024-05-01T12:00:07Z",
+                "paid",
+                "::checkout::payment",
+                "buy",
+                0,
+            ),
+            (
+                "2024-05-01T12:00:03Z",
+                "paid",
+                "::checkout::payment",
+                "rush",
+                0,
+            ),
+            ("2024-05-01T12:00:04Z", "cart", "::checkout", "buy", 1),
+        ] {
+            content.push_str(&format!(
+                "{},{},{},GET,{},{}\n",
+                time, check, group, scenario, value
+            ));
+        }
+        std::fs::write(directory.join("checks.csv"), content).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checks_are_nested_under_their_groups() {
+        let directory = test_directory("checks", "nested");
+        write_checks(&directory);
+
+        let report = ChecksReport::from_directory(&directory).await.unwrap();
+        assert_eq!((report.passes, report.fails), (3, 4));
+
+        let root = &report.root;
+        assert_eq!(root.checks[0].name, "status is 200");
+        let checkout = &root.groups[0];
+        assert_eq!(checkout.path, "::checkout");
+        // Totals of a group include its subgroups
+        assert_eq!((checkout.passes, checkout.fails), (2, 2));
+        let payment = &checkout.groups[0];
+        assert_eq!(payment.path, "::checkout::payment");
+        assert_eq!((payment.passes, payment.fails), (1, 2));
+
+        let rows = report.rows();
+        let rows: Vec<(usize, &str, &str)> = rows
+            .iter()
+            .map(|row| (row.depth, row.label.as_str(), row.scenario.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (0, "status is 200", "browse"),
+                (0, "::checkout", ""),
+                (1, "cart", "buy"),
+                (1, "::checkout::payment", ""),
+                (2, "paid", "all"),
+                (3, "", "buy"),
+                (3, "", "rush"),
+            ]
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_first_failure_is_the_earliest_one() {
+        let directory = test_directory("checks", "first_failure");
+        write_checks(&directory);
+
+        let report = ChecksReport::from_directory(&directory).await.unwrap();
+        let time = |value: &str| Some(value.parse::<DateTime<Utc>>().unwrap());
+
+        // Out of order in the file
+        let status = &report.root.checks[0];
+        assert_eq!(status.first_failed_at, time("2024-05-01T12:00:05Z"));
+
+        let paid = &report.root.groups[0].groups[0].checks[0];
+        assert_eq!(paid.first_failed_at, time("2024-05-01T12:00:03Z"));
+        let scenarios: Vec<(&str, Option<DateTime<Utc>>)> = paid
+            .scenarios
+            .iter()
+            .map(|scenario| (scenario.scenario.as_str(), scenario.first_failed_at))
+            .collect();
+        assert_eq!(
+            scenarios,
+            [
+                ("buy", time("2024-05-01T12:00:07Z")),
+                ("rush", time("2024-05-01T12:00:03Z")),
+            ]
+        );
+
+        // Checks that never failed have no first failure
+        let cart = &report.root.groups[0].checks[0];
+        assert_eq!(cart.first_failed_at, None);
+        assert_eq!(cart.pass_rate, 1.0);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn pass_rates_of_no_checks_are_zero() {
+        assert_eq!(pass_rate(0, 0), 0.0);
+        assert_eq!(pass_rate(3, 1), 0.75);
+    }
+}
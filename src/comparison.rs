@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::html::{format_duration, format_p_value, format_rate, render_comparison};
+use crate::layout::ExportedRun;
+use crate::metrics::HttpReqDurationMetric;
+use crate::output::OutputFormat;
+use crate::statistics::{bootstrap_percentile_differences, mann_whitney_u};
+use crate::summary::{endpoint_method, endpoint_name, EndpointSummary, Summary};
+use crate::table::TextTable;
+
+pub const DEFAULT_SIGNIFICANCE_LEVEL: f64 = 0.05;
+pub const DEFAULT_RESAMPLES: usize = 1000;
+
+/// Fixed seed keeps bootstrap intervals the same between invocations.
+const BOOTSTRAP_SEED: u64 = 6;
+
+/// Differences between two runs, where the base run is the reference point.
+#[derive(Serialize, Debug, Clone)]
+pub struct Comparison {
+    pub base: String,
+    pub target: String,
+    pub requests: Delta,
+    pub error_rate: Delta,
+    pub p95_duration: Delta,
+    pub duration_test: SignificanceTest,
+    pub endpoints: Vec<EndpointComparison>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EndpointComparison {
+    pub method: String,
+    pub name: String,
+    pub requests: Delta,
+    pub error_rate: Delta,
+    pub p95_duration: Delta,
+    pub duration_test: SignificanceTest,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Improved,
+    Unchanged,
+    Regressed,
+}
+
+/// Percentiles of request durations which get a confidence interval of
+/// their difference.
+pub const COMPARED_PERCENTILES: &[f64] = &[50.0, 90.0, 95.0, 99.0];
+
+/// Statistical tests of request durations of both runs. A change is reported
+/// only when it's unlikely to be caused by noise: either the durations
+/// shifted as a whole (Mann–Whitney U test), or the confidence intervals of
+/// the percentile differences don't include zero, and the tests don't
+/// contradict each other.
+#[derive(Serialize, Debug, Clone)]
+pub struct SignificanceTest {
+    pub verdict: Verdict,
+    /// Two-sided p-value of the Mann–Whitney U test, i.e. the probability of
+    /// a shift at least this large when durations of the runs don't differ.
+    pub p_value: Option<f64>,
+    /// Whether the p-value is below the significance level.
+    pub significant: bool,
+    /// Bootstrap confidence intervals of the percentile differences (target
+    /// minus base) in milliseconds, one for each of the compared percentiles.
+    pub percentile_differences: Vec<PercentileDifference>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct PercentileDifference {
+    pub percentile: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl PercentileDifference {
+    /// Sign of the change when the interval doesn't include zero.
+    fn direction(&self) -> f64 {
+        match (self.low > 0.0, self.high < 0.0) {
+            (true, _) => 1.0,
+            (_, true) => -1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComparisonOptions {
+    pub significance_level: f64,
+    pub resamples: usize,
+}
+
+impl Default for ComparisonOptions {
+    fn default() -> Self {
+        ComparisonOptions {
+            significance_level: DEFAULT_SIGNIFICANCE_LEVEL,
+            resamples: DEFAULT_RESAMPLES,
+        }
+    }
+}
+
+/// Parses the `--significance-level` option, which has to be within (0, 1).
+pub fn parse_significance_level(value: &str) -> std::result::Result<f64, String> {
+    let level: f64 = value
+        .parse()
+        .map_err(|_| format!("`{}` isn't a number", value))?;
+    match level > 0.0 && level < 1.0 {
+        true => Ok(level),
+        false => Err(String::from(
+            "the significance level has to be greater than 0 and less than 1",
+        )),
+    }
+}
+
+impl SignificanceTest {
+    pub fn new(base: &[f64], target: &[f64], options: &ComparisonOptions) -> Self {
+        let mann_whitney = mann_whitney_u(base, target);
+        let mut rng = SmallRng::seed_from_u64(BOOTSTRAP_SEED);
+        let percentile_differences: Vec<PercentileDifference> = bootstrap_percentile_differences(
+            base,
+            target,
+            COMPARED_PERCENTILES,
+            1.0 - options.significance_level,
+            options.resamples,
+            &mut rng,
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .zip(COMPARED_PERCENTILES)
+        .map(|((low, high), percentile)| PercentileDifference {
+            percentile: *percentile,
+            low,
+            high,
+        })
+        .collect();
+
+        let significant =
+            mann_whitney.is_some_and(|test| test.p_value < options.significance_level);
+        let shift = match mann_whitney {
+            Some(test) if significant => test.z.signum(),
+            _ => 0.0,
+        };
+        // Percentiles changed in opposite directions don't tell anything
+        let directions: Vec<f64> = percentile_differences
+            .iter()
+            .map(PercentileDifference::direction)
+            .filter(|direction| *direction != 0.0)
+            .collect();
+        let tail_shift = match directions.first() {
+            Some(first) if directions.iter().all(|direction| direction == first) => *first,
+            _ => 0.0,
+        };
+        let verdict = match (shift, tail_shift) {
+            (shift, tail_shift) if shift * tail_shift < 0.0 => Verdict::Unchanged,
+            (shift, tail_shift) if shift + tail_shift > 0.0 => Verdict::Regressed,
+            (shift, tail_shift) if shift + tail_shift < 0.0 => Verdict::Improved,
+            _ => Verdict::Unchanged,
+        };
+
+        SignificanceTest {
+            verdict,
+            p_value: mann_whitney.map(|test| test.p_value),
+            significant,
+            percentile_differences,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Delta {
+    pub base: f64,
+    pub target: f64,
+    /// Relative change in percent. Not defined when the base value is zero.
+    pub change: Option<f64>,
+}
+
+impl Delta {
+    pub fn new(base: f64, target: f64) -> Self {
+        let change = match base != 0.0 {
+            true => Some((target - base) / base * 100.0),
+            false => None,
+        };
+        Delta {
+            base,
+            target,
+            change,
+        }
+    }
+}
+
+impl Comparison {
+    /// Compares summaries of the runs and tests the raw request durations, so
+    /// runs exported from Prometheus are refused.
+    /// The tests run on a blocking thread, since bootstrapping large runs
+    /// takes a while.
+    pub async fn load(
+        base: &ExportedRun,
+        target: &ExportedRun,
+        options: &ComparisonOptions,
+    ) -> Result<Self> {
+        for run in [base, target] {
+            run.manifest
+                .source
+                .ensure_not_aggregated("the request durations")?;
+        }
+        let base_summary = Summary::load(&base.path).await?;
+        let target_summary = Summary::load(&target.path).await?;
+        let base_durations = Dataset::open(&base.path)
+            .await?
+            .load_metric::<HttpReqDurationMetric>()
+            .await?;
+        let target_durations = Dataset::open(&target.path)
+            .await?
+            .load_metric::<HttpReqDurationMetric>()
+            .await?;
+
+        let requests = Delta::new(base_summary.requests as f64, target_summary.requests as f64);
+        let error_rate = Delta::new(base_summary.error_rate, target_summary.error_rate);
+        let p95_duration = Delta::new(
+            base_summary.http_req_duration.p95,
+            target_summary.http_req_duration.p95,
+        );
+        let options = *options;
+        let (duration_test, endpoints) = spawn_blocking(move || {
+            let duration_test = SignificanceTest::new(
+                &duration_values(&base_durations),
+                &duration_values(&target_durations),
+                &options,
+            );
+            let endpoints = compare_endpoints(
+                &base_summary.endpoints,
+                &group_durations(&base_durations),
+                &target_summary.endpoints,
+                &group_durations(&target_durations),
+                &options,
+            );
+            (duration_test, endpoints)
+        })
+        .await
+        .map_err(std::io::Error::from)?;
+
+        Ok(Comparison {
+            base: base.name.clone(),
+            target: target.name.clone(),
+            requests,
+            error_rate,
+            p95_duration,
+            duration_test,
+            endpoints,
+        })
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_comparison(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut table = TextTable::new(&[
+            "Endpoint",
+            "Requests",
+            "Error rate",
+            "p95 (ms)",
+            "p95 change",
+            "Verdict",
+            "p-value",
+            "Significant",
+            "Changed percentiles (ms)",
+        ]);
+        table.add_row(comparison_row(
+            "all requests",
+            &self.requests,
+            &self.error_rate,
+            &self.p95_duration,
+            &self.duration_test,
+        ));
+        for endpoint in &self.endpoints {
+            table.add_row(comparison_row(
+                &format!("{} {}", endpoint.method, endpoint.name),
+                &endpoint.requests,
+                &endpoint.error_rate,
+                &endpoint.p95_duration,
+                &endpoint.duration_test,
+            ));
+        }
+
+        let title = format!("{} vs {}", self.base, self.target);
+        match format {
+            OutputFormat::Markdown => format!("## {}\n\n{}", title, table.to_markdown()),
+            _ => format!("{}\n{}", title, table.to_console()),
+        }
+    }
+}
+
+fn comparison_row(
+    name: &str,
+    requests: &Delta,
+    error_rate: &Delta,
+    p95_duration: &Delta,
+    duration_test: &SignificanceTest,
+) -> Vec<String> {
+    vec![
+        name.to_string(),
+        format!("{:.0} -> {:.0}", requests.base, requests.target),
+        format!(
+            "{} -> {}",
+            format_rate(error_rate.base),
+            format_rate(error_rate.target)
+        ),
+        format!(
+            "{} -> {}",
+            format_duration(p95_duration.base),
+            format_duration(p95_duration.target)
+        ),
+        p95_duration
+            .change
+            .map(|change| format!("{:+.1}%", change))
+            .unwrap_or_else(|| String::from("n/a")),
+        format!("{:?}", duration_test.verdict).to_lowercase(),
+        duration_test
+            .p_value
+            .map(format_p_value)
+            .unwrap_or_else(|| String::from("n/a")),
+        match duration_test.significant {
+            true => String::from("yes"),
+            false => String::from("no"),
+        },
+        changed_percentiles(duration_test),
+    ]
+}
+
+/// Lists the percentiles with confidence intervals that don't include zero.
+fn changed_percentiles(duration_test: &SignificanceTest) -> String {
+    let changed: Vec<String> = duration_test
+        .percentile_differences
+        .iter()
+        .filter(|difference| difference.direction() != 0.0)
+        .map(|difference| {
+            format!(
+                "p{} [{}, {}]",
+                difference.percentile,
+                format_duration(difference.low),
+                format_duration(difference.high)
+            )
+        })
+        .collect();
+    match changed.is_empty() {
+        true => String::from("none"),
+        false => changed.join(", "),
+    }
+}
+
+fn duration_values(records: &[HttpReqDurationMetric]) -> Vec<f64> {
+    records.iter().map(|record| record.value).collect()
+}
+
+/// Groups request durations by endpoints, in the same way as the summary.
+fn group_durations(records: &[HttpReqDurationMetric]) -> BTreeMap<(String, String), Vec<f64>> {
+    let mut endpoints: BTreeMap<(String, String), Vec<f64>> = BTreeMap::new();
+    for record in records {
+        let key = (endpoint_method(record), endpoint_name(record));
+        endpoints.entry(key).or_default().push(record.value);
+    }
+    endpoints
+}
+
+type EndpointPair<'a> = (Option<&'a EndpointSummary>, Option<&'a EndpointSummary>);
+
+/// Matches endpoints of both runs by the method and name. An endpoint missing
+/// in one of the runs is compared against zeros.
+fn compare_endpoints(
+    base: &[EndpointSummary],
+    base_durations: &BTreeMap<(String, String), Vec<f64>>,
+    target: &[EndpointSummary],
+    target_durations: &BTreeMap<(String, String), Vec<f64>>,
+    options: &ComparisonOptions,
+) -> Vec<EndpointComparison> {
+    let no_durations = vec![];
+    let mut endpoints: BTreeMap<(String, String), EndpointPair> = BTreeMap::new();
+    for endpoint in base {
+        let key = (endpoint.method.clone(), endpoint.name.clone());
+        endpoints.entry(key).or_default().0 = Some(endpoint);
+    }
+    for endpoint in target {
+        let key = (endpoint.method.clone(), endpoint.name.clone());
+        endpoints.entry(key).or_default().1 = Some(endpoint);
+    }
+
+    endpoints
+        .into_iter()
+        .map(|((method, name), (base, target))| {
+            let key = (method, name);
+            let duration_test = SignificanceTest::new(
+                base_durations.get(&key).unwrap_or(&no_durations),
+                target_durations.get(&key).unwrap_or(&no_durations),
+                options,
+            );
+            let (method, name) = key;
+            EndpointComparison {
+                method,
+                name,
+                requests: Delta::new(
+                    base.map(|endpoint| endpoint.requests as f64).unwrap_or(0.0),
+                    target
+                        .map(|endpoint| endpoint.requests as f64)
+                        .unwrap_or(0.0),
+                ),
+                error_rate: Delta::new(
+                    base.map(|endpoint| endpoint.error_rate).unwrap_or(0.0),
+                    target.map(|endpoint| endpoint.error_rate).unwrap_or(0.0),
+                ),
+                p95_duration: Delta::new(
+                    base.map(|endpoint| endpoint.http_req_duration.p95)
+                        .unwrap_or(0.0),
+                    target
+                        .map(|endpoint| endpoint.http_req_duration.p95)
+                        .unwrap_or(0.0),
+                ),
+                duration_test,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn significance_level_is_within_zero_and_one() {
+        assert_eq!(parse_significance_level("0.01"), Ok(0.01));
+        assert!(parse_significance_level("0").is_err());
+        assert!(parse_significance_level("1").is_err());
+        assert!(parse_significance_level("-0.5").is_err());
+        assert!(parse_significance_level("NaN").is_err());
+        assert!(parse_significance_level("five").is_err());
+    }
+
+    #[test]
+    fn slower_target_is_a_regression() {
+        let base: Vec<f64> = (0..300).map(|value| f64::from(value % 100)).collect();
+        let target: Vec<f64> = base.iter().map(|value| value * 1.5 + 20.0).collect();
+        let test = SignificanceTest::new(&base, &target, &ComparisonOptions::default());
+
+        assert_eq!(test.verdict, Verdict::Regressed);
+        assert!(test.significant);
+        assert!(test.p_value.unwrap() < 0.0001);
+        let percentiles: Vec<f64> = test
+            .percentile_differences
+            .iter()
+            .map(|difference| difference.percentile)
+            .collect();
+        assert_eq!(percentiles, COMPARED_PERCENTILES);
+        assert!(test
+            .percentile_differences
+            .iter()
+            .all(|difference| difference.low > 0.0));
+    }
+
+    #[test]
+    fn same_durations_are_unchanged() {
+        let durations: Vec<f64> = (0..300).map(|value| f64::from(value % 100)).collect();
+        let test = SignificanceTest::new(&durations, &durations, &ComparisonOptions::default());
+
+        assert_eq!(test.verdict, Verdict::Unchanged);
+        assert!(!test.significant);
+        assert!(test.p_value.unwrap() > 0.9999);
+    }
+
+    #[test]
+    fn missing_durations_are_not_tested() {
+        let test = SignificanceTest::new(&[], &[1.0, 2.0], &ComparisonOptions::default());
+        assert_eq!(test.verdict, Verdict::Unchanged);
+        assert_eq!(test.p_value, None);
+        assert!(test.percentile_differences.is_empty());
+    }
+}
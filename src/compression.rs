@@ -0,0 +1,84 @@
+use std::str::FromStr;
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+
+pub type OutputWriter = Box<dyn AsyncWrite + Send + Unpin>;
+pub type InputReader = Box<dyn AsyncRead + Send + Unpin>;
+
+pub const SUPPORTED_COMPRESSIONS: &[Compression] = &[Compression::Gzip, Compression::Zstd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+
+    /// Detects the compression by the file extension.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        SUPPORTED_COMPRESSIONS
+            .iter()
+            .find(|compression| filename.ends_with(&format!(".{}", compression.extension())))
+            .copied()
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unsupported compression: {}", value)),
+        }
+    }
+}
+
+/// Appends the compression extension (if any) to the file name.
+pub fn compressed_filename(filename: &str, compression: Option<Compression>) -> String {
+    match compression {
+        Some(compression) => format!("{}.{}", filename, compression.extension()),
+        None => filename.to_string(),
+    }
+}
+
+/// Wraps the file into an encoder, so that data is compressed on the fly while
+/// writing. The writer must be shut down for flushing the compression trailer.
+pub fn create_writer(file: File, compression: Option<Compression>) -> OutputWriter {
+    match compression {
+        Some(Compression::Gzip) => Box::new(GzipEncoder::new(file)),
+        Some(Compression::Zstd) => Box::new(ZstdEncoder::new(file)),
+        None => Box::new(file),
+    }
+}
+
+/// Wraps the file into a decoder, so that compressed files can be read in the
+/// same way as plain ones. Files appended by several exports consist of many
+/// compressed members, so all of them are decoded.
+pub fn create_reader(file: File, compression: Option<Compression>) -> InputReader {
+    let buffered_file = BufReader::new(file);
+    match compression {
+        Some(Compression::Gzip) => {
+            let mut decoder = GzipDecoder::new(buffered_file);
+            decoder.multiple_members(true);
+            Box::new(decoder)
+        }
+        Some(Compression::Zstd) => {
+            let mut decoder = ZstdDecoder::new(buffered_file);
+            decoder.multiple_members(true);
+            Box::new(decoder)
+        }
+        None => Box::new(buffered_file),
+    }
+}
@@ -0,0 +1,456 @@
+use std::path::{Path, PathBuf};
+
+use csv_async::{
+    AsyncDeserializer, AsyncReader, DeserializeRecordsIntoStream, StringRecord,
+    StringRecordsIntoStream,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::fs::{metadata, File};
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio_stream::StreamExt;
+
+use crate::archive::ensure_not_archive;
+use crate::compression::{
+    compressed_filename, create_reader, Compression, InputReader, SUPPORTED_COMPRESSIONS,
+};
+use crate::error::{Error, Result};
+use crate::manifest::{Manifest, MetricEntry};
+use crate::metrics::K6Metric;
+use crate::source::Row;
+
+/// Extensions of the exported files which can be read back, in the order of
+/// preference.
+const READABLE_EXTENSIONS: &[&str] = &["csv", "jsonl"];
+
+/// Metrics exported into a directory. The manifest is read once when the
+/// dataset is opened, so loading many metrics doesn't parse it again.
+pub struct Dataset {
+    directory: PathBuf,
+    manifest: Option<Manifest>,
+}
+
+impl Dataset {
+    /// Opens the exported directory. Missing metric files are fine, but not
+    /// a missing directory. Archives have to be extracted first.
+    pub async fn open(directory: &Path) -> Result<Self> {
+        ensure_not_archive(directory)?;
+        metadata(directory).await?;
+        let manifest = match Manifest::exists(directory) {
+            true => Some(Manifest::load(directory).await?),
+            false => None,
+        };
+
+        Ok(Dataset {
+            directory: directory.to_path_buf(),
+            manifest,
+        })
+    }
+
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Fails when the run was exported from a source with aggregates only.
+    /// Runs without a manifest are taken for raw data points.
+    pub fn ensure_not_aggregated(&self, data: &str) -> Result<()> {
+        match &self.manifest {
+            Some(manifest) => manifest.source.ensure_not_aggregated(data),
+            None => Ok(()),
+        }
+    }
+
+    /// Finds the exported file of the metric. The file listed in the manifest
+    /// is used, so files left by other exports are ignored. Without a
+    /// manifest, CSV files are preferred over JSON ones, and compressed files
+    /// are picked up as well.
+    pub fn find_metric_file<T: K6Metric>(&self) -> Result<Option<PathBuf>> {
+        if let Some(manifest) = &self.manifest {
+            return match manifest
+                .metrics
+                .iter()
+                .find(|entry| entry.measurement == T::metric_table_name())
+            {
+                Some(entry) => Ok(Some(self.directory.join(readable_file(entry)?))),
+                None => Ok(None),
+            };
+        }
+
+        let mut compressions = vec![None];
+        compressions.extend(SUPPORTED_COMPRESSIONS.iter().copied().map(Some));
+
+        Ok(READABLE_EXTENSIONS
+            .iter()
+            .map(|extension| format!("{}.{}", T::metric_table_name(), extension))
+            .flat_map(|filename| {
+                compressions
+                    .iter()
+                    .map(move |compression| compressed_filename(&filename, *compression))
+            })
+            .map(|filename| self.directory.join(filename))
+            .find(|path| path.exists()))
+    }
+
+    /// Opens the exported file of the metric for reading it record by
+    /// record. Returns `None` when the metric wasn't exported.
+    pub async fn open_metric<T>(&self) -> Result<Option<MetricReader<T>>>
+    where
+        T: K6Metric + DeserializeOwned + 'static,
+    {
+        match self.find_metric_file::<T>()? {
+            Some(filepath) => Ok(Some(MetricReader::open(&filepath).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads all exported records of the metric. Returns an empty list when
+    /// the metric wasn't exported.
+    pub async fn load_metric<T>(&self) -> Result<Vec<T>>
+    where
+        T: K6Metric + DeserializeOwned + 'static,
+    {
+        let mut result = vec![];
+        if let Some(mut reader) = self.open_metric::<T>().await? {
+            while let Some(record) = reader.next_record().await? {
+                result.push(record);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Reads the exported records of a metric one by one, so big files don't
+/// have to fit into memory.
+pub struct MetricReader<T> {
+    records: ExportedRecords<T>,
+}
+
+enum ExportedRecords<T> {
+    Csv(DeserializeRecordsIntoStream<'static, InputReader, T>),
+    JsonLines(Lines<BufReader<InputReader>>),
+}
+
+impl<T> MetricReader<T>
+where
+    T: K6Metric + DeserializeOwned + 'static,
+{
+    async fn open(filepath: &Path) -> Result<Self> {
+        let filename = filepath.to_string_lossy().to_string();
+        let input_file = File::open(filepath).await?;
+        let input_reader = create_reader(input_file, Compression::from_filename(&filename));
+        let records = match readable_extension(&filename) {
+            Some("jsonl") => ExportedRecords::JsonLines(BufReader::new(input_reader).lines()),
+            _ => ExportedRecords::Csv(
+                AsyncDeserializer::from_reader(input_reader).into_deserialize::<T>(),
+            ),
+        };
+
+        Ok(MetricReader { records })
+    }
+
+    /// Returns the next record, or `None` at the end of the file.
+    pub async fn next_record(&mut self) -> Result<Option<T>> {
+        let deserialization_error = |reason: String| Error::Deserialization {
+            measurement: T::metric_table_name().to_string(),
+            reason,
+        };
+        match &mut self.records {
+            ExportedRecords::Csv(records) => match records.next().await {
+                Some(record) => record
+                    .map(Some)
+                    .map_err(|error| deserialization_error(error.to_string())),
+                None => Ok(None),
+            },
+            ExportedRecords::JsonLines(lines) => loop {
+                let line = match lines.next_line().await? {
+                    Some(line) if line.is_empty() => continue,
+                    Some(line) => line,
+                    None => return Ok(None),
+                };
+                return serde_json::from_str(&line)
+                    .map(Some)
+                    .map_err(|error| deserialization_error(error.to_string()));
+            },
+        }
+    }
+
+    /// Returns up to `size` next records. The list is empty at the end of
+    /// the file.
+    pub async fn next_batch(&mut self, size: usize) -> Result<Vec<T>> {
+        let mut batch = Vec::with_capacity(size);
+        while batch.len() < size {
+            match self.next_record().await? {
+                Some(record) => batch.push(record),
+                None => break,
+            }
+        }
+
+        Ok(batch)
+    }
+}
+
+/// Reads the exported file of a measurement listed in the manifest without
+/// knowing the type of the metric. Values of CSV files are kept as strings.
+pub async fn load_rows(directory: &Path, entry: &MetricEntry) -> Result<Vec<Row>> {
+    let mut reader = RowReader::open(directory, entry).await?;
+    let mut result = vec![];
+    while let Some(row) = reader.next_row().await? {
+        result.push(row);
+    }
+
+    Ok(result)
+}
+
+/// Reads the exported file of a measurement row by row, so big files don't
+/// have to fit into memory. Values of CSV files are kept as strings.
+pub struct RowReader {
+    measurement: String,
+    rows: ExportedRows,
+}
+
+enum ExportedRows {
+    Csv {
+        headers: StringRecord,
+        records: StringRecordsIntoStream<'static, InputReader>,
+    },
+    JsonLines(Lines<BufReader<InputReader>>),
+}
+
+impl RowReader {
+    pub async fn open(directory: &Path, entry: &MetricEntry) -> Result<Self> {
+        let filename = readable_file(entry)?;
+        let input_file = File::open(directory.join(filename)).await?;
+        let input_reader = create_reader(input_file, Compression::from_filename(filename));
+        let rows = match readable_extension(filename) {
+            Some("jsonl") => ExportedRows::JsonLines(BufReader::new(input_reader).lines()),
+            _ => {
+                let mut csv_reader = AsyncReader::from_reader(input_reader);
+                ExportedRows::Csv {
+                    headers: csv_reader.headers().await?.clone(),
+                    records: csv_reader.into_records(),
+                }
+            }
+        };
+
+        Ok(RowReader {
+            measurement: entry.measurement.clone(),
+            rows,
+        })
+    }
+
+    /// Returns the next row, or `None` at the end of the file.
+    pub async fn next_row(&mut self) -> Result<Option<Row>> {
+        match &mut self.rows {
+            ExportedRows::Csv { headers, records } => match records.next().await {
+                Some(record) => Ok(Some(
+                    headers
+                        .iter()
+                        .zip(record?.iter())
+                        .map(|(header, value)| {
+                            (header.to_string(), Value::String(value.to_string()))
+                        })
+                        .collect(),
+                )),
+                None => Ok(None),
+            },
+            ExportedRows::JsonLines(lines) => loop {
+                let line = match lines.next_line().await? {
+                    Some(line) if line.is_empty() => continue,
+                    Some(line) => line,
+                    None => return Ok(None),
+                };
+                return serde_json::from_str(&line).map(Some).map_err(|error| {
+                    Error::Deserialization {
+                        measurement: self.measurement.clone(),
+                        reason: error.to_string(),
+                    }
+                });
+            },
+        }
+    }
+}
+
+/// Picks the file of the manifest entry which the reports can read.
+fn readable_file(entry: &MetricEntry) -> Result<&str> {
+    std::iter::once(&entry.file)
+        .chain(entry.extra_files.iter().map(|extra| &extra.file))
+        .find(|file| readable_extension(file).is_some())
+        .map(String::as_str)
+        .ok_or_else(|| Error::Deserialization {
+            measurement: entry.measurement.clone(),
+            reason: String::from("neither a CSV nor a JSON file was exported"),
+        })
+}
+
+/// Extension of a readable file with the compression extension stripped.
+fn readable_extension(filename: &str) -> Option<&'static str> {
+    let filename = match Compression::from_filename(filename) {
+        Some(compression) => filename
+            .strip_suffix(&format!(".{}", compression.extension()))
+            .unwrap_or(filename),
+        None => filename,
+    };
+    READABLE_EXTENSIONS
+        .iter()
+        .find(|extension| filename.ends_with(&format!(".{}", extension)))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::layout::RunMetadata;
+    use crate::manifest::{Source, TimeWindow};
+    use crate::metrics::{VusMetric, WsSessionsMetric};
+    use crate::test_support::test_directory;
+
+    const VUS_CSV: &str = "time,value\n2024-05-01T12:00:00Z,5\n2024-05-01T12:00:01Z,10\n";
+
+    fn vus_entry(file: &str) -> MetricEntry {
+        MetricEntry {
+            measurement: String::from("vus"),
+            file: file.to_string(),
+            query: String::from("SELECT time, value FROM vus"),
+            rows: 2,
+            first_timestamp: None,
+            last_timestamp: None,
+            sha256: String::new(),
+            extra_files: vec![],
+        }
+    }
+
+    async fn save_manifest(directory: &Path, metrics: Vec<MetricEntry>) {
+        let mut manifest = Manifest::new(
+            RunMetadata::new(Utc::now(), &None, &None),
+            Source {
+                kind: String::from("influxdb"),
+                url: String::from("http://localhost:8086"),
+                database: None,
+                retention_policy: None,
+            },
+            TimeWindow {
+                from: None,
+                to: Utc::now(),
+                trimmed: None,
+            },
+            vec![],
+        );
+        manifest.metrics = metrics;
+        manifest.save(directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_file_listed_in_the_manifest_is_loaded() {
+        let directory = test_directory("dataset", "manifest");
+        // Left by an earlier export without compression
+        std::fs::write(directory.join("vus.csv"), "time,value\nbroken,row\n").unwrap();
+        std::fs::write(directory.join("ws_sessions.csv"), "time,value\n").unwrap();
+        save_manifest(&directory, vec![vus_entry("vus.jsonl")]).await;
+        std::fs::write(
+            directory.join("vus.jsonl"),
+            "{\"time\":\"2024-05-01T12:00:00Z\",\"value\":5.0}\n",
+        )
+        .unwrap();
+
+        let dataset = Dataset::open(&directory).await.unwrap();
+        let vus = dataset.load_metric::<VusMetric>().await.unwrap();
+        assert_eq!(vus.len(), 1);
+        assert_eq!(vus[0].value, 5.0);
+        assert!(dataset
+            .find_metric_file::<WsSessionsMetric>()
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_are_searched_for_without_a_manifest() {
+        let directory = test_directory("dataset", "search");
+        std::fs::write(directory.join("vus.csv"), VUS_CSV).unwrap();
+
+        let dataset = Dataset::open(&directory).await.unwrap();
+        let vus = dataset.load_metric::<VusMetric>().await.unwrap();
+        let values: Vec<f64> = vus.iter().map(|record| record.value).collect();
+        assert_eq!(values, [5.0, 10.0]);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn records_are_read_in_batches() {
+        let directory = test_directory("dataset", "batches");
+        std::fs::write(
+            directory.join("vus.jsonl"),
+            "{\"time\":\"2024-05-01T12:00:00Z\",\"value\":5.0}\n\n\
+             {\"time\":\"2024-05-01T12:00:01Z\",\"value\":10.0}\n\
+             {\"time\":\"2024-05-01T12:00:02Z\",\"value\":15.0}\n",
+        )
+        .unwrap();
+
+        let dataset = Dataset::open(&directory).await.unwrap();
+        let mut reader = dataset.open_metric::<VusMetric>().await.unwrap().unwrap();
+        let sizes = [
+            reader.next_batch(2).await.unwrap().len(),
+            reader.next_batch(2).await.unwrap().len(),
+            reader.next_batch(2).await.unwrap().len(),
+        ];
+        assert_eq!(sizes, [2, 1, 0]);
+        assert!(dataset
+            .open_metric::<WsSessionsMetric>()
+            .await
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rows_are_read_from_a_readable_file() {
+        let directory = test_directory("dataset", "rows");
+        std::fs::write(directory.join("vus.csv"), VUS_CSV).unwrap();
+        let mut entry = vus_entry("vus.parquet");
+        entry.extra_files.push(crate::manifest::ExportedFile {
+            format: String::from("csv"),
+            file: String::from("vus.csv"),
+            sha256: String::new(),
+        });
+
+        let rows = load_rows(&directory, &entry).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["value"], Value::String(String::from("10")));
+        assert!(load_rows(&directory, &vus_entry("vus.parquet"))
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn archives_and_missing_directories_are_rejected() {
+        let directory = test_directory("dataset", "archive");
+        let archive_path = directory.join("run.tar.zst");
+        std::fs::write(&archive_path, "").unwrap();
+
+        assert!(matches!(
+            Dataset::open(&archive_path).await,
+            Err(Error::ArchiveInput(_))
+        ));
+        assert!(matches!(
+            Dataset::open(&directory.join("missing")).await,
+            Err(Error::Io(_))
+        ));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn compression_extensions_are_stripped() {
+        assert_eq!(readable_extension("vus.csv.gz"), Some("csv"));
+        assert_eq!(readable_extension("vus.jsonl.zst"), Some("jsonl"));
+        assert_eq!(readable_extension("vus.csv.parquet"), None);
+    }
+}
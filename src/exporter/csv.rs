@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use csv_async::{AsyncWriter, AsyncWriterBuilder};
+use serde_json::Value;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use crate::compression::{compressed_filename, create_writer, Compression, OutputWriter};
+use crate::error::Result;
+use crate::exporter::{Exporter, MetricSink};
+use crate::metrics::Column;
+use crate::source::Row;
+
+/// Writes each measurement into a CSV file with a header, which is the format
+/// read back by the reports.
+pub struct CsvExporter {
+    compression: Option<Compression>,
+}
+
+impl CsvExporter {
+    pub fn new(compression: Option<Compression>) -> Self {
+        CsvExporter { compression }
+    }
+}
+
+struct CsvSink {
+    filename: String,
+    columns: &'static [Column],
+    writer: AsyncWriter<OutputWriter>,
+}
+
+#[async_trait]
+impl Exporter for CsvExporter {
+    fn format(&self) -> &str {
+        "csv"
+    }
+
+    async fn open(
+        &self,
+        directory: &Path,
+        measurement: &str,
+        columns: &'static [Column],
+        append_to: Option<&str>,
+    ) -> Result<Box<dyn MetricSink>> {
+        let filename = match append_to {
+            Some(filename) => filename.to_string(),
+            None => compressed_filename(&format!("{0}.csv", measurement), self.compression),
+        };
+        let output_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append_to.is_some())
+            .truncate(append_to.is_none())
+            .open(directory.join(&filename))
+            .await?;
+        let output_writer = create_writer(output_file, Compression::from_filename(&filename));
+        let mut writer = AsyncWriterBuilder::new()
+            .has_headers(false)
+            .create_writer(output_writer);
+        if append_to.is_none() {
+            let header: Vec<&str> = columns.iter().map(|column| column.name).collect();
+            writer.write_record(&header).await?;
+        }
+
+        Ok(Box::new(CsvSink {
+            filename,
+            columns,
+            writer,
+        }))
+    }
+}
+
+#[async_trait]
+impl MetricSink for CsvSink {
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<()> {
+        for row in rows {
+            let record: Vec<String> = self
+                .columns
+                .iter()
+                .map(|column| format_value(row.get(column.name)))
+                .collect();
+            self.writer.write_record(&record).await?;
+        }
+        Ok(())
+    }
+
+    async fn finalize(self: Box<Self>) -> Result<String> {
+        let mut output_writer = self.writer.into_inner().await?;
+        output_writer.shutdown().await?;
+        Ok(self.filename)
+    }
+}
+
+fn format_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
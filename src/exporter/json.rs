@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use crate::compression::{compressed_filename, create_writer, Compression, OutputWriter};
+use crate::error::Result;
+use crate::exporter::{Exporter, MetricSink};
+use crate::metrics::Column;
+use crate::source::Row;
+
+/// Writes each measurement into a file with a JSON object per line.
+pub struct JsonExporter {
+    compression: Option<Compression>,
+}
+
+impl JsonExporter {
+    pub fn new(compression: Option<Compression>) -> Self {
+        JsonExporter { compression }
+    }
+}
+
+struct JsonSink {
+    filename: String,
+    writer: OutputWriter,
+}
+
+#[async_trait]
+impl Exporter for JsonExporter {
+    fn format(&self) -> &str {
+        "json"
+    }
+
+    async fn open(
+        &self,
+        directory: &Path,
+        measurement: &str,
+        _columns: &'static [Column],
+        append_to: Option<&str>,
+    ) -> Result<Box<dyn MetricSink>> {
+        let filename = match append_to {
+            Some(filename) => filename.to_string(),
+            None => compressed_filename(&format!("{0}.jsonl", measurement), self.compression),
+        };
+        let output_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append_to.is_some())
+            .truncate(append_to.is_none())
+            .open(directory.join(&filename))
+            .await?;
+        let writer = create_writer(output_file, Compression::from_filename(&filename));
+
+        Ok(Box::new(JsonSink { filename, writer }))
+    }
+}
+
+#[async_trait]
+impl MetricSink for JsonSink {
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<()> {
+        for row in rows {
+            let mut line = serde_json::to_vec(row)?;
+            line.push(b'\n');
+            self.writer.write_all(&line).await?;
+        }
+        Ok(())
+    }
+
+    async fn finalize(mut self: Box<Self>) -> Result<String> {
+        self.writer.shutdown().await?;
+        Ok(self.filename)
+    }
+}
@@ -0,0 +1,186 @@
+mod csv;
+mod json;
+mod parquet;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+
+use crate::compression::Compression;
+use crate::error::Result;
+use crate::metrics::Column;
+use crate::source::Row;
+
+pub use self::csv::CsvExporter;
+pub use self::json::JsonExporter;
+pub use self::parquet::ParquetExporter;
+
+/// Writes data points of every exported measurement into files of some
+/// format. Several exporters can be used at once, in which case all of them
+/// get the same data points.
+#[async_trait]
+pub trait Exporter: Send + Sync {
+    /// Name of the format recorded into the manifest, e.g. `csv`.
+    fn format(&self) -> &str;
+
+    /// Whether data points can be appended to a file written by an earlier
+    /// export, which the follow mode relies on.
+    fn supports_append(&self) -> bool {
+        true
+    }
+
+    /// Opens the file of the measurement in the output directory. Data points
+    /// are appended to `append_to` when the measurement was exported earlier.
+    async fn open(
+        &self,
+        directory: &Path,
+        measurement: &str,
+        columns: &'static [Column],
+        append_to: Option<&str>,
+    ) -> Result<Box<dyn MetricSink>>;
+}
+
+/// File of a single measurement opened by the exporter.
+#[async_trait]
+pub trait MetricSink: Send {
+    /// Writes data points with values of the columns the sink was opened with.
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<()>;
+
+    /// Flushes the written data and returns the file name relative to the
+    /// output directory.
+    async fn finalize(self: Box<Self>) -> Result<String>;
+}
+
+/// Formats supported out of the box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn exporter(&self, compression: Option<Compression>) -> Box<dyn Exporter> {
+        match self {
+            ExportFormat::Csv => Box::new(CsvExporter::new(compression)),
+            ExportFormat::Json => Box::new(JsonExporter::new(compression)),
+            ExportFormat::Parquet => Box::new(ParquetExporter::new(compression)),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!("unsupported format: {}", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::dataset::Dataset;
+    use crate::metrics::{HttpReqDurationMetric, K6Metric};
+    use crate::test_support::test_directory;
+
+    fn request_row(second: u32) -> Row {
+        let row = json!({
+            "time": format!("2024-05-01T12:00:{:02}Z", second),
+            "expected_response": true,
+            "group": "",
+            "method": "GET",
+            "name": "home",
+            "proto": "HTTP/1.1",
+            "scenario": "default",
+            "status": 200,
+            "tls_version": null,
+            "url": "http://test.k6.io/",
+            "value": 100.5 + f64::from(second),
+        });
+        serde_json::from_value(row).unwrap()
+    }
+
+    /// Exports two data points, appends a third one the same way as the
+    /// follow mode does and reads the file back.
+    async fn export_and_append(
+        name: &str,
+        exporter: Box<dyn Exporter>,
+    ) -> (String, Vec<HttpReqDurationMetric>) {
+        let directory = test_directory("exporter", name);
+        let columns = HttpReqDurationMetric::schema();
+        let measurement = HttpReqDurationMetric::metric_table_name();
+
+        let mut sink = exporter
+            .open(&directory, measurement, columns, None)
+            .await
+            .unwrap();
+        sink.write_batch(&[request_row(0), request_row(1)])
+            .await
+            .unwrap();
+        let filename = sink.finalize().await.unwrap();
+
+        let mut sink = exporter
+            .open(&directory, measurement, columns, Some(&filename))
+            .await
+            .unwrap();
+        sink.write_batch(&[request_row(2)]).await.unwrap();
+        assert_eq!(sink.finalize().await.unwrap(), filename);
+
+        let records = Dataset::open(&directory)
+            .await
+            .unwrap()
+            .load_metric::<HttpReqDurationMetric>()
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(directory).unwrap();
+        (filename, records)
+    }
+
+    fn assert_records(records: &[HttpReqDurationMetric]) {
+        let values: Vec<f64> = records.iter().map(|record| record.value).collect();
+        assert_eq!(values, [100.5, 101.5, 102.5]);
+        assert_eq!(records[2].time.to_rfc3339(), "2024-05-01T12:00:02+00:00");
+        assert_eq!(records[0].status, 200);
+        assert!(records[0].expected_response);
+        assert_eq!(records[0].method.as_deref(), Some("GET"));
+        assert_eq!(records[0].tls_version, None);
+    }
+
+    #[tokio::test]
+    async fn csv_files_are_read_back() {
+        let (filename, records) = export_and_append("csv", ExportFormat::Csv.exporter(None)).await;
+        assert_eq!(filename, "http_req_duration.csv");
+        assert_records(&records);
+    }
+
+    #[tokio::test]
+    async fn compressed_csv_files_are_read_back() {
+        let exporter = ExportFormat::Csv.exporter(Some(Compression::Gzip));
+        let (filename, records) = export_and_append("csv-gzip", exporter).await;
+        assert_eq!(filename, "http_req_duration.csv.gz");
+        assert_records(&records);
+    }
+
+    #[tokio::test]
+    async fn json_files_are_read_back() {
+        let exporter = ExportFormat::Json.exporter(Some(Compression::Zstd));
+        let (filename, records) = export_and_append("json-zstd", exporter).await;
+        assert_eq!(filename, "http_req_duration.jsonl.zst");
+        assert_records(&records);
+    }
+
+    #[test]
+    fn parquet_files_are_not_appended_to() {
+        assert!(!ExportFormat::Parquet.exporter(None).supports_append());
+        assert!(ExportFormat::Csv.exporter(None).supports_append());
+    }
+}
@@ -0,0 +1,417 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parquet::basic::{
+    Compression as Codec, LogicalType, Repetition, TimeUnit, Type as PhysicalType,
+};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use serde_json::Value;
+use tokio::task::spawn_blocking;
+
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+use crate::exporter::{Exporter, MetricSink};
+use crate::metrics::{Column, DataType};
+use crate::source::Row;
+
+/// Number of rows kept in memory before they're flushed as a row group.
+const DEFAULT_ROW_GROUP_SIZE: usize = 65_536;
+
+/// Writes each measurement into a Parquet file. Rows are flushed as a row
+/// group every 64k rows, but the file can't be appended to in the follow
+/// mode. With compression the column chunks are compressed by the codec of
+/// the same name, so the file keeps the `.parquet` extension.
+pub struct ParquetExporter {
+    row_group_size: usize,
+    compression: Option<Compression>,
+}
+
+impl ParquetExporter {
+    pub fn new(compression: Option<Compression>) -> Self {
+        ParquetExporter {
+            row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            compression,
+        }
+    }
+
+    pub fn with_row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = row_group_size.max(1);
+        self
+    }
+}
+
+impl Default for ParquetExporter {
+    fn default() -> Self {
+        ParquetExporter::new(None)
+    }
+}
+
+struct ParquetSink {
+    filename: String,
+    columns: &'static [Column],
+    row_group_size: usize,
+    // Taken by the blocking tasks which write the row groups
+    writer: Option<SerializedFileWriter<File>>,
+    rows: Vec<Row>,
+}
+
+#[async_trait]
+impl Exporter for ParquetExporter {
+    fn format(&self) -> &str {
+        "parquet"
+    }
+
+    fn supports_append(&self) -> bool {
+        false
+    }
+
+    async fn open(
+        &self,
+        directory: &Path,
+        measurement: &str,
+        columns: &'static [Column],
+        append_to: Option<&str>,
+    ) -> Result<Box<dyn MetricSink>> {
+        if append_to.is_some() {
+            return Err(Error::Export(String::from(
+                "Parquet files can't be appended to",
+            )));
+        }
+
+        let filename = format!("{0}.parquet", measurement);
+        let schema = Arc::new(message_type(columns)?);
+        let codec = match self.compression {
+            Some(Compression::Gzip) => Codec::GZIP(Default::default()),
+            Some(Compression::Zstd) => Codec::ZSTD(Default::default()),
+            None => Codec::UNCOMPRESSED,
+        };
+        let properties = Arc::new(
+            WriterProperties::builder()
+                .set_created_by(format!("k6-reports version {}", env!("CARGO_PKG_VERSION")))
+                .set_compression(codec)
+                .build(),
+        );
+        let path = directory.join(&filename);
+        let writer = spawn_blocking(move || {
+            let output_file = File::create(path)?;
+            SerializedFileWriter::new(output_file, schema, properties).map_err(parquet_error)
+        })
+        .await
+        .map_err(|error| Error::Export(error.to_string()))??;
+
+        Ok(Box::new(ParquetSink {
+            filename,
+            columns,
+            row_group_size: self.row_group_size,
+            writer: Some(writer),
+            rows: vec![],
+        }))
+    }
+}
+
+impl ParquetSink {
+    /// Writes the rows as a row group on a blocking thread.
+    async fn flush_row_group(&mut self, rows: Vec<Row>) -> Result<()> {
+        let mut writer = self
+            .writer
+            .take()
+            .ok_or_else(|| Error::Export(String::from("the Parquet file is already closed")))?;
+        let columns = self.columns;
+        let writer = spawn_blocking(move || {
+            write_row_group(&mut writer, columns, &rows)?;
+            Ok::<_, Error>(writer)
+        })
+        .await
+        .map_err(|error| Error::Export(error.to_string()))??;
+        self.writer = Some(writer);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MetricSink for ParquetSink {
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<()> {
+        self.rows.extend_from_slice(rows);
+        while self.rows.len() >= self.row_group_size {
+            let rest = self.rows.split_off(self.row_group_size);
+            let row_group = std::mem::replace(&mut self.rows, rest);
+            self.flush_row_group(row_group).await?;
+        }
+        Ok(())
+    }
+
+    async fn finalize(mut self: Box<Self>) -> Result<String> {
+        if !self.rows.is_empty() {
+            let row_group = std::mem::take(&mut self.rows);
+            self.flush_row_group(row_group).await?;
+        }
+        if let Some(writer) = self.writer.take() {
+            spawn_blocking(move || writer.close().map_err(parquet_error))
+                .await
+                .map_err(|error| Error::Export(error.to_string()))??;
+        }
+        Ok(self.filename)
+    }
+}
+
+/// Describes the columns as a flat Parquet schema. Timestamps are stored as
+/// microseconds since the epoch in UTC.
+fn message_type(columns: &[Column]) -> Result<Type> {
+    let mut fields = vec![];
+    for column in columns {
+        let (physical_type, logical_type) = match column.data_type {
+            DataType::Timestamp => (
+                PhysicalType::INT64,
+                Some(LogicalType::timestamp(true, TimeUnit::MICROS)),
+            ),
+            DataType::String => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+            DataType::Boolean => (PhysicalType::BOOLEAN, None),
+            DataType::Integer => (PhysicalType::INT64, None),
+            DataType::Float => (PhysicalType::DOUBLE, None),
+        };
+        let repetition = match column.nullable {
+            true => Repetition::OPTIONAL,
+            false => Repetition::REQUIRED,
+        };
+        let field = Type::primitive_type_builder(column.name, physical_type)
+            .with_repetition(repetition)
+            .with_logical_type(logical_type)
+            .build()
+            .map_err(parquet_error)?;
+        fields.push(Arc::new(field));
+    }
+
+    Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()
+        .map_err(parquet_error)
+}
+
+/// Writes a column chunk per column. Missing values of a required column are
+/// stored as zeros or empty strings.
+fn write_row_group(
+    writer: &mut SerializedFileWriter<File>,
+    columns: &[Column],
+    rows: &[Row],
+) -> Result<()> {
+    let mut row_group = writer.next_row_group().map_err(parquet_error)?;
+    for column in columns {
+        let mut column_writer = row_group
+            .next_column()
+            .map_err(parquet_error)?
+            .ok_or_else(|| Error::Export(format!("the `{}` column is missing", column.name)))?;
+
+        let values: Vec<Option<&Value>> = rows
+            .iter()
+            .map(|row| row.get(column.name).filter(|value| !value.is_null()))
+            .collect();
+        let definition_levels: Option<Vec<i16>> = column
+            .nullable
+            .then(|| values.iter().map(|value| value.is_some() as i16).collect());
+        let present = values
+            .iter()
+            .filter(|value| !column.nullable || value.is_some());
+
+        let levels = definition_levels.as_deref();
+        match column.data_type {
+            DataType::Boolean => {
+                let values: Vec<bool> = present
+                    .map(|value| value.and_then(Value::as_bool).unwrap_or(false))
+                    .collect();
+                column_writer
+                    .typed::<BoolType>()
+                    .write_batch(&values, levels, None)
+            }
+            DataType::Timestamp => {
+                let values: Vec<i64> = present
+                    .map(|value| {
+                        value
+                            .and_then(Value::as_str)
+                            .and_then(|value| value.parse::<DateTime<Utc>>().ok())
+                            .map(|time| time.timestamp_micros())
+                            .unwrap_or(0)
+                    })
+                    .collect();
+                column_writer
+                    .typed::<Int64Type>()
+                    .write_batch(&values, levels, None)
+            }
+            DataType::Integer => {
+                let values: Vec<i64> = present
+                    .map(|value| value.and_then(Value::as_i64).unwrap_or(0))
+                    .collect();
+                column_writer
+                    .typed::<Int64Type>()
+                    .write_batch(&values, levels, None)
+            }
+            DataType::Float => {
+                let values: Vec<f64> = present
+                    .map(|value| value.and_then(Value::as_f64).unwrap_or(0.0))
+                    .collect();
+                column_writer
+                    .typed::<DoubleType>()
+                    .write_batch(&values, levels, None)
+            }
+            DataType::String => {
+                let values: Vec<ByteArray> = present
+                    .map(|value| match value {
+                        Some(Value::String(text)) => ByteArray::from(text.as_str()),
+                        Some(value) => ByteArray::from(value.to_string().as_str()),
+                        None => ByteArray::from(""),
+                    })
+                    .collect();
+                column_writer
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, levels, None)
+            }
+        }
+        .map_err(parquet_error)?;
+        column_writer.close().map_err(parquet_error)?;
+    }
+
+    row_group.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+fn parquet_error(error: ParquetError) -> Error {
+    Error::Export(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use chrono::{TimeZone, Utc};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+    use serde_json::json;
+
+    use super::*;
+    use crate::metrics::{HttpReqDurationMetric, K6Metric};
+    use crate::test_support::test_directory;
+
+    fn request_row(second: u32, status: Option<u16>) -> Row {
+        let time = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, second).unwrap();
+        let row = json!({
+            "time": time.to_rfc3339(),
+            "expected_response": status == Some(200),
+            "group": "::home",
+            "method": "GET",
+            "name": "home",
+            "proto": "HTTP/1.1",
+            "scenario": "default",
+            "status": status,
+            "tls_version": null,
+            "url": "http://test.k6.io/",
+            "value": 100.5 + f64::from(second),
+        });
+        serde_json::from_value(row).unwrap()
+    }
+
+    async fn export(directory: &Path, exporter: ParquetExporter, rows: &[Row]) -> String {
+        let mut sink = exporter
+            .open(
+                directory,
+                "http_req_duration",
+                HttpReqDurationMetric::schema(),
+                None,
+            )
+            .await
+            .unwrap();
+        sink.write_batch(rows).await.unwrap();
+        sink.finalize().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn rows_are_read_back_by_the_parquet_reader() {
+        let directory = test_directory("parquet", "round-trip");
+        let rows = vec![request_row(0, Some(200)), request_row(1, None)];
+        let filename = export(&directory, ParquetExporter::default(), &rows).await;
+
+        let reader =
+            SerializedFileReader::new(File::open(directory.join(filename)).unwrap()).unwrap();
+        let read_rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(read_rows.len(), 2);
+
+        let first: Vec<(&String, &Field)> = read_rows[0].get_column_iter().collect();
+        let columns: Vec<&str> = first.iter().map(|(name, _)| name.as_str()).collect();
+        let expected_columns: Vec<&str> = HttpReqDurationMetric::schema()
+            .iter()
+            .map(|column| column.name)
+            .collect();
+        assert_eq!(columns, expected_columns);
+
+        let field = |row: usize, name: &str| {
+            read_rows[row]
+                .get_column_iter()
+                .find(|(column, _)| column.as_str() == name)
+                .map(|(_, field)| field.clone())
+                .unwrap()
+        };
+        let time = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            field(0, "time"),
+            Field::TimestampMicros(time.timestamp_micros())
+        );
+        assert_eq!(field(0, "expected_response"), Field::Bool(true));
+        assert_eq!(
+            field(0, "url"),
+            Field::Str(String::from("http://test.k6.io/"))
+        );
+        assert_eq!(field(0, "value"), Field::Double(100.5));
+        assert_eq!(field(0, "tls_version"), Field::Null);
+        assert_eq!(field(1, "value"), Field::Double(101.5));
+        // `status` is required, so the missing value is stored as zero
+        assert_eq!(field(1, "status"), Field::Long(0));
+        assert_eq!(field(1, "group"), Field::Str(String::from("::home")));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rows_are_flushed_in_row_groups() {
+        let directory = test_directory("parquet", "row-groups");
+        let rows: Vec<Row> = (0..5)
+            .map(|second| request_row(second, Some(200)))
+            .collect();
+        let exporter = ParquetExporter::default().with_row_group_size(2);
+        let filename = export(&directory, exporter, &rows).await;
+
+        let reader =
+            SerializedFileReader::new(File::open(directory.join(filename)).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 3);
+        assert_eq!(metadata.file_metadata().num_rows(), 5);
+        assert_eq!(reader.get_row_iter(None).unwrap().count(), 5);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn column_chunks_are_compressed() {
+        let directory = test_directory("parquet", "compressed");
+        let rows = vec![request_row(0, Some(200))];
+        let exporter = ParquetExporter::new(Some(Compression::Zstd));
+        let filename = export(&directory, exporter, &rows).await;
+        assert_eq!(filename, "http_req_duration.parquet");
+
+        let reader =
+            SerializedFileReader::new(File::open(directory.join(filename)).unwrap()).unwrap();
+        let column = reader.metadata().row_group(0).column(0);
+        assert!(matches!(column.compression(), Codec::ZSTD(_)));
+        assert_eq!(reader.get_row_iter(None).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::html::{format_duration, format_rate, render_groups};
+use crate::metrics::{GroupDurationMetric, HttpReqDurationMetric};
+use crate::output::OutputFormat;
+use crate::summary::{endpoint_method, endpoint_name, DurationStats};
+use crate::table::TextTable;
+
+/// Separator of nested groups in the `group` tag, e.g. `::checkout::payment`.
+pub const GROUP_SEPARATOR: &str = "::";
+
+/// Timings of the groups of a run, nested the same way as in the test script.
+#[derive(Serialize, Debug, Clone)]
+pub struct GroupReport {
+    /// Requests made outside of any group and the top-level groups.
+    pub root: GroupTimings,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct GroupTimings {
+    pub name: String,
+    /// Full group path as stored in the `group` tag.
+    pub path: String,
+    /// Number of times the group was executed.
+    pub executions: u64,
+    /// Durations of the group from the `group_duration` metric in milliseconds.
+    pub group_duration: Option<DurationStats>,
+    pub requests: Vec<GroupRequests>,
+    pub groups: Vec<GroupTimings>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GroupRequests {
+    pub method: String,
+    pub name: String,
+    pub requests: u64,
+    pub error_rate: f64,
+    pub http_req_duration: DurationStats,
+}
+
+/// Splits the group path into the names of the nested groups along with
+/// their full paths, from the outermost one.
+pub fn split_group_path(path: &str) -> Vec<(String, String)> {
+    let mut current_path = String::new();
+    path.split(GROUP_SEPARATOR)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            current_path = format!("{}{}{}", current_path, GROUP_SEPARATOR, name);
+            (name.to_string(), current_path.clone())
+        })
+        .collect()
+}
+
+/// Group of a report tree, nested the same way as in the test script. The
+/// root group has an empty path and holds what was run outside of any group.
+pub trait GroupNode: Sized {
+    /// Line of the flattened tree, see `collect_rows`.
+    type Row;
+
+    /// Creates an empty group with the given name and full path.
+    fn new_group(name: String, path: String) -> Self;
+    fn name(&self) -> &str;
+    fn path(&self) -> &str;
+    fn groups(&self) -> &[Self];
+    fn groups_mut(&mut self) -> &mut Vec<Self>;
+    /// Row of the group itself.
+    fn group_row(&self, depth: usize) -> Self::Row;
+    /// Rows of the requests, checks, etc. run right in the group.
+    fn push_item_rows(&self, depth: usize, rows: &mut Vec<Self::Row>);
+}
+
+/// Flattens the tree in the depth-first order. The root group doesn't get a
+/// row of its own, so its items and groups start at the given depth.
+pub fn collect_rows<G: GroupNode>(group: &G, depth: usize, rows: &mut Vec<G::Row>) {
+    let nested_depth = match group.path().is_empty() {
+        true => depth,
+        false => {
+            rows.push(group.group_row(depth));
+            depth + 1
+        }
+    };
+
+    group.push_item_rows(nested_depth, rows);
+    for subgroup in group.groups() {
+        collect_rows(subgroup, nested_depth, rows);
+    }
+}
+
+/// Finds the group by its full path, creating the missing parent groups.
+pub fn find_or_insert_group<'a, G: GroupNode>(root: &'a mut G, path: &str) -> &'a mut G {
+    let mut group = root;
+    for (name, path) in split_group_path(path) {
+        let position = match group.groups().iter().position(|child| child.name() == name) {
+            Some(position) => position,
+            None => {
+                group.groups_mut().push(G::new_group(name, path));
+                group.groups().len() - 1
+            }
+        };
+        group = &mut group.groups_mut()[position];
+    }
+    group
+}
+
+impl GroupReport {
+    pub async fn from_directory(directory: &Path) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the group durations")?;
+        let group_durations = dataset.load_metric::<GroupDurationMetric>().await?;
+        let durations = dataset.load_metric::<HttpReqDurationMetric>().await?;
+
+        let mut group_values: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        for record in &group_durations {
+            group_values
+                .entry(record.group.clone().unwrap_or_default())
+                .or_default()
+                .push(record.value);
+        }
+        let mut group_requests: BTreeMap<String, BTreeMap<(String, String), Vec<_>>> =
+            BTreeMap::new();
+        for record in durations {
+            group_requests
+                .entry(record.group.clone().unwrap_or_default())
+                .or_default()
+                .entry((endpoint_method(&record), endpoint_name(&record)))
+                .or_default()
+                .push(record);
+        }
+
+        let mut root = GroupTimings::default();
+        for (path, values) in group_values {
+            let group = find_or_insert_group(&mut root, &path);
+            group.executions = values.len() as u64;
+            group.group_duration = Some(DurationStats::from_values(values));
+        }
+        for (path, endpoints) in group_requests {
+            let group = find_or_insert_group(&mut root, &path);
+            group.requests = endpoints
+                .into_iter()
+                .map(|((method, name), records)| summarize_requests(method, name, records))
+                .collect();
+        }
+
+        Ok(GroupReport { root })
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_groups(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// Rows of the tree view in the depth-first order.
+    pub fn rows(&self) -> Vec<GroupRow> {
+        let mut rows = vec![];
+        collect_rows(&self.root, 0, &mut rows);
+        rows
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let indent = match format {
+            OutputFormat::Markdown => "&nbsp;&nbsp;",
+            _ => "  ",
+        };
+        let mut table = TextTable::new(&[
+            "Group / request",
+            "Count",
+            "Errors",
+            "Mean",
+            "p50",
+            "p90",
+            "p95",
+            "p99",
+            "Max",
+        ]);
+        for row in self.rows() {
+            let stats = row.duration.as_ref();
+            let cell = |pick: fn(&DurationStats) -> f64| {
+                stats
+                    .map(|stats| format!("{} ms", format_duration(pick(stats))))
+                    .unwrap_or_default()
+            };
+            table.add_row(vec![
+                format!("{}{}", indent.repeat(row.depth), row.label),
+                row.count.map(|count| count.to_string()).unwrap_or_default(),
+                row.error_rate.map(format_rate).unwrap_or_default(),
+                cell(|stats| stats.mean),
+                cell(|stats| stats.p50),
+                cell(|stats| stats.p90),
+                cell(|stats| stats.p95),
+                cell(|stats| stats.p99),
+                cell(|stats| stats.max),
+            ]);
+        }
+
+        let note = "Group rows show `group_duration`, request rows show `http_req_duration` of the requests made inside of the group.";
+        match format {
+            OutputFormat::Markdown => {
+                format!("## Groups\n\n{}\n\n{}", note, table.to_markdown())
+            }
+            _ => format!("Groups\n{}\n\n{}", note, table.to_console()),
+        }
+    }
+}
+
+/// Flattened line of the tree view used by the text and HTML renderers.
+pub struct GroupRow {
+    pub depth: usize,
+    pub is_group: bool,
+    pub label: String,
+    /// Executions of the group or the number of requests. Unknown for groups
+    /// when `group_duration` wasn't exported.
+    pub count: Option<u64>,
+    pub error_rate: Option<f64>,
+    pub duration: Option<DurationStats>,
+}
+
+impl GroupNode for GroupTimings {
+    type Row = GroupRow;
+
+    fn new_group(name: String, path: String) -> Self {
+        GroupTimings {
+            name,
+            path,
+            ..GroupTimings::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn groups(&self) -> &[Self] {
+        &self.groups
+    }
+
+    fn groups_mut(&mut self) -> &mut Vec<Self> {
+        &mut self.groups
+    }
+
+    fn group_row(&self, depth: usize) -> GroupRow {
+        GroupRow {
+            depth,
+            is_group: true,
+            label: self.path.clone(),
+            count: self.group_duration.as_ref().map(|_| self.executions),
+            error_rate: None,
+            duration: self.group_duration.clone(),
+        }
+    }
+
+    fn push_item_rows(&self, depth: usize, rows: &mut Vec<GroupRow>) {
+        for endpoint in &self.requests {
+            rows.push(GroupRow {
+                depth,
+                is_group: false,
+                label: format!("{} {}", endpoint.method, endpoint.name)
+                    .trim()
+                    .to_string(),
+                count: Some(endpoint.requests),
+                error_rate: Some(endpoint.error_rate),
+                duration: Some(endpoint.http_req_duration.clone()),
+            });
+        }
+    }
+}
+
+fn summarize_requests(
+    method: String,
+    name: String,
+    records: Vec<HttpReqDurationMetric>,
+) -> GroupRequests {
+    let failed = records
+        .iter()
+        .filter(|record| !record.expected_response)
+        .count();
+    GroupRequests {
+        method,
+        name,
+        requests: records.len() as u64,
+        error_rate: failed as f64 / records.len() as f64,
+        http_req_duration: DurationStats::from_values(
+            records.iter().map(|record| record.value).collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_directory;
+
+    const HEADER: &str =
+        "time,expected_response,group,method,name,proto,scenario,status,tls_version,url,value\n";
+
+    fn write_run(directory: &Path) {
+        std::fs::write(
+            directory.join("group_duration.csv"),
+            "time,group,scenario,value\n\
+             2024-05-01T12:00:01Z,::checkout::payment,default,300\n\
+             2024-05-01T12:00:02Z,::checkout::payment,default,500\n\
+             2024-05-01T12:00:03Z,::browse,default,100\n",
+        )
+        .unwrap();
+        let mut durations = String::from(HEADER);
+        for (expected_response, group, url, value) in [
+            (true, "", "/health", 5.0),
+            (true, "::checkout::payment", "/pay", 200.0),
+            (false, "::checkout::payment", "/pay", 400.0),
+            (true, "::checkout", "/cart", 50.0),
+        ] {
+            durations.push_str(&format!(
+                "2024-05-01T12:00:00Z,{0},{1},POST,{2},HTTP/1.1,default,200,,{2},{3}\n",
+                expected_response, group, url, value
+            ));
+        }
+        std::fs::write(directory.join("http_req_duration.csv"), durations).unwrap();
+    }
+
+    #[test]
+    fn group_paths_are_split_into_nested_groups() {
+        assert_eq!(
+            split_group_path("::checkout::payment"),
+            [
+                (String::from("checkout"), String::from("::checkout")),
+                (String::from("payment"), String::from("::checkout::payment")),
+            ]
+        );
+        assert!(split_group_path("").is_empty());
+    }
+
+    #[tokio::test]
+    async fn groups_are_nested_with_their_requests() {
+        let directory = test_directory("groups", "nested");
+        write_run(&directory);
+
+        let report = GroupReport::from_directory(&directory).await.unwrap();
+        let root = &report.root;
+        assert_eq!(root.requests[0].name, "/health");
+        let names: Vec<&str> = root
+            .groups
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect();
+        // Ordered by the group path
+        assert_eq!(names, ["browse", "checkout"]);
+
+        // The parent group is created for its subgroup, without own timings
+        let checkout = &root.groups[1];
+        assert_eq!(checkout.path, "::checkout");
+        assert!(checkout.group_duration.is_none());
+        assert_eq!(checkout.requests[0].name, "/cart");
+
+        let payment = &checkout.groups[0];
+        assert_eq!(payment.executions, 2);
+        assert_eq!(payment.group_duration.as_ref().unwrap().max, 500.0);
+        assert_eq!(payment.requests[0].requests, 2);
+        assert_eq!(payment.requests[0].error_rate, 0.5);
+
+        let rows: Vec<String> = report
+            .rows()
+            .iter()
+            .map(|row| format!("{}{} {:?}", "  ".repeat(row.depth), row.label, row.count))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "POST /health Some(1)",
+                "::browse Some(1)",
+                "::checkout None",
+                "  POST /cart Some(1)",
+                "  ::checkout::payment Some(2)",
+                "    POST /pay Some(2)",
+            ]
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,979 @@
+use std::fmt::Write;
+
+use crate::chart::{line_chart, stacked_bar_chart, BarSegment, ChartSeries};
+use crate::checks::{pass_rate, ChecksReport};
+use crate::comparison::{Comparison, Delta, SignificanceTest, Verdict};
+use crate::groups::GroupReport;
+use crate::layout::ExportedRun;
+use crate::saturation::{
+    LoadLevel, Saturation, LATENCY_DEGRADATION_THRESHOLD, THROUGHPUT_EFFICIENCY_THRESHOLD,
+};
+use crate::slo::{optional_budget, optional_rate, SloCompliance, SloReport};
+use crate::statuses::{count_of, format_codes, StatusCount, StatusReport};
+use crate::summary::Summary;
+use crate::timeline::{Bucket, Timeline};
+use crate::trend::{SeriesKind, Trend, TrendSeries, TrendValue};
+use crate::waterfall::{PhaseStats, Waterfall};
+
+const STYLES: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1, h2 { font-weight: 500; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.4em 0.8em; text-align: left; }
+th { background: #f5f5f5; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+.worse { color: #c62828; }
+.better { color: #2e7d32; }
+.muted { color: #777; }
+.significant { color: #c62828; font-weight: bold; }
+";
+
+/// Escapes the text for using it inside of HTML elements and attributes.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Encodes the text for using it as a part of a URL.
+pub fn encode_url_component(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n{2}</body>\n</html>\n",
+        escape(title),
+        STYLES,
+        body
+    )
+}
+
+pub fn render_index(runs: &[(ExportedRun, Option<Summary>)]) -> String {
+    let mut body = String::new();
+    if runs.is_empty() {
+        body.push_str("<p class=\"muted\">No exported runs found.</p>\n");
+        return page("k6 reports", &body);
+    }
+
+    body.push_str("<table>\n<tr><th>Run</th><th>Started at</th><th>Test ID</th><th>Profile</th><th>Git SHA</th><th>Requests</th><th>Error rate</th><th>p95 (ms)</th></tr>\n");
+    for (run, summary) in runs {
+        let metadata = &run.manifest.run;
+        let _ = write!(
+            body,
+            "<tr><td><a href=\"/runs/{0}\">{1}</a></td><td>{2}</td><td>{3}</td><td>{4}</td><td>{5}</td>",
+            encode_url_component(&run.name),
+            escape(&run.name),
+            metadata.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            escape(metadata.testid.as_deref().unwrap_or("")),
+            escape(metadata.profile.as_deref().unwrap_or("")),
+            escape(metadata.git_sha.as_deref().unwrap_or("")),
+        );
+        match summary {
+            Some(summary) => {
+                let _ = writeln!(
+                    body,
+                    "<td class=\"number\">{0}</td><td class=\"number\">{1}</td><td class=\"number\">{2}</td></tr>",
+                    summary.requests,
+                    format_rate(summary.error_rate),
+                    format_duration(summary.http_req_duration.p95),
+                );
+            }
+            None => body.push_str("<td colspan=\"3\" class=\"muted\">no summary</td></tr>\n"),
+        }
+    }
+    body.push_str("</table>\n");
+
+    body.push_str("<h2>Compare runs</h2>\n<form action=\"/compare\" method=\"get\">\n");
+    for (field, label) in [("base", "Base"), ("target", "Target")] {
+        let _ = write!(body, "<label>{0} <select name=\"{1}\">", label, field);
+        for (run, _) in runs {
+            let _ = write!(
+                body,
+                "<option value=\"{0}\">{0}</option>",
+                escape(&run.name)
+            );
+        }
+        body.push_str("</select></label>\n");
+    }
+    body.push_str("<button type=\"submit\">Compare</button>\n</form>\n");
+
+    page("k6 reports", &body)
+}
+
+pub fn render_run(run: &ExportedRun, summary: &Summary) -> String {
+    let manifest = &run.manifest;
+    let mut body = String::from("<p><a href=\"/\">&larr; All runs</a></p>\n");
+
+    body.push_str("<h2>Run</h2>\n<table>\n");
+    let window_from = manifest
+        .time_window
+        .from
+        .map(|from| from.to_rfc3339())
+        .unwrap_or_else(|| String::from("beginning"));
+    let source = match (&manifest.source.database, &manifest.source.retention_policy) {
+        (Some(database), Some(retention_policy)) => format!(
+            "{0} {1} ({2}.{3})",
+            manifest.source.kind, manifest.source.url, database, retention_policy
+        ),
+        _ => format!("{0} {1}", manifest.source.kind, manifest.source.url),
+    };
+    let rows = [
+        ("Started at", manifest.run.started_at.to_rfc3339()),
+        ("Test ID", manifest.run.testid.clone().unwrap_or_default()),
+        ("Profile", manifest.run.profile.clone().unwrap_or_default()),
+        ("Git SHA", manifest.run.git_sha.clone().unwrap_or_default()),
+        ("Source", source),
+        (
+            "Time window",
+            format!(
+                "{} &ndash; {}",
+                escape(&window_from),
+                manifest.time_window.to.to_rfc3339()
+            ),
+        ),
+        ("Filters", manifest.filters.join(" AND ")),
+        ("Tool version", manifest.tool_version.clone()),
+    ];
+    for (name, value) in rows {
+        let value = match name {
+            "Time window" => value,
+            _ => escape(&value),
+        };
+        let _ = writeln!(body, "<tr><th>{0}</th><td>{1}</td></tr>", name, value);
+    }
+    body.push_str("</table>\n");
+
+    body.push_str("<h2>Totals</h2>\n<table>\n");
+    let totals = [
+        ("Requests", summary.requests.to_string()),
+        ("Failed requests", summary.failed_requests.to_string()),
+        ("Error rate", format_rate(summary.error_rate)),
+        ("Iterations", summary.iterations.to_string()),
+        ("Dropped iterations", summary.dropped_iterations.to_string()),
+        ("Max VUs", format!("{:.0}", summary.max_vus)),
+        ("Checks passed", summary.checks_passed.to_string()),
+        ("Checks failed", summary.checks_failed.to_string()),
+        (
+            "Mean duration (ms)",
+            format_duration(summary.http_req_duration.mean),
+        ),
+        (
+            "p95 duration (ms)",
+            format_duration(summary.http_req_duration.p95),
+        ),
+        (
+            "p99 duration (ms)",
+            format_duration(summary.http_req_duration.p99),
+        ),
+    ];
+    for (name, value) in totals {
+        let _ = writeln!(
+            body,
+            "<tr><th>{0}</th><td class=\"number\">{1}</td></tr>",
+            name, value
+        );
+    }
+    body.push_str("</table>\n");
+
+    body.push_str("<h2>Endpoints</h2>\n<table>\n<tr><th>Method</th><th>Name</th><th>Requests</th><th>Error rate</th><th>Mean (ms)</th><th>p50 (ms)</th><th>p90 (ms)</th><th>p95 (ms)</th><th>p99 (ms)</th><th>Max (ms)</th></tr>\n");
+    for endpoint in &summary.endpoints {
+        let duration = &endpoint.http_req_duration;
+        let _ = writeln!(
+            body,
+            "<tr><td>{0}</td><td>{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td><td class=\"number\">{4}</td><td class=\"number\">{5}</td><td class=\"number\">{6}</td><td class=\"number\">{7}</td><td class=\"number\">{8}</td><td class=\"number\">{9}</td></tr>",
+            escape(&endpoint.method),
+            escape(&endpoint.name),
+            endpoint.requests,
+            format_rate(endpoint.error_rate),
+            format_duration(duration.mean),
+            format_duration(duration.p50),
+            format_duration(duration.p90),
+            format_duration(duration.p95),
+            format_duration(duration.p99),
+            format_duration(duration.max),
+        );
+    }
+    body.push_str("</table>\n");
+
+    let mut protocols = vec![];
+    if let Some(grpc) = &summary.grpc_req_duration {
+        protocols.push(("gRPC requests", String::new(), grpc));
+    }
+    if let Some(websockets) = &summary.websockets {
+        let sessions = format!(
+            "{} sessions, {} messages sent, {} received",
+            websockets.sessions, websockets.messages_sent, websockets.messages_received
+        );
+        protocols.push(("WebSocket connecting", sessions, &websockets.ws_connecting));
+        protocols.push((
+            "WebSocket sessions",
+            String::new(),
+            &websockets.ws_session_duration,
+        ));
+    }
+    if !protocols.is_empty() {
+        body.push_str("<h2>Protocols</h2>\n<table>\n<tr><th>Metric</th><th>Mean (ms)</th><th>p50 (ms)</th><th>p95 (ms)</th><th>p99 (ms)</th><th>Max (ms)</th><th></th></tr>\n");
+        for (name, note, stats) in protocols {
+            let _ = writeln!(
+                body,
+                "<tr><td>{0}</td><td class=\"number\">{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td><td class=\"number\">{4}</td><td class=\"number\">{5}</td><td class=\"muted\">{6}</td></tr>",
+                name,
+                format_duration(stats.mean),
+                format_duration(stats.p50),
+                format_duration(stats.p95),
+                format_duration(stats.p99),
+                format_duration(stats.max),
+                escape(&note),
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    if !summary.web_vitals.is_empty() {
+        body.push_str("<h2>Web vitals</h2>\n<table>\n<tr><th>Metric</th><th>Samples</th><th>p50</th><th>p90</th><th>p95</th><th>Poor</th></tr>\n");
+        for vital in &summary.web_vitals {
+            let _ = writeln!(
+                body,
+                "<tr><td>{0}</td><td class=\"number\">{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td><td class=\"number\">{4}</td><td class=\"number\">{5}</td></tr>",
+                escape(&vital.name),
+                vital.samples,
+                format_duration(vital.values.p50),
+                format_duration(vital.values.p90),
+                format_duration(vital.values.p95),
+                format_rate(vital.poor_rate),
+            );
+        }
+        body.push_str("</table>\n<p class=\"muted\">Values are in milliseconds, except for the unitless CLS.</p>\n");
+    }
+
+    if let Some(slo) = &summary.slo {
+        body.push_str("<h2>Apdex and SLOs</h2>\n");
+        body.push_str(&slo_section(slo));
+    }
+
+    body.push_str("<h2>Files</h2>\n<ul>\n");
+    for metric in &manifest.metrics {
+        let files = std::iter::once((&metric.file, &metric.sha256)).chain(
+            metric
+                .extra_files
+                .iter()
+                .map(|extra| (&extra.file, &extra.sha256)),
+        );
+        for (file, sha256) in files {
+            let _ = writeln!(
+                body,
+                "<li><a href=\"/runs/{0}/files/{1}\">{2}</a> <span class=\"muted\">{3} rows, sha256 {4}</span></li>",
+                encode_url_component(&run.name),
+                encode_url_component(file),
+                escape(file),
+                metric.rows,
+                sha256,
+            );
+        }
+    }
+    body.push_str("</ul>\n");
+
+    page(&format!("Run {}", run.name), &body)
+}
+
+pub fn render_comparison(comparison: &Comparison) -> String {
+    let mut body = String::from("<p><a href=\"/\">&larr; All runs</a></p>\n");
+
+    body.push_str(
+        "<h2>Totals</h2>\n<table>\n<tr><th></th><th>Base</th><th>Target</th><th>Change</th><th>Verdict</th><th>p-value</th><th>Percentile difference CIs (ms)</th></tr>\n",
+    );
+    let totals = [
+        ("Requests", comparison.requests, false, DeltaFormat::Count),
+        ("Error rate", comparison.error_rate, true, DeltaFormat::Rate),
+        (
+            "p95 duration (ms)",
+            comparison.p95_duration,
+            true,
+            DeltaFormat::Duration,
+        ),
+    ];
+    for (name, delta, higher_is_worse, delta_format) in totals {
+        let test_cells = match delta_format {
+            DeltaFormat::Duration => significance_cells(&comparison.duration_test),
+            _ => String::from("<td colspan=\"3\"></td>"),
+        };
+        let _ = writeln!(
+            body,
+            "<tr><th>{0}</th>{1}{2}</tr>",
+            name,
+            delta_cells(&delta, higher_is_worse, delta_format),
+            test_cells
+        );
+    }
+    body.push_str("</table>\n");
+
+    body.push_str("<h2>Endpoints</h2>\n<table>\n<tr><th>Method</th><th>Name</th><th colspan=\"3\">Requests</th><th colspan=\"3\">Error rate</th><th colspan=\"3\">p95 (ms)</th><th>Verdict</th><th>p-value</th><th>Percentile difference CIs (ms)</th></tr>\n");
+    for endpoint in &comparison.endpoints {
+        let _ = writeln!(
+            body,
+            "<tr><td>{0}</td><td>{1}</td>{2}{3}{4}{5}</tr>",
+            escape(&endpoint.method),
+            escape(&endpoint.name),
+            delta_cells(&endpoint.requests, false, DeltaFormat::Count),
+            delta_cells(&endpoint.error_rate, true, DeltaFormat::Rate),
+            delta_cells(&endpoint.p95_duration, true, DeltaFormat::Duration),
+            significance_cells(&endpoint.duration_test),
+        );
+    }
+    body.push_str("</table>\n");
+
+    page(
+        &format!("{} vs {}", comparison.base, comparison.target),
+        &body,
+    )
+}
+
+pub fn render_timeline(timeline: &Timeline) -> String {
+    let buckets = &timeline.buckets;
+    let labels: Vec<String> = buckets
+        .iter()
+        .map(|bucket| bucket.start.format("%H:%M:%S").to_string())
+        .collect();
+    let mut body = format!(
+        "<p class=\"muted\">{0} intervals of {1} s.</p>\n",
+        buckets.len(),
+        timeline.interval
+    );
+    if buckets.is_empty() {
+        return page("Timeline", &body);
+    }
+
+    let values = |pick: fn(&Bucket) -> Option<f64>| -> Vec<Option<f64>> {
+        buckets.iter().map(pick).collect()
+    };
+    let charts = [
+        (
+            "Throughput",
+            "req/s",
+            vec![ChartSeries::new(
+                "requests",
+                values(|bucket| Some(bucket.requests_per_second)),
+            )],
+        ),
+        (
+            "Request duration",
+            "ms",
+            vec![
+                ChartSeries::new("p50", values(|bucket| bucket.p50_duration)),
+                ChartSeries::new("p95", values(|bucket| bucket.p95_duration)),
+                ChartSeries::new("p99", values(|bucket| bucket.p99_duration)),
+            ],
+        ),
+        (
+            "Error rate",
+            "%",
+            vec![ChartSeries::new(
+                "errors",
+                values(|bucket| Some(bucket.error_rate * 100.0)),
+            )],
+        ),
+        (
+            "Data transfer",
+            "KB/s",
+            vec![
+                ChartSeries::new("sent", values(|bucket| Some(bucket.data_sent / 1024.0))),
+                ChartSeries::new(
+                    "received",
+                    values(|bucket| Some(bucket.data_received / 1024.0)),
+                ),
+            ],
+        ),
+        (
+            "Active VUs",
+            "VUs",
+            vec![ChartSeries::new("vus", values(|bucket| bucket.vus))],
+        ),
+    ];
+    for (title, unit, series) in charts {
+        body.push_str(&line_chart(title, &labels, &series, unit));
+    }
+
+    body.push_str("<h2>Intervals</h2>\n<table>\n<tr><th>Time</th><th>Requests/s</th><th>Error rate</th><th>p50 (ms)</th><th>p95 (ms)</th><th>p99 (ms)</th><th>Sent (KB/s)</th><th>Received (KB/s)</th><th>VUs</th></tr>\n");
+    let optional = |value: Option<f64>, format_value: fn(f64) -> String| {
+        value.map(format_value).unwrap_or_else(|| String::from("-"))
+    };
+    for bucket in buckets {
+        let _ = writeln!(
+            body,
+            "<tr><td>{0}</td><td class=\"number\">{1:.2}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td><td class=\"number\">{4}</td><td class=\"number\">{5}</td><td class=\"number\">{6:.2}</td><td class=\"number\">{7:.2}</td><td class=\"number\">{8}</td></tr>",
+            bucket.start.format("%Y-%m-%d %H:%M:%S UTC"),
+            bucket.requests_per_second,
+            format_rate(bucket.error_rate),
+            optional(bucket.p50_duration, format_duration),
+            optional(bucket.p95_duration, format_duration),
+            optional(bucket.p99_duration, format_duration),
+            bucket.data_sent / 1024.0,
+            bucket.data_received / 1024.0,
+            optional(bucket.vus, |value| format!("{:.0}", value)),
+        );
+    }
+    body.push_str("</table>\n");
+
+    page("Timeline", &body)
+}
+
+pub fn render_saturation(saturation: &Saturation) -> String {
+    let levels = &saturation.levels;
+    let mut body = format!(
+        "<p>{0}</p>\n<p class=\"muted\">The knee is the first level where throughput per VU drops below {1} of the baseline and p95 latency exceeds {2}x of the baseline.</p>\n",
+        escape(&saturation.conclusion()),
+        format_rate(THROUGHPUT_EFFICIENCY_THRESHOLD),
+        LATENCY_DEGRADATION_THRESHOLD
+    );
+    if levels.is_empty() {
+        return page("Saturation", &body);
+    }
+
+    let labels: Vec<String> = levels
+        .iter()
+        .map(|level| format!("{:.0}", level.vus))
+        .collect();
+    let highlighted: Vec<bool> = (0..levels.len())
+        .map(|index| Some(index) == saturation.knee)
+        .collect();
+    let series = |name: &str, pick: fn(&LoadLevel) -> f64| ChartSeries {
+        name: name.to_string(),
+        values: levels.iter().map(|level| Some(pick(level))).collect(),
+        highlighted: highlighted.clone(),
+    };
+    body.push_str(&line_chart(
+        "Throughput by active VUs",
+        &labels,
+        &[series("requests/s", |level| level.requests_per_second)],
+        "",
+    ));
+    body.push_str(&line_chart(
+        "Request duration by active VUs",
+        &labels,
+        &[
+            series("p50", |level| level.p50_duration),
+            series("p95", |level| level.p95_duration),
+        ],
+        "ms",
+    ));
+
+    body.push_str("<h2>Levels</h2>\n<table>\n<tr><th>VUs</th><th>Duration (s)</th><th>Requests/s</th><th>Requests/s per VU</th><th>Efficiency</th><th>p50 (ms)</th><th>p95 (ms)</th><th>p95 ratio</th><th>Error rate</th></tr>\n");
+    for (index, level) in levels.iter().enumerate() {
+        let class = match Some(index) == saturation.knee {
+            true => " class=\"significant\"",
+            false => "",
+        };
+        let _ = writeln!(
+            body,
+            "<tr{0}><td class=\"number\">{1:.1}</td><td class=\"number\">{2:.0}</td><td class=\"number\">{3:.2}</td><td class=\"number\">{4:.3}</td><td class=\"number\">{5}</td><td class=\"number\">{6}</td><td class=\"number\">{7}</td><td class=\"number\">{8:.2}x</td><td class=\"number\">{9}</td></tr>",
+            class,
+            level.vus,
+            level.duration_seconds,
+            level.requests_per_second,
+            level.requests_per_second_per_vu,
+            format_rate(level.efficiency),
+            format_duration(level.p50_duration),
+            format_duration(level.p95_duration),
+            level.latency_ratio,
+            format_rate(level.error_rate),
+        );
+    }
+    body.push_str("</table>\n");
+
+    page("Saturation", &body)
+}
+
+pub fn render_statuses(report: &StatusReport) -> String {
+    let mut body = format!(
+        "<p>{0} requests, {1} unexpected responses ({2})</p>\n",
+        report.requests,
+        report.unexpected_responses,
+        format_rate(report.unexpected_rate)
+    );
+
+    let classes = report.class_names();
+    if !report.buckets.is_empty() {
+        let labels: Vec<String> = report
+            .buckets
+            .iter()
+            .map(|bucket| bucket.start.format("%H:%M:%S").to_string())
+            .collect();
+        let series: Vec<ChartSeries> = classes
+            .iter()
+            .map(|class| {
+                ChartSeries::new(
+                    class,
+                    report
+                        .buckets
+                        .iter()
+                        .map(|bucket| {
+                            let requests = bucket.classes.get(class).copied().unwrap_or(0);
+                            Some(requests as f64 / report.interval as f64)
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+        body.push_str(&line_chart(
+            "Responses by status class",
+            &labels,
+            &series,
+            "/s",
+        ));
+    }
+
+    for (title, counts) in [
+        ("Status classes", &report.classes),
+        ("Status codes", &report.codes),
+    ] {
+        let _ = writeln!(
+            body,
+            "<h2>{}</h2>\n<table>\n<tr><th>Status</th><th>Requests</th><th>Share</th></tr>",
+            title
+        );
+        for StatusCount {
+            status,
+            requests,
+            share,
+        } in counts
+        {
+            let _ = writeln!(
+                body,
+                "<tr><td>{0}</td><td class=\"number\">{1}</td><td class=\"number\">{2}</td></tr>",
+                escape(status),
+                requests,
+                format_rate(*share)
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    let codes: Vec<&String> = report.codes.iter().map(|code| &code.status).collect();
+    body.push_str("<h2>Endpoints</h2>\n<table>\n<tr><th>Method</th><th>Name</th><th>Requests</th><th>Unexpected</th>");
+    for code in &codes {
+        let _ = write!(body, "<th>{}</th>", escape(code));
+    }
+    body.push_str("</tr>\n");
+    for endpoint in &report.endpoints {
+        let _ = write!(
+            body,
+            "<tr><td>{0}</td><td>{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td>",
+            escape(&endpoint.method),
+            escape(&endpoint.name),
+            endpoint.requests,
+            format_rate(endpoint.unexpected_rate)
+        );
+        for code in &codes {
+            let _ = write!(
+                body,
+                "<td class=\"number\">{}</td>",
+                count_of(&endpoint.codes, code)
+            );
+        }
+        body.push_str("</tr>\n");
+    }
+    body.push_str("</table>\n");
+
+    body.push_str("<h2>Top failing URLs</h2>\n<table>\n<tr><th>Method</th><th>URL</th><th>Requests</th><th>Unexpected</th><th>Rate</th><th>Statuses</th></tr>\n");
+    for url in &report.top_failing_urls {
+        let _ = writeln!(
+            body,
+            "<tr><td>{0}</td><td>{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td><td class=\"number\">{4}</td><td>{5}</td></tr>",
+            escape(&url.method),
+            escape(&url.url),
+            url.requests,
+            url.unexpected_responses,
+            format_rate(url.unexpected_rate),
+            escape(&format_codes(&url.codes))
+        );
+    }
+    body.push_str("</table>\n");
+
+    page("Status codes", &body)
+}
+
+pub fn render_waterfall(waterfall: &Waterfall) -> String {
+    let rows: Vec<_> = waterfall.rows().collect();
+    let labels: Vec<String> = rows
+        .iter()
+        .map(|endpoint| {
+            format!("{} {}", endpoint.method, endpoint.name)
+                .trim()
+                .to_string()
+        })
+        .collect();
+    let segments = |pick: fn(&PhaseStats) -> f64, scale: f64| -> Vec<BarSegment> {
+        waterfall
+            .phases
+            .iter()
+            .enumerate()
+            .map(|(index, phase)| BarSegment {
+                name: phase.clone(),
+                values: rows
+                    .iter()
+                    .map(|endpoint| pick(&endpoint.phases[index]) * scale)
+                    .collect(),
+            })
+            .collect()
+    };
+
+    let mut body = String::from("<p class=\"muted\">The total time is the sum of all phases: blocked, connecting and TLS handshaking happen before the request is sent, while sending, waiting and receiving make up <code>http_req_duration</code>.</p>\n");
+    if let Some(note) = waterfall.ambiguous_note() {
+        let _ = writeln!(body, "<p class=\"muted\">{}.</p>", escape(&note));
+    }
+    body.push_str(&stacked_bar_chart(
+        "Mean time per phase",
+        &labels,
+        &segments(|phase| phase.mean, 1.0),
+        "ms",
+    ));
+    body.push_str(&stacked_bar_chart(
+        "Mean share of the total time",
+        &labels,
+        &segments(|phase| phase.mean_share, 100.0),
+        "%",
+    ));
+
+    body.push_str(
+        "<h2>Phases</h2>\n<table>\n<tr><th>Endpoint</th><th>Requests</th><th>Total (ms)</th>",
+    );
+    for phase in &waterfall.phases {
+        let _ = write!(body, "<th>{}</th>", escape(phase));
+    }
+    body.push_str("</tr>\n");
+    for (label, endpoint) in labels.iter().zip(&rows) {
+        let _ = write!(
+            body,
+            "<tr><td>{0}</td><td class=\"number\">{1}</td><td class=\"number\">{2}</td>",
+            escape(label),
+            endpoint.requests,
+            format_duration(endpoint.mean_total)
+        );
+        for phase in &endpoint.phases {
+            let _ = write!(
+                body,
+                "<td class=\"number\">{0} ms / {1} ms<br><span class=\"muted\">{2} / {3}</span></td>",
+                format_duration(phase.mean),
+                format_duration(phase.p95),
+                format_rate(phase.mean_share),
+                format_rate(phase.p95_share)
+            );
+        }
+        body.push_str("</tr>\n");
+    }
+    body.push_str("</table>\n<p class=\"muted\">Each cell shows the mean / p95 duration and the mean / p95 share of the total time.</p>\n");
+
+    page("Request phases", &body)
+}
+
+pub fn render_slo(report: &SloReport) -> String {
+    page("Apdex and SLOs", &slo_section(report))
+}
+
+fn slo_section(report: &SloReport) -> String {
+    let mut body = String::from("<table>\n<tr><th>Method</th><th>Name</th><th>Rule</th><th>Requests</th><th>Apdex</th><th>Rating</th><th>Latency SLI</th><th>Latency budget</th><th>Error rate</th><th>Error budget</th><th>Status</th></tr>\n");
+    for endpoint in &report.endpoints {
+        let slo = endpoint.slo.as_ref();
+        let passed = endpoint.apdex.passed && slo.map(|slo| slo.passed).unwrap_or(true);
+        let (class, status) = match passed {
+            true => ("better", "ok"),
+            false => ("worse", "breached"),
+        };
+        let _ = writeln!(
+            body,
+            "<tr><td>{0}</td><td>{1}</td><td>{2}</td><td class=\"number\">{3}</td><td class=\"number\">{4:.2}</td><td>{5}</td><td class=\"number\">{6}</td><td class=\"number\">{7}</td><td class=\"number\">{8}</td><td class=\"number\">{9}</td><td class=\"{10}\">{11}</td></tr>",
+            escape(&endpoint.method),
+            escape(&endpoint.name),
+            escape(&endpoint.rule),
+            endpoint.requests,
+            endpoint.apdex.score,
+            endpoint.apdex.rating,
+            optional_rate(slo.and_then(|slo| slo.latency_compliance)),
+            optional_budget(slo, SloCompliance::latency_budget),
+            optional_rate(slo.map(|slo| slo.error_rate)),
+            optional_budget(slo, SloCompliance::error_budget),
+            class,
+            status,
+        );
+    }
+    body.push_str("</table>\n");
+
+    let _ = writeln!(
+        body,
+        "<p class=\"{0}\">{1}</p>",
+        if report.breaches > 0 {
+            "worse"
+        } else {
+            "muted"
+        },
+        match report.breaches {
+            0 => String::from("All Apdex scores and SLOs are met."),
+            breaches => format!("{} Apdex score(s) or SLO(s) breached.", breaches),
+        }
+    );
+    body
+}
+
+pub fn render_checks(report: &ChecksReport) -> String {
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "<p class=\"{0}\">{1} checks passed, {2} failed ({3}).</p>",
+        if report.fails > 0 { "worse" } else { "muted" },
+        report.passes,
+        report.fails,
+        format_rate(report.pass_rate)
+    );
+    body.push_str("<table>\n<tr><th>Group / check</th><th>Scenario</th><th>Passes</th><th>Fails</th><th>Pass rate</th><th>First failure</th></tr>\n");
+    for row in report.rows() {
+        let label = match row.is_group {
+            true => format!("<strong>{}</strong>", escape(&row.label)),
+            false => escape(&row.label),
+        };
+        let _ = writeln!(
+            body,
+            "<tr><td style=\"padding-left: {0}em\">{1}</td><td>{2}</td><td class=\"number\">{3}</td><td class=\"number{4}\">{5}</td><td class=\"number\">{6}</td><td>{7}</td></tr>",
+            0.8 + 1.5 * row.depth as f64,
+            label,
+            escape(&row.scenario),
+            row.passes,
+            if row.fails > 0 { " worse" } else { "" },
+            row.fails,
+            format_rate(pass_rate(row.passes, row.fails)),
+            row.first_failed_at
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+        );
+    }
+    body.push_str("</table>\n");
+
+    page("Checks", &body)
+}
+
+pub fn render_groups(report: &GroupReport) -> String {
+    let mut body = String::from("<p class=\"muted\">Group rows show <code>group_duration</code> of the group, request rows show <code>http_req_duration</code> of the requests made inside of it.</p>\n");
+    body.push_str("<table>\n<tr><th>Group / request</th><th>Count</th><th>Errors</th><th>Mean (ms)</th><th>p50 (ms)</th><th>p90 (ms)</th><th>p95 (ms)</th><th>p99 (ms)</th><th>Max (ms)</th></tr>\n");
+    for row in report.rows() {
+        let label = match row.is_group {
+            true => format!("<strong>{}</strong>", escape(&row.label)),
+            false => escape(&row.label),
+        };
+        let _ = write!(
+            body,
+            "<tr><td style=\"padding-left: {0}em\">{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td>",
+            0.8 + 1.5 * row.depth as f64,
+            label,
+            row.count.map(|count| count.to_string()).unwrap_or_default(),
+            row.error_rate.map(format_rate).unwrap_or_default(),
+        );
+        match &row.duration {
+            Some(stats) => {
+                for value in [
+                    stats.mean, stats.p50, stats.p90, stats.p95, stats.p99, stats.max,
+                ] {
+                    let _ = write!(body, "<td class=\"number\">{}</td>", format_duration(value));
+                }
+            }
+            None => body.push_str(&"<td></td>".repeat(6)),
+        }
+        body.push_str("</tr>\n");
+    }
+    body.push_str("</table>\n");
+
+    page("Groups", &body)
+}
+
+/// Picks values of one metric from the series.
+type TrendValues = fn(&TrendSeries) -> &Vec<TrendValue>;
+
+pub fn render_trend(trend: &Trend) -> String {
+    let labels: Vec<String> = trend.runs.iter().map(|run| run.name.clone()).collect();
+    let mut body = format!(
+        "<p class=\"muted\">{0} runs. Circled points are at least {1} standard deviations away from the mean of the previous runs.</p>\n",
+        trend.runs.len(),
+        trend.significant_z_score
+    );
+
+    let metrics: [(&str, &str, TrendValues, f64); 4] = [
+        ("p95 duration", "ms", |series| &series.p95_duration, 1.0),
+        ("Error rate", "%", |series| &series.error_rate, 100.0),
+        (
+            "Throughput",
+            "req/s",
+            |series| &series.requests_per_second,
+            1.0,
+        ),
+        (
+            "Checks passed",
+            "%",
+            |series| &series.check_pass_rate,
+            100.0,
+        ),
+    ];
+    for (kind, heading) in [
+        (SeriesKind::Endpoint, "Endpoints"),
+        (SeriesKind::Scenario, "Scenarios"),
+    ] {
+        let _ = writeln!(body, "<h2>{}</h2>", heading);
+        for (title, unit, values, scale) in metrics {
+            let series: Vec<ChartSeries> = trend
+                .series
+                .iter()
+                .filter(|series| series.kind == kind || series.kind == SeriesKind::Overall)
+                .filter(|series| values(series).iter().any(|value| value.value.is_some()))
+                .map(|series| ChartSeries {
+                    name: series.name.clone(),
+                    values: values(series)
+                        .iter()
+                        .map(|value| value.value.map(|value| value * scale))
+                        .collect(),
+                    highlighted: values(series)
+                        .iter()
+                        .map(|value| value.significant)
+                        .collect(),
+                })
+                .collect();
+            if series.len() > 1 {
+                body.push_str(&line_chart(title, &labels, &series, unit));
+            }
+        }
+    }
+
+    body.push_str("<h2>Values</h2>\n");
+    for series in &trend.series {
+        let _ = writeln!(
+            body,
+            "<h3>{0:?}: {1}</h3>\n<table>\n<tr><th>Run</th><th>Started at</th><th>p95 (ms)</th><th>Error rate</th><th>Requests/s</th><th>Checks passed</th></tr>",
+            series.kind,
+            escape(&series.name)
+        );
+        for (index, run) in trend.runs.iter().enumerate() {
+            let _ = writeln!(
+                body,
+                "<tr><td>{0}</td><td>{1}</td>{2}{3}{4}{5}</tr>",
+                escape(&run.name),
+                run.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                trend_cell(&series.p95_duration[index], format_duration),
+                trend_cell(&series.error_rate[index], format_rate),
+                trend_cell(&series.requests_per_second[index], |value| {
+                    format!("{:.2}", value)
+                }),
+                trend_cell(&series.check_pass_rate[index], format_rate),
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    page("Trend", &body)
+}
+
+fn trend_cell(value: &TrendValue, format_value: fn(f64) -> String) -> String {
+    match value.value {
+        Some(number) => format!(
+            "<td class=\"number{0}\">{1}</td>",
+            if value.significant {
+                " significant"
+            } else {
+                ""
+            },
+            format_value(number)
+        ),
+        None => String::from("<td class=\"number muted\">-</td>"),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DeltaFormat {
+    Count,
+    Rate,
+    Duration,
+}
+
+fn delta_cells(delta: &Delta, higher_is_worse: bool, delta_format: DeltaFormat) -> String {
+    let format_value = |value: f64| match delta_format {
+        DeltaFormat::Count => format!("{:.0}", value),
+        DeltaFormat::Rate => format_rate(value),
+        DeltaFormat::Duration => format_duration(value),
+    };
+    let (class, change) = match delta.change {
+        Some(change) => {
+            let class = match (change > 0.0, change < 0.0, higher_is_worse) {
+                (true, _, true) | (_, true, false) => "worse",
+                (true, _, false) | (_, true, true) => "better",
+                _ => "",
+            };
+            (class, format!("{:+.1}%", change))
+        }
+        None => ("muted", String::from("n/a")),
+    };
+
+    format!(
+        "<td class=\"number\">{0}</td><td class=\"number\">{1}</td><td class=\"number {2}\">{3}</td>",
+        format_value(delta.base),
+        format_value(delta.target),
+        class,
+        change
+    )
+}
+
+fn significance_cells(test: &SignificanceTest) -> String {
+    let (class, verdict) = match test.verdict {
+        Verdict::Improved => ("better", "improved"),
+        Verdict::Unchanged => ("muted", "unchanged"),
+        Verdict::Regressed => ("worse", "regressed"),
+    };
+    let p_value = match test.p_value {
+        Some(p_value) if test.significant => {
+            format!("<strong>{}</strong>", escape(&format_p_value(p_value)))
+        }
+        Some(p_value) => escape(&format_p_value(p_value)),
+        None => String::from("n/a"),
+    };
+    let intervals: Vec<String> = test
+        .percentile_differences
+        .iter()
+        .map(|difference| {
+            format!(
+                "p{} {} &ndash; {}",
+                difference.percentile,
+                format_duration(difference.low),
+                format_duration(difference.high)
+            )
+        })
+        .collect();
+    let intervals = match intervals.is_empty() {
+        true => String::from("n/a"),
+        false => intervals.join("<br>"),
+    };
+
+    format!(
+        "<td class=\"{0}\">{1}</td><td class=\"number\">{2}</td><td class=\"number\">{3}</td>",
+        class, verdict, p_value, intervals
+    )
+}
+
+pub fn format_rate(rate: f64) -> String {
+    format!("{:.2}%", rate * 100.0)
+}
+
+/// Formats the p-value, where very small values aren't shown exactly.
+pub fn format_p_value(p_value: f64) -> String {
+    match p_value < 0.0001 {
+        true => String::from("<0.0001"),
+        false => format!("{:.4}", p_value),
+    }
+}
+
+pub fn format_duration(duration: f64) -> String {
+    format!("{:.2}", duration)
+}
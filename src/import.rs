@@ -0,0 +1,335 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use influxdb::{Client, Timestamp, WriteQuery};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::dataset::Dataset;
+use crate::error::{Error, Result};
+use crate::influx::{create_client, ConnectionOptions};
+use crate::manifest::{Manifest, MANIFEST_FILENAME};
+use crate::metrics::{visit_metrics, ColumnKind, K6Metric, MetricVisitor};
+use crate::source::to_row;
+
+/// Number of data points sent in a single write request.
+const BATCH_SIZE: usize = 5000;
+
+/// How the timestamps of the imported data points are moved.
+#[derive(Debug, Clone, Copy)]
+pub enum TimeShift {
+    /// Move every data point by the offset.
+    Offset(Duration),
+    /// Move the data points so that the run starts at the given time, e.g.
+    /// now, so the retention policy doesn't drop it again.
+    StartAt(DateTime<Utc>),
+}
+
+/// Writes an exported run back into InfluxDB in the same shape as the k6
+/// InfluxDB output does, so the run can be looked at in Grafana again after
+/// the retention policy has dropped it.
+///
+/// InfluxDB keeps one point per measurement, tag set and timestamp, so data
+/// points sharing them (e.g. two identical requests finished within the same
+/// microsecond) overwrite each other. Runs exported from InfluxDB have lost
+/// them already, but runs exported from k6 results files or PostgreSQL may
+/// lose a few data points on the import.
+pub struct RunImporter {
+    connection_url: String,
+    db_client: Client,
+    time_shift: Option<TimeShift>,
+    testid: Option<String>,
+}
+
+impl RunImporter {
+    pub fn new(connection: &ConnectionOptions) -> Self {
+        let (connection_url, db_client) = create_client(connection);
+        RunImporter {
+            connection_url,
+            db_client,
+            time_shift: None,
+            testid: None,
+        }
+    }
+
+    pub fn with_time_shift(mut self, time_shift: Option<TimeShift>) -> Self {
+        self.time_shift = time_shift;
+        self
+    }
+
+    /// Tags every data point with the `testid` tag. By default the tag is
+    /// taken from the run metadata, if the run was exported for a `testid`.
+    pub fn with_testid(mut self, testid: Option<String>) -> Self {
+        self.testid = testid;
+        self
+    }
+
+    pub fn connection_url(&self) -> &str {
+        &self.connection_url
+    }
+
+    /// Imports every metric exported into the directory. Returns the number of
+    /// the written data points.
+    pub async fn import(&self, directory: &Path) -> Result<u64> {
+        let dataset = Dataset::open(directory).await?;
+        let manifest = dataset.manifest().ok_or_else(|| {
+            Error::Import(format!(
+                "`{}` has no {}; only runs exported by k6-reports can be imported",
+                directory.display(),
+                MANIFEST_FILENAME
+            ))
+        })?;
+        let offset = time_offset(self.time_shift, manifest);
+        let testid = self.testid.as_ref().or(manifest.run.testid.as_ref());
+        let mut import = MetricImport {
+            importer: self,
+            dataset: &dataset,
+            offset,
+            testid,
+        };
+        let counts = visit_metrics(&mut import).await?;
+        Ok(counts.into_iter().sum())
+    }
+
+    /// Imports the metric if it was exported. The records are read and
+    /// written in batches, so big runs don't have to fit into memory.
+    async fn import_metric<T>(&self, import: &MetricImport<'_>) -> Result<u64>
+    where
+        T: 'static + K6Metric + Serialize + DeserializeOwned + Send,
+    {
+        let mut reader = match import.dataset.open_metric::<T>().await? {
+            Some(reader) => reader,
+            None => return Ok(0),
+        };
+
+        println!(
+            "Importing data for the `{0}` metrics",
+            T::metric_table_name()
+        );
+        let mut written = 0;
+        loop {
+            let batch = reader.next_batch(BATCH_SIZE).await?;
+            if batch.is_empty() {
+                break;
+            }
+            let points = batch
+                .iter()
+                .map(|record| to_point(record, import.offset, import.testid))
+                .collect::<Result<Vec<_>>>()?;
+
+            written += points.len() as u64;
+            self.db_client.query(points).await.map_err(|error| {
+                match Error::from(error) {
+                    // Writes report a missing database only in the message
+                    Error::Query(message) if message.contains("database not found") => {
+                        Error::DatabaseNotFound(self.db_client.database_name().to_string())
+                    }
+                    error => error,
+                }
+            })?;
+        }
+
+        Ok(written)
+    }
+}
+
+/// Offset of the imported data points. Moving the run to a start time is
+/// relative to the earliest exported data point.
+fn time_offset(time_shift: Option<TimeShift>, manifest: &Manifest) -> Duration {
+    match time_shift {
+        Some(TimeShift::Offset(offset)) => offset,
+        Some(TimeShift::StartAt(start)) => {
+            match manifest
+                .metrics
+                .iter()
+                .filter_map(|entry| entry.first_timestamp)
+                .min()
+            {
+                Some(first_timestamp) => start - first_timestamp,
+                None => Duration::zero(),
+            }
+        }
+        None => Duration::zero(),
+    }
+}
+
+/// Converts the record into a data point of the k6 InfluxDB output. Tags with
+/// empty values are left out, the same as k6 does, since InfluxDB doesn't
+/// accept them.
+fn to_point<T>(record: &T, offset: Duration, testid: Option<&String>) -> Result<WriteQuery>
+where
+    T: K6Metric + Serialize,
+{
+    let time = record.time() + offset;
+    let nanoseconds = time
+        .timestamp_nanos_opt()
+        .ok_or_else(|| Error::Import(format!("the shifted time {} is out of range", time)))?;
+    let mut row = to_row(record)?;
+    let mut point = WriteQuery::new(
+        Timestamp::Nanoseconds(nanoseconds as u128),
+        T::metric_table_name(),
+    );
+
+    for column in T::schema() {
+        let value = match row.remove(column.name) {
+            Some(value) => value,
+            None => continue,
+        };
+        point = match (column.kind, value) {
+            (ColumnKind::Time, _) | (_, Value::Null) => point,
+            (ColumnKind::Tag, Value::String(tag)) if tag.is_empty() => point,
+            (ColumnKind::Tag, Value::String(tag)) => point.add_tag(column.name, tag),
+            (ColumnKind::Tag, tag) => point.add_tag(column.name, tag.to_string()),
+            // k6 writes every field as a float
+            (ColumnKind::Field, Value::Number(field)) => {
+                point.add_field(column.name, field.as_f64().unwrap_or_default())
+            }
+            (ColumnKind::Field, Value::Bool(field)) => {
+                point.add_field(column.name, f64::from(u8::from(field)))
+            }
+            (ColumnKind::Field, field) => point.add_field(column.name, field.to_string()),
+        };
+    }
+    if let Some(testid) = testid {
+        point = point.add_tag("testid", testid.as_str());
+    }
+    Ok(point)
+}
+
+/// Settings shared by the imported metrics of a run.
+struct MetricImport<'a> {
+    importer: &'a RunImporter,
+    dataset: &'a Dataset,
+    offset: Duration,
+    testid: Option<&'a String>,
+}
+
+#[async_trait]
+impl MetricVisitor for MetricImport<'_> {
+    type Output = u64;
+
+    async fn visit<T>(&mut self, _optional: bool) -> Result<u64>
+    where
+        T: 'static + K6Metric + Serialize + DeserializeOwned + Send + Sync,
+    {
+        self.importer.import_metric::<T>(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use influxdb::Query;
+    use serde_json::json;
+
+    use super::*;
+    use crate::layout::RunMetadata;
+    use crate::manifest::{MetricEntry, Source, TimeWindow};
+    use crate::metrics::{HttpReqDurationMetric, VusMetric};
+
+    fn line_protocol(point: WriteQuery) -> String {
+        point.build().unwrap().get()
+    }
+
+    fn time(second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, second).unwrap()
+    }
+
+    fn manifest_with(first_timestamps: &[Option<DateTime<Utc>>]) -> Manifest {
+        let mut manifest = Manifest::new(
+            RunMetadata::new(time(0), &None, &None),
+            Source {
+                kind: String::from("influxdb"),
+                url: String::from("http://localhost:8086"),
+                database: None,
+                retention_policy: None,
+            },
+            TimeWindow {
+                from: None,
+                to: time(59),
+                trimmed: None,
+            },
+            vec![],
+        );
+        manifest.metrics = first_timestamps
+            .iter()
+            .map(|first_timestamp| MetricEntry {
+                measurement: String::from("vus"),
+                file: String::from("vus.csv"),
+                query: String::new(),
+                rows: 1,
+                first_timestamp: *first_timestamp,
+                last_timestamp: None,
+                sha256: String::new(),
+                extra_files: vec![],
+            })
+            .collect();
+        manifest
+    }
+
+    #[test]
+    fn records_become_points_of_the_k6_output() {
+        let record: HttpReqDurationMetric = serde_json::from_value(json!({
+            "time": "2024-05-01T12:00:01.5Z",
+            "expected_response": true,
+            "group": "",
+            "method": "GET",
+            "name": "/a b",
+            "proto": "HTTP/1.1",
+            "scenario": "default",
+            "status": 200,
+            "tls_version": null,
+            "url": "/a b",
+            "value": 123.5
+        }))
+        .unwrap();
+        let testid = String::from("run-1");
+
+        // Empty and missing tags are left out, spaces are escaped
+        assert_eq!(
+            line_protocol(to_point(&record, Duration::zero(), Some(&testid)).unwrap()),
+            "http_req_duration,expected_response=true,method=GET,name=/a\\ b,proto=HTTP/1.1,\
+             scenario=default,status=200,url=/a\\ b,testid=run-1 value=123.5 1714564801500000000"
+        );
+
+        let vus = VusMetric {
+            time: time(2),
+            value: 10.0,
+        };
+        assert_eq!(
+            line_protocol(to_point(&vus, Duration::seconds(-2), None).unwrap()),
+            "vus value=10 1714564800000000000"
+        );
+    }
+
+    #[test]
+    fn times_out_of_range_are_refused() {
+        let vus = VusMetric {
+            time: time(0),
+            value: 1.0,
+        };
+        let result = to_point(&vus, Duration::days(365 * 300), None);
+        assert!(matches!(result, Err(Error::Import(_))));
+    }
+
+    #[test]
+    fn runs_are_moved_relative_to_the_first_data_point() {
+        let manifest = manifest_with(&[Some(time(30)), None, Some(time(10))]);
+        assert_eq!(time_offset(None, &manifest), Duration::zero());
+        assert_eq!(
+            time_offset(Some(TimeShift::Offset(Duration::hours(1))), &manifest),
+            Duration::hours(1)
+        );
+        assert_eq!(
+            time_offset(Some(TimeShift::StartAt(time(15))), &manifest),
+            Duration::seconds(5)
+        );
+        // Nothing was exported, so there is nothing to move
+        assert_eq!(
+            time_offset(Some(TimeShift::StartAt(time(15))), &manifest_with(&[None])),
+            Duration::zero()
+        );
+    }
+}
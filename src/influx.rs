@@ -0,0 +1,410 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use chrono::SecondsFormat;
+use influxdb::integrations::serde_integration::DatabaseQueryResult;
+use influxdb::{Client, ReadQuery};
+use serde_json::Value;
+use structopt::StructOpt;
+
+use crate::error::{Error, Result};
+use crate::manifest::Source;
+use crate::source::{MetricQuery, MetricSource, QueryFilters, Row, RowBatches, FETCH_BATCH_SIZE};
+
+const DEFAULT_RETENTION_POLICY: &str = "autogen";
+
+/// Connection settings of the InfluxDB server, which can be used as a part
+/// of command line arguments.
+#[derive(StructOpt, Debug, Clone)]
+pub struct ConnectionOptions {
+    #[structopt(
+        short = "h",
+        long = "host",
+        default_value = "localhost",
+        help = " Host to connect to"
+    )]
+    pub host: String,
+
+    #[structopt(
+        short = "p",
+        long = "port",
+        default_value = "8086",
+        help = "Port to connect to"
+    )]
+    pub port: String,
+
+    #[structopt(
+        short = "d",
+        long = "database",
+        default_value = "k6",
+        help = "Database to connect to the server"
+    )]
+    pub database: String,
+
+    #[structopt(
+        short = "u",
+        long = "username",
+        help = "Username for a connection",
+        env = "K6_REPORTS_DB_USERNAME",
+        hide_env_values = true
+    )]
+    pub username: Option<String>,
+
+    #[structopt(
+        short = "s",
+        long = "password",
+        help = "Used password along with the username",
+        env = "K6_REPORTS_DB_PASSWORD",
+        hide_env_values = true
+    )]
+    pub password: Option<String>,
+
+    #[structopt(long = "--https", help = "Connecting to the database with HTTPS")]
+    pub https: bool,
+}
+
+/// Creates the InfluxDB client. Returns the connection URL as well, which is
+/// safe to show since it never contains credentials.
+pub fn create_client(connection: &ConnectionOptions) -> (String, Client) {
+    let connection_url = match connection.https {
+        true => format!("https://{0}:{1}", connection.host, connection.port),
+        false => format!("http://{0}:{1}", connection.host, connection.port),
+    };
+
+    let db_client = match (&connection.username, &connection.password) {
+        (Some(username), Some(password)) => {
+            Client::new(&connection_url, &connection.database).with_auth(username, password)
+        }
+        _ => Client::new(&connection_url, &connection.database),
+    };
+
+    (connection_url, db_client)
+}
+
+/// Executes the query and checks the result for errors of the statement.
+pub async fn execute_query(db_client: &Client, raw_query: &str) -> Result<DatabaseQueryResult> {
+    let response = db_client.json_query(ReadQuery::new(raw_query)).await?;
+
+    // Errors of a particular statement (e.g. a missing database) are returned
+    // along with the results instead of the top-level error of the response
+    if let Some(message) = response
+        .results
+        .first()
+        .and_then(|result| result.get("error"))
+        .and_then(|error| error.as_str())
+    {
+        return match message.starts_with("database not found") {
+            true => Err(Error::DatabaseNotFound(
+                db_client.database_name().to_string(),
+            )),
+            false => Err(Error::Query(message.to_string())),
+        };
+    }
+
+    Ok(response)
+}
+
+/// Quotes the value for using it as a string literal in InfluxQL.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// InfluxDB v1 database which k6 writes into with `--out influxdb`.
+pub struct InfluxSource {
+    connection_url: String,
+    db_client: Client,
+    retention_policy: String,
+}
+
+impl InfluxSource {
+    pub fn new(connection: &ConnectionOptions) -> Self {
+        let (connection_url, db_client) = create_client(connection);
+        InfluxSource {
+            connection_url,
+            db_client,
+            retention_policy: String::from(DEFAULT_RETENTION_POLICY),
+        }
+    }
+
+    pub fn with_retention_policy(mut self, retention_policy: &str) -> Self {
+        self.retention_policy = retention_policy.to_string();
+        self
+    }
+
+    /// Builds the query of the metric. Data points exported earlier and the
+    /// ones left for the next poll of the follow mode are skipped by `since`
+    /// and `until`, which aren't part of the query recorded into the manifest.
+    fn build_query(
+        &self,
+        query: &MetricQuery,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> String {
+        let mut raw_query = format!(
+            "SELECT {0} FROM {1}.{2}.{3}",
+            query.fields.join(", "),
+            self.db_client.database_name(),
+            self.retention_policy,
+            query.measurement
+        );
+
+        // Excluded groups make sense only for the metrics tagged with a group
+        let mut filters = self.describe_filters(&QueryFilters {
+            excluded_groups: match query.columns.iter().any(|column| column.name == "group") {
+                true => query.filters.excluded_groups.clone(),
+                false => vec![],
+            },
+            ..query.filters.clone()
+        });
+        if let Some(since) = since {
+            filters.push(format!(
+                "time > '{}'",
+                since.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            ));
+        }
+        if let Some(until) = until {
+            filters.push(format!(
+                "time <= '{}'",
+                until.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            ));
+        }
+
+        if !filters.is_empty() {
+            raw_query.push_str(&format!(" WHERE {0}", filters.join(" AND ")));
+        }
+
+        raw_query
+    }
+}
+
+#[async_trait]
+impl MetricSource for InfluxSource {
+    fn describe(&self) -> Source {
+        Source {
+            kind: String::from("influxdb"),
+            url: self.connection_url.clone(),
+            database: Some(self.db_client.database_name().to_string()),
+            retention_policy: Some(self.retention_policy.clone()),
+        }
+    }
+
+    fn describe_filters(&self, filters: &QueryFilters) -> Vec<String> {
+        let mut result = vec![];
+
+        if let Some(from) = filters.from {
+            result.push(format!(
+                "time > '{}'",
+                from.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+        }
+
+        if let Some(trimmed_window) = &filters.trimmed_window {
+            result.push(format!(
+                "time >= '{}' AND time <= '{}'",
+                trimmed_window
+                    .from
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                trimmed_window
+                    .to
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+        }
+
+        if let Some(testid) = &filters.testid {
+            result.push(format!(r#""testid"={}"#, quote_literal(testid)));
+        }
+
+        for group in &filters.excluded_groups {
+            result.push(format!(r#""group"!={}"#, quote_literal(group)));
+        }
+
+        result
+    }
+
+    fn describe_query(&self, query: &MetricQuery) -> String {
+        self.build_query(query, None, None)
+    }
+
+    async fn measurements(&self, filters: &QueryFilters) -> Result<HashSet<String>> {
+        // Only tags can be used in the conditions of `SHOW MEASUREMENTS`
+        let raw_query = match &filters.testid {
+            Some(testid) => format!(
+                r#"SHOW MEASUREMENTS WHERE "testid"={}"#,
+                quote_literal(testid)
+            ),
+            None => String::from("SHOW MEASUREMENTS"),
+        };
+        let response = execute_query(&self.db_client, &raw_query).await?;
+        Ok(series_rows(&response)
+            .into_iter()
+            .filter_map(|mut row| match row.remove("name") {
+                Some(Value::String(name)) => Some(name),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn fetch(&self, query: &MetricQuery) -> Result<Box<dyn RowBatches + '_>> {
+        Ok(Box::new(InfluxBatches {
+            db_client: &self.db_client,
+            raw_query: self.build_query(query, query.since, query.filters.until),
+            offset: 0,
+            finished: false,
+        }))
+    }
+}
+
+/// Pages through the data points of a query with `LIMIT` and `OFFSET`, so
+/// the whole measurement isn't sent in a single response.
+struct InfluxBatches<'a> {
+    db_client: &'a Client,
+    raw_query: String,
+    offset: usize,
+    finished: bool,
+}
+
+impl InfluxBatches<'_> {
+    fn page_query(&self) -> String {
+        format!(
+            "{0} LIMIT {1} OFFSET {2}",
+            self.raw_query, FETCH_BATCH_SIZE, self.offset
+        )
+    }
+}
+
+#[async_trait]
+impl RowBatches for InfluxBatches<'_> {
+    async fn next_batch(&mut self) -> Result<Option<Vec<Row>>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let response = execute_query(self.db_client, &self.page_query()).await?;
+        let rows = series_rows(&response);
+        self.offset += rows.len();
+        self.finished = rows.len() < FETCH_BATCH_SIZE;
+        Ok(Some(rows).filter(|rows| !rows.is_empty()))
+    }
+}
+
+/// Converts the series of the first statement into rows keyed by the column
+/// names.
+fn series_rows(response: &DatabaseQueryResult) -> Vec<Row> {
+    let series = response
+        .results
+        .first()
+        .and_then(|result| result.get("series"))
+        .and_then(|series| series.as_array());
+
+    let mut rows = vec![];
+    for series in series.into_iter().flatten() {
+        let columns: Vec<&str> = series
+            .get("columns")
+            .and_then(|columns| columns.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|column| column.as_str())
+            .collect();
+        let values = series
+            .get("values")
+            .and_then(|values| values.as_array())
+            .into_iter()
+            .flatten();
+        for value in values.filter_map(|value| value.as_array()) {
+            rows.push(
+                columns
+                    .iter()
+                    .map(|column| column.to_string())
+                    .zip(value.iter().cloned())
+                    .collect(),
+            );
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::manifest::TrimmedWindow;
+    use crate::metrics::{GroupDurationMetric, VusMetric};
+
+    fn source() -> InfluxSource {
+        InfluxSource::new(&ConnectionOptions {
+            host: String::from("localhost"),
+            port: String::from("8086"),
+            database: String::from("k6"),
+            username: None,
+            password: None,
+            https: false,
+        })
+    }
+
+    fn filters() -> QueryFilters {
+        QueryFilters {
+            from: Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()),
+            testid: Some(String::from("checkout's")),
+            excluded_groups: vec![String::from("::setup")],
+            ..QueryFilters::default()
+        }
+    }
+
+    #[test]
+    fn recorded_query_has_the_filters_only() {
+        let query = MetricQuery::new::<GroupDurationMetric>(QueryFilters {
+            until: Some(Utc.with_ymd_and_hms(2024, 5, 1, 13, 0, 0).unwrap()),
+            ..filters()
+        })
+        .since(Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap()));
+        assert_eq!(
+            source().describe_query(&query),
+            r#"SELECT time, "group", "scenario", value FROM k6.autogen.group_duration WHERE time > '2024-05-01T12:00:00Z' AND "testid"='checkout\'s' AND "group"!='::setup'"#
+        );
+    }
+
+    #[test]
+    fn fetched_data_points_are_bounded_by_since_and_until() {
+        let query = MetricQuery::new::<VusMetric>(QueryFilters {
+            trimmed_window: Some(TrimmedWindow {
+                method: String::from("fixed"),
+                from: Utc.with_ymd_and_hms(2024, 5, 1, 12, 5, 0).unwrap(),
+                to: Utc.with_ymd_and_hms(2024, 5, 1, 12, 55, 0).unwrap(),
+            }),
+            ..filters()
+        });
+        let since = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 5, 1, 12, 40, 0).unwrap();
+
+        // `vus` isn't tagged with a group, so the excluded groups are ignored
+        assert_eq!(
+            source().build_query(&query, Some(since), Some(until)),
+            "SELECT time, value FROM k6.autogen.vus WHERE time > '2024-05-01T12:00:00Z' \
+             AND time >= '2024-05-01T12:05:00Z' AND time <= '2024-05-01T12:55:00Z' \
+             AND \"testid\"='checkout\\'s' \
+             AND time > '2024-05-01T12:30:00Z' AND time <= '2024-05-01T12:40:00Z'"
+        );
+    }
+
+    #[test]
+    fn data_points_are_fetched_in_pages() {
+        let source = source();
+        let query = MetricQuery::new::<VusMetric>(QueryFilters::default());
+        let batches = InfluxBatches {
+            db_client: &source.db_client,
+            raw_query: source.build_query(&query, None, None),
+            offset: 20_000,
+            finished: false,
+        };
+        assert_eq!(
+            batches.page_query(),
+            "SELECT time, value FROM k6.autogen.vus LIMIT 10000 OFFSET 20000"
+        );
+    }
+
+    #[test]
+    fn literals_are_escaped() {
+        assert_eq!(quote_literal(r"a\'b"), r"'a\\\'b'");
+    }
+}
@@ -0,0 +1,635 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use csv_async::{AsyncReaderBuilder, StringRecord, StringRecordsIntoStream};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio_stream::StreamExt;
+
+use crate::compression::{create_reader, Compression, InputReader};
+use crate::error::{Error, Result};
+use crate::manifest::Source;
+use crate::source::{
+    point_row, MetricQuery, MetricSource, QueryFilters, Row, RowBatches, FETCH_BATCH_SIZE,
+};
+
+/// Columns of the k6 CSV output which aren't tags.
+const CSV_METRIC_NAME: &str = "metric_name";
+const CSV_TIMESTAMP: &str = "timestamp";
+const CSV_METRIC_VALUE: &str = "metric_value";
+const CSV_EXTRA_TAGS: &str = "extra_tags";
+const CSV_METADATA: &str = "metadata";
+
+/// Formats of the files written by k6 itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum K6OutputFormat {
+    /// `k6 run --out json=results.json`
+    Json,
+    /// `k6 run --out csv=results.csv`
+    Csv,
+}
+
+impl K6OutputFormat {
+    /// Detects the format by the file extension, e.g. `results.json.gz`.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let filename = match Compression::from_filename(filename) {
+            Some(compression) => {
+                filename.trim_end_matches(&format!(".{}", compression.extension()))
+            }
+            None => filename,
+        };
+        match Path::new(filename).extension()?.to_str()? {
+            "json" => Some(K6OutputFormat::Json),
+            "csv" => Some(K6OutputFormat::Csv),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            K6OutputFormat::Json => "k6-json",
+            K6OutputFormat::Csv => "k6-csv",
+        }
+    }
+}
+
+/// Data point as it's written by k6, before it's shaped into the columns of
+/// a metric.
+struct DataPoint {
+    time: DateTime<Utc>,
+    value: f64,
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct JsonLine {
+    #[serde(rename = "type")]
+    kind: String,
+    metric: String,
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    time: DateTime<Utc>,
+    value: f64,
+    tags: Option<HashMap<String, Value>>,
+}
+
+/// Results file written by the k6 JSON or CSV output. The file is read line
+/// by line on every query and only the data points of the queried metric are
+/// kept, so big files don't have to fit into memory and the data points
+/// added to the file afterwards are picked up by the follow mode. The cost is
+/// a pass over the whole file, decompressing it again, for every exported
+/// metric.
+pub struct K6OutputSource {
+    path: PathBuf,
+    format: K6OutputFormat,
+}
+
+impl K6OutputSource {
+    /// Opens the file, which can be compressed with gzip or zstd. The format
+    /// is detected by the file extension.
+    pub async fn load(path: &Path) -> Result<Self> {
+        let filename = path.to_string_lossy().to_string();
+        let format = K6OutputFormat::from_filename(&filename).ok_or_else(|| Error::Input {
+            path: path.to_path_buf(),
+            reason: String::from("unknown format, expected a .json or .csv file"),
+        })?;
+        File::open(path).await?;
+
+        Ok(K6OutputSource {
+            path: path.to_path_buf(),
+            format,
+        })
+    }
+}
+
+fn matches(point: &DataPoint, query: &MetricQuery, has_group: bool) -> bool {
+    let filters = &query.filters;
+    let after = |time: Option<DateTime<Utc>>| time.is_none_or(|time| point.time > time);
+    let within_trimmed_window = filters
+        .trimmed_window
+        .as_ref()
+        .is_none_or(|window| point.time >= window.from && point.time <= window.to);
+    let has_testid = filters
+        .testid
+        .as_ref()
+        .is_none_or(|testid| point.tags.get("testid") == Some(testid));
+    let is_excluded_group = has_group
+        && filters
+            .excluded_groups
+            .iter()
+            .any(|group| point.tags.get("group") == Some(group));
+
+    after(filters.from)
+        && after(query.since)
+        && filters.until.is_none_or(|until| point.time <= until)
+        && within_trimmed_window
+        && has_testid
+        && !is_excluded_group
+}
+
+#[async_trait]
+impl MetricSource for K6OutputSource {
+    fn describe(&self) -> Source {
+        Source {
+            kind: self.format.kind().to_string(),
+            url: self.path.to_string_lossy().to_string(),
+            database: None,
+            retention_policy: None,
+        }
+    }
+
+    fn describe_filters(&self, filters: &QueryFilters) -> Vec<String> {
+        let mut result = vec![];
+
+        if let Some(from) = filters.from {
+            result.push(format!("time > {}", format_time(from)));
+        }
+
+        if let Some(trimmed_window) = &filters.trimmed_window {
+            result.push(format!(
+                "time >= {} AND time <= {}",
+                format_time(trimmed_window.from),
+                format_time(trimmed_window.to)
+            ));
+        }
+
+        if let Some(testid) = &filters.testid {
+            result.push(format!("testid = {:?}", testid));
+        }
+
+        for group in &filters.excluded_groups {
+            result.push(format!("group != {:?}", group));
+        }
+
+        result
+    }
+
+    fn describe_query(&self, query: &MetricQuery) -> String {
+        let columns: Vec<&str> = query.columns.iter().map(|column| column.name).collect();
+        format!(
+            "{0} points of {1} with {2}",
+            query.measurement,
+            self.path.display(),
+            columns.join(", ")
+        )
+    }
+
+    async fn measurements(&self, filters: &QueryFilters) -> Result<HashSet<String>> {
+        let query = MetricQuery {
+            measurement: "",
+            fields: &[],
+            columns: &[],
+            filters: filters.clone(),
+            since: None,
+        };
+        let mut reader = PointReader::open(&self.path, self.format).await?;
+        let mut result = HashSet::new();
+        while let Some((metric, point)) = reader.next_point().await? {
+            if matches(&point, &query, false) {
+                result.insert(metric);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn fetch(&self, query: &MetricQuery) -> Result<Box<dyn RowBatches + '_>> {
+        Ok(Box::new(K6OutputBatches {
+            reader: PointReader::open(&self.path, self.format).await?,
+            has_group: query.columns.iter().any(|column| column.name == "group"),
+            query: query.clone(),
+        }))
+    }
+}
+
+/// Data points of the queried metric, collected while reading the file.
+struct K6OutputBatches {
+    reader: PointReader,
+    query: MetricQuery,
+    has_group: bool,
+}
+
+#[async_trait]
+impl RowBatches for K6OutputBatches {
+    async fn next_batch(&mut self) -> Result<Option<Vec<Row>>> {
+        let mut points = vec![];
+        while points.len() < FETCH_BATCH_SIZE {
+            match self.reader.next_point().await? {
+                Some((metric, point))
+                    if metric == self.query.measurement
+                        && matches(&point, &self.query, self.has_group) =>
+                {
+                    points.push(point)
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        // Points of concurrent VUs can be written slightly out of order, which
+        // is fixed within each batch, so the whole file doesn't have to be
+        // kept in memory
+        points.sort_by_key(|point| point.time);
+
+        Ok(Some(points)
+            .filter(|points| !points.is_empty())
+            .map(|points| {
+                points
+                    .iter()
+                    .map(|point| {
+                        point_row(self.query.columns, point.time, point.value, &point.tags)
+                    })
+                    .collect()
+            }))
+    }
+}
+
+/// Reads the data points of the file one by one.
+struct PointReader {
+    path: PathBuf,
+    line_number: usize,
+    lines: PointLines,
+}
+
+enum PointLines {
+    Json(Lines<BufReader<InputReader>>),
+    Csv {
+        headers: StringRecord,
+        metric_name_index: usize,
+        timestamp_index: usize,
+        metric_value_index: usize,
+        records: StringRecordsIntoStream<'static, InputReader>,
+    },
+}
+
+impl PointReader {
+    async fn open(path: &Path, format: K6OutputFormat) -> Result<Self> {
+        let input_file = File::open(path).await?;
+        let compression = Compression::from_filename(&path.to_string_lossy());
+        let input_reader = create_reader(input_file, compression);
+        let lines = match format {
+            K6OutputFormat::Json => PointLines::Json(BufReader::new(input_reader).lines()),
+            K6OutputFormat::Csv => {
+                let mut csv_reader = AsyncReaderBuilder::new().create_reader(input_reader);
+                let headers = csv_reader.headers().await?.clone();
+                let column_index = |name: &str| {
+                    headers
+                        .iter()
+                        .position(|header| header == name)
+                        .ok_or_else(|| Error::Input {
+                            path: path.to_path_buf(),
+                            reason: format!("the `{}` column is missing", name),
+                        })
+                };
+                PointLines::Csv {
+                    metric_name_index: column_index(CSV_METRIC_NAME)?,
+                    timestamp_index: column_index(CSV_TIMESTAMP)?,
+                    metric_value_index: column_index(CSV_METRIC_VALUE)?,
+                    headers,
+                    records: csv_reader.into_records(),
+                }
+            }
+        };
+
+        Ok(PointReader {
+            path: path.to_path_buf(),
+            // The header is the first line of the CSV output
+            line_number: match format {
+                K6OutputFormat::Json => 0,
+                K6OutputFormat::Csv => 1,
+            },
+            lines,
+        })
+    }
+
+    /// Returns the next data point along with the name of its metric, or
+    /// `None` at the end of the file.
+    async fn next_point(&mut self) -> Result<Option<(String, DataPoint)>> {
+        match &mut self.lines {
+            PointLines::Json(lines) => {
+                while let Some(line) = lines.next_line().await? {
+                    self.line_number += 1;
+                    if let Some(point) = parse_json_line(&line)
+                        .map_err(|reason| invalid_line(&self.path, self.line_number, reason))?
+                    {
+                        return Ok(Some(point));
+                    }
+                }
+                Ok(None)
+            }
+            PointLines::Csv {
+                headers,
+                metric_name_index,
+                timestamp_index,
+                metric_value_index,
+                records,
+            } => match records.next().await {
+                Some(record) => {
+                    let record = record?;
+                    self.line_number += 1;
+                    let point =
+                        parse_csv_record(headers, &record, *timestamp_index, *metric_value_index)
+                            .map_err(|reason| invalid_line(&self.path, self.line_number, reason))?;
+                    let metric = record.get(*metric_name_index).unwrap_or_default();
+                    Ok(Some((metric.to_string(), point)))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+fn invalid_line(path: &Path, line_number: usize, reason: String) -> Error {
+    Error::Input {
+        path: path.to_path_buf(),
+        reason: format!("line {}: {}", line_number, reason),
+    }
+}
+
+/// Parses a line of the JSON output, which describes either a metric or a
+/// data point of it, e.g. `{"type":"Point","metric":"vus","data":{"time":
+/// "...","value":10,"tags":{}}}`. Only the data points are used.
+fn parse_json_line(line: &str) -> std::result::Result<Option<(String, DataPoint)>, String> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let json_line: JsonLine = serde_json::from_str(line).map_err(|error| error.to_string())?;
+    if json_line.kind != "Point" {
+        return Ok(None);
+    }
+
+    let point: JsonPoint =
+        serde_json::from_value(json_line.data).map_err(|error| error.to_string())?;
+    let tags = point
+        .tags
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::String(value) => (name, value),
+            value => (name, value.to_string()),
+        })
+        .collect();
+    Ok(Some((
+        json_line.metric,
+        DataPoint {
+            time: point.time,
+            value: point.value,
+            tags,
+        },
+    )))
+}
+
+/// Parses a row of the CSV output. Besides the metric name, timestamp and
+/// value columns, each system tag has its own column, while other tags are
+/// joined in the `extra_tags` column as `name=value&name=value`.
+fn parse_csv_record(
+    headers: &StringRecord,
+    record: &StringRecord,
+    timestamp_index: usize,
+    metric_value_index: usize,
+) -> std::result::Result<DataPoint, String> {
+    let timestamp = record.get(timestamp_index).unwrap_or_default();
+    let time =
+        parse_timestamp(timestamp).ok_or_else(|| format!("invalid timestamp `{}`", timestamp))?;
+    let value = record.get(metric_value_index).unwrap_or_default();
+    let value = value
+        .parse()
+        .map_err(|_| format!("invalid value `{}`", value))?;
+
+    let mut tags = HashMap::new();
+    for (header, field) in headers.iter().zip(record.iter()) {
+        match header {
+            CSV_METRIC_NAME | CSV_TIMESTAMP | CSV_METRIC_VALUE | CSV_METADATA => {}
+            CSV_EXTRA_TAGS => {
+                let extra_tags = field
+                    .split('&')
+                    .filter_map(|tag| tag.split_once('='))
+                    .filter(|(_, value)| !value.is_empty());
+                for (name, value) in extra_tags {
+                    tags.insert(name.to_string(), value.to_string());
+                }
+            }
+            _ if field.is_empty() => {}
+            _ => {
+                tags.insert(header.to_string(), field.to_string());
+            }
+        }
+    }
+
+    Ok(DataPoint { time, value, tags })
+}
+
+/// Parses timestamps of any `timeFormat` of the CSV output. Unix timestamps
+/// are told apart by the number of digits, so seconds, milliseconds,
+/// microseconds and nanoseconds since 2001 are recognized.
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = timestamp.parse::<DateTime<Utc>>() {
+        return Some(time);
+    }
+
+    let number: i64 = timestamp.parse().ok()?;
+    let time = match timestamp.trim_start_matches('-').len() {
+        0..=10 => Utc.timestamp_opt(number, 0).single()?,
+        11..=13 => Utc.timestamp_millis_opt(number).single()?,
+        14..=16 => Utc.timestamp_micros(number).single()?,
+        _ => Utc.timestamp_nanos(number),
+    };
+    Some(time)
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{HttpReqDurationMetric, VusMetric};
+    use crate::source::fetch_metric;
+    use crate::test_support::test_directory;
+
+    const JSON_RESULTS: &str = r#"{"type":"Metric","data":{"name":"vus","type":"gauge","contains":"default","thresholds":[],"submetrics":null},"metric":"vus"}
+{"type":"Point","data":{"time":"2024-05-01T10:00:02Z","value":2,"tags":{"testid":"run-1"}},"metric":"vus"}
+{"type":"Point","data":{"time":"2024-05-01T10:00:01Z","value":1,"tags":{"testid":"run-1"}},"metric":"vus"}
+
+{"type":"Point","data":{"time":"2024-05-01T10:00:03Z","value":5,"tags":{"testid":"run-2"}},"metric":"vus"}
+{"type":"Point","data":{"time":"2024-05-01T10:00:01.5Z","value":120.5,"tags":{"expected_response":"true","group":"::login","method":"GET","name":"https://test.k6.io/","status":"200","url":"https://test.k6.io/","testid":"run-1"}},"metric":"http_req_duration"}
+{"type":"Point","data":{"time":"2024-05-01T10:00:02.5Z","value":80,"tags":{"expected_response":"false","group":"::setup","status":404,"url":"https://test.k6.io/missing","testid":"run-1"}},"metric":"http_req_duration"}
+"#;
+
+    const CSV_RESULTS: &str = "metric_name,timestamp,metric_value,check,error,error_code,expected_response,group,method,name,proto,scenario,service,status,subproto,tls_version,url,extra_tags,metadata
+http_req_duration,1714557601,120.5,,,,true,::login,GET,https://test.k6.io/,HTTP/1.1,default,,200,,,https://test.k6.io/,testid=run-1&region=eu,
+vus,1714557602000,3,,,,,,,,,,,,,,,testid=run-1,
+vus,1714557601000,2,,,,,,,,,,,,,,,testid=run-2,
+";
+
+    async fn write_fixture(name: &str, contents: &str) -> PathBuf {
+        let path = test_directory("k6-output", name).join(name);
+        tokio::fs::write(&path, contents).await.unwrap();
+        path
+    }
+
+    async fn fetch_rows(source: &K6OutputSource, query: &MetricQuery) -> Vec<Row> {
+        let mut batches = source.fetch(query).await.unwrap();
+        let mut rows = vec![];
+        while let Some(batch) = batches.next_batch().await.unwrap() {
+            rows.extend(batch);
+        }
+        rows
+    }
+
+    fn filters(testid: &str) -> QueryFilters {
+        QueryFilters {
+            testid: Some(testid.to_string()),
+            ..QueryFilters::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_the_json_output() {
+        let path = write_fixture("results.json", JSON_RESULTS).await;
+        let source = K6OutputSource::load(&path).await.unwrap();
+
+        let measurements = source.measurements(&QueryFilters::default()).await.unwrap();
+        assert_eq!(
+            measurements,
+            HashSet::from([String::from("vus"), String::from("http_req_duration")])
+        );
+
+        let query = MetricQuery::new::<VusMetric>(filters("run-1"));
+        let vus: Vec<VusMetric> = fetch_metric(&source, &query).await.unwrap();
+        let values: Vec<f64> = vus.iter().map(|point| point.value).collect();
+        assert_eq!(values, [1.0, 2.0]);
+
+        let mut query_filters = filters("run-1");
+        query_filters.excluded_groups = vec![String::from("::setup")];
+        let query = MetricQuery::new::<HttpReqDurationMetric>(query_filters);
+        let durations: Vec<HttpReqDurationMetric> = fetch_metric(&source, &query).await.unwrap();
+        assert_eq!(durations.len(), 1);
+        assert_eq!(durations[0].status, 200);
+        assert_eq!(durations[0].group.as_deref(), Some("::login"));
+        assert_eq!(durations[0].proto, None);
+
+        let query = MetricQuery::new::<HttpReqDurationMetric>(filters("run-1"));
+        let durations: Vec<HttpReqDurationMetric> = fetch_metric(&source, &query).await.unwrap();
+        assert_eq!(durations.len(), 2);
+        assert_eq!(durations[1].status, 404);
+        assert!(!durations[1].expected_response);
+
+        tokio::fs::remove_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn picks_up_appended_data_points() {
+        let path = write_fixture("appended.json", JSON_RESULTS).await;
+        let source = K6OutputSource::load(&path).await.unwrap();
+        let query = MetricQuery::new::<VusMetric>(filters("run-2"));
+        assert_eq!(fetch_rows(&source, &query).await.len(), 1);
+
+        let appended = r#"{"type":"Point","data":{"time":"2024-05-01T10:00:04Z","value":6,"tags":{"testid":"run-2"}},"metric":"vus"}"#;
+        tokio::fs::write(&path, format!("{}{}\n", JSON_RESULTS, appended))
+            .await
+            .unwrap();
+        let since = "2024-05-01T10:00:03Z".parse().ok();
+        let rows = fetch_rows(&source, &query.clone().since(since)).await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["value"], Value::from(6.0));
+
+        tokio::fs::remove_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_the_points_in_batches() {
+        let lines: String = (0..FETCH_BATCH_SIZE + 1)
+            .map(|second| {
+                format!(
+                    "{{\"type\":\"Point\",\"data\":{{\"time\":\"{}\",\"value\":1}},\"metric\":\"vus\"}}\n",
+                    format_time(Utc.timestamp_opt(1714557600 + second as i64, 0).unwrap())
+                )
+            })
+            .collect();
+        let path = write_fixture("batches.json", &lines).await;
+        let source = K6OutputSource::load(&path).await.unwrap();
+
+        let query = MetricQuery::new::<VusMetric>(QueryFilters::default());
+        let mut batches = source.fetch(&query).await.unwrap();
+        let first = batches.next_batch().await.unwrap().unwrap();
+        assert_eq!(first.len(), FETCH_BATCH_SIZE);
+        let second = batches.next_batch().await.unwrap().unwrap();
+        assert_eq!(second[0]["time"], Value::from("2024-05-01T12:46:40Z"));
+        assert!(batches.next_batch().await.unwrap().is_none());
+
+        tokio::fs::remove_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_invalid_json_lines() {
+        let path = write_fixture("invalid.json", "{\"type\":\"Point\"}\n").await;
+        let source = K6OutputSource::load(&path).await.unwrap();
+        let error = source
+            .measurements(&QueryFilters::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("line 1"), "{}", error);
+
+        tokio::fs::remove_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_the_csv_output() {
+        let path = write_fixture("results.csv", CSV_RESULTS).await;
+        let source = K6OutputSource::load(&path).await.unwrap();
+
+        let query = MetricQuery::new::<HttpReqDurationMetric>(filters("run-1"));
+        let rows = fetch_rows(&source, &query).await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["time"], Value::from("2024-05-01T10:00:01Z"));
+        assert_eq!(rows[0]["proto"], Value::from("HTTP/1.1"));
+        assert_eq!(rows[0]["tls_version"], Value::Null);
+        assert_eq!(rows[0]["value"], Value::from(120.5));
+
+        let query = MetricQuery::new::<VusMetric>(QueryFilters::default());
+        let vus: Vec<VusMetric> = fetch_metric(&source, &query).await.unwrap();
+        let values: Vec<f64> = vus.iter().map(|point| point.value).collect();
+        assert_eq!(values, [2.0, 3.0]);
+
+        tokio::fs::remove_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn detects_the_format() {
+        let format = K6OutputFormat::from_filename;
+        assert_eq!(format("results.json"), Some(K6OutputFormat::Json));
+        assert_eq!(format("results.csv.gz"), Some(K6OutputFormat::Csv));
+        assert_eq!(format("results.json.zst"), Some(K6OutputFormat::Json));
+        assert_eq!(format("results.txt"), None);
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        let expected = "2024-05-01T10:00:01Z".parse::<DateTime<Utc>>().ok();
+        assert_eq!(parse_timestamp("2024-05-01T10:00:01Z"), expected);
+        assert_eq!(parse_timestamp("1714557601"), expected);
+        assert_eq!(parse_timestamp("1714557601000"), expected);
+        assert_eq!(parse_timestamp("1714557601000000"), expected);
+        assert_eq!(parse_timestamp("1714557601000000000"), expected);
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+}
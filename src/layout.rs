@@ -0,0 +1,389 @@
+use std::cmp::Reverse;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read, read_dir, remove_file, write};
+
+use crate::archive::ensure_not_archive;
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+use crate::manifest::{Manifest, MANIFEST_FILENAME};
+use crate::metrics::builtin_measurements;
+
+pub const INDEX_FILENAME: &str = "index.json";
+
+/// Name of the run exported right into the root directory (the flat layout).
+pub const ROOT_RUN_NAME: &str = "~";
+
+/// Extensions of the files written by the exporters, before the compression.
+const METRIC_FILE_EXTENSIONS: &[&str] = &["csv", "jsonl", "parquet"];
+
+/// Information that identifies a single test run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunMetadata {
+    pub started_at: DateTime<Utc>,
+    pub testid: Option<String>,
+    pub profile: Option<String>,
+    pub git_sha: Option<String>,
+}
+
+impl RunMetadata {
+    pub fn new(
+        started_at: DateTime<Utc>,
+        testid: &Option<String>,
+        profile: &Option<String>,
+    ) -> Self {
+        RunMetadata {
+            started_at,
+            testid: testid.to_owned(),
+            profile: profile.to_owned(),
+            git_sha: None,
+        }
+    }
+
+    /// Commit of the tested code, which is known only when it's given.
+    pub fn with_git_sha(mut self, git_sha: Option<String>) -> Self {
+        self.git_sha = git_sha;
+        self
+    }
+
+    /// Renders the run directory name from the template with the `{date}`,
+    /// `{testid}`, `{git_sha}` and `{profile}` placeholders. Path separators
+    /// are replaced, and so are the `.` and `..` names, so the run can't be
+    /// exported outside of the root directory.
+    pub fn render(&self, template: &str) -> String {
+        let date = self.started_at.format("%Y-%m-%dT%H-%M-%SZ").to_string();
+        let unknown = String::from("unknown");
+        let rendered = template
+            .replace("{date}", &date)
+            .replace("{testid}", self.testid.as_ref().unwrap_or(&unknown))
+            .replace("{git_sha}", self.git_sha.as_ref().unwrap_or(&unknown))
+            .replace("{profile}", self.profile.as_ref().unwrap_or(&unknown));
+
+        let rendered: String = rendered
+            .chars()
+            .map(|character| match character {
+                '/' | '\\' | ':' => '_',
+                _ => character,
+            })
+            .collect();
+        match rendered.as_str() {
+            "" | "." | ".." => format!("_{}", rendered),
+            _ => rendered,
+        }
+    }
+}
+
+/// Lists all runs exported into the root output directory.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RunIndex {
+    pub runs: Vec<RunIndexEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunIndexEntry {
+    pub directory: String,
+    pub manifest: String,
+    #[serde(flatten)]
+    pub run: RunMetadata,
+}
+
+impl RunIndex {
+    pub async fn load(root_directory: &Path) -> Result<Self> {
+        let path = root_directory.join(INDEX_FILENAME);
+        match path.exists() {
+            true => Ok(serde_json::from_slice(&read(path).await?)?),
+            false => Ok(RunIndex::default()),
+        }
+    }
+
+    pub async fn save(&self, root_directory: &Path) -> Result<()> {
+        let content = serde_json::to_vec_pretty(self)?;
+        write(root_directory.join(INDEX_FILENAME), content).await?;
+        Ok(())
+    }
+
+    /// Adds the run to the index, replacing a previous entry for the same directory.
+    pub fn register(&mut self, directory: &str, run: &RunMetadata) {
+        self.runs.retain(|entry| entry.directory != directory);
+        self.runs.push(RunIndexEntry {
+            directory: directory.to_string(),
+            manifest: format!("{}/{}", directory, MANIFEST_FILENAME),
+            run: run.clone(),
+        });
+        self.runs.sort_by_key(|entry| entry.run.started_at);
+    }
+}
+
+/// Resolves the directory for the exported files. With a template, each run
+/// gets its own subdirectory in the root output directory.
+pub fn run_directory(
+    root_directory: &str,
+    template: &Option<String>,
+    run: &RunMetadata,
+) -> Result<PathBuf> {
+    let template = match template {
+        Some(template) => template,
+        None => return Ok(PathBuf::from(root_directory)),
+    };
+
+    let name = run.render(template);
+    let mut components = Path::new(&name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(Path::new(root_directory).join(name)),
+        _ => Err(Error::Export(format!(
+            "the layout template renders into `{}`, which isn't a directory name",
+            name
+        ))),
+    }
+}
+
+/// Fails when the directory already contains exported data, so that a previous
+/// run can't be silently overwritten. Files of metrics count as well, e.g.
+/// when an earlier export was interrupted before writing the manifest.
+pub async fn ensure_not_exported(directory: &Path) -> Result<()> {
+    if Manifest::exists(directory) {
+        return Err(Error::OutputExists(directory.to_path_buf()));
+    }
+    if !directory.is_dir() {
+        return Ok(());
+    }
+
+    let measurements = builtin_measurements().await?;
+    let mut entries = read_dir(directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if is_metric_file(&entry.file_name().to_string_lossy(), &measurements) {
+            return Err(Error::OutputExists(directory.to_path_buf()));
+        }
+    }
+    Ok(())
+}
+
+/// Tells whether the exporters write files of this name, e.g. `vus.csv.gz`.
+fn is_metric_file(filename: &str, measurements: &[&str]) -> bool {
+    let filename = match Compression::from_filename(filename) {
+        Some(compression) => filename.trim_end_matches(&format!(".{}", compression.extension())),
+        None => filename,
+    };
+    match filename.rsplit_once('.') {
+        Some((measurement, extension)) => {
+            METRIC_FILE_EXTENSIONS.contains(&extension) && measurements.contains(&measurement)
+        }
+        None => false,
+    }
+}
+
+/// Removes the files of the export listed in the manifest of the directory,
+/// so that files of metrics missing in the new export don't stay behind.
+/// Other files are kept.
+pub async fn remove_exported_files(directory: &Path) -> Result<()> {
+    if !Manifest::exists(directory) {
+        return Ok(());
+    }
+
+    let manifest = Manifest::load(directory).await?;
+    let files = manifest.metrics.iter().flat_map(|entry| {
+        std::iter::once(&entry.file).chain(entry.extra_files.iter().map(|extra| &extra.file))
+    });
+    for file in files {
+        let path = directory.join(file);
+        // Only plain names are written into the manifest
+        if path.parent() != Some(directory) || !path.exists() {
+            continue;
+        }
+        remove_file(path).await?;
+    }
+    remove_file(directory.join(MANIFEST_FILENAME)).await?;
+
+    Ok(())
+}
+
+/// Exported run found in the root output directory.
+#[derive(Debug, Clone)]
+pub struct ExportedRun {
+    pub name: String,
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl ExportedRun {
+    /// Opens the run directory given by the user, which is named by its path.
+    pub async fn open(directory: &str) -> Result<Self> {
+        let path = PathBuf::from(directory);
+        ensure_not_archive(&path)?;
+        Ok(ExportedRun {
+            name: directory.to_string(),
+            manifest: Manifest::load(&path).await?,
+            path,
+        })
+    }
+}
+
+/// Finds all exported runs: the root directory itself (for the flat layout)
+/// and its subdirectories with a manifest. The newest runs go first. Runs
+/// with a manifest that can't be read are skipped with a warning, so a broken
+/// run doesn't hide all others.
+pub async fn discover_runs(root_directory: &Path) -> Result<Vec<ExportedRun>> {
+    ensure_not_archive(root_directory)?;
+    let mut runs = vec![];
+    if Manifest::exists(root_directory) {
+        runs.extend(load_run(String::from(ROOT_RUN_NAME), root_directory.to_path_buf()).await);
+    }
+
+    let mut entries = read_dir(root_directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.is_dir() && Manifest::exists(&path) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            runs.extend(load_run(name, path).await);
+        }
+    }
+
+    runs.sort_by_key(|run| Reverse(run.manifest.run.started_at));
+    Ok(runs)
+}
+
+async fn load_run(name: String, path: PathBuf) -> Option<ExportedRun> {
+    match Manifest::load(&path).await {
+        Ok(manifest) => Some(ExportedRun {
+            name,
+            path,
+            manifest,
+        }),
+        Err(error) => {
+            eprintln!("warning: skipping `{}`: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_support::test_directory;
+
+    fn run(testid: &str) -> RunMetadata {
+        RunMetadata::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+            &Some(testid.to_string()),
+            &None,
+        )
+        .with_git_sha(Some(String::from("1a2b3c4")))
+    }
+
+    fn manifest() -> serde_json::Value {
+        serde_json::json!({
+            "tool_version": "0.1.0",
+            "created_at": "2024-05-01T12:30:00Z",
+            "run": {"started_at": "2024-05-01T12:30:00Z", "testid": null, "profile": null, "git_sha": null},
+            "source": {"kind": "influxdb", "url": "http://localhost:8086"},
+            "time_window": {"from": null, "to": "2024-05-01T12:40:00Z"},
+            "filters": [],
+            "metrics": [{
+                "measurement": "vus",
+                "file": "vus.csv",
+                "query": "SELECT time, value FROM vus",
+                "rows": 0,
+                "first_timestamp": null,
+                "last_timestamp": null,
+                "sha256": "",
+                "extra_files": [{"format": "json", "file": "vus.jsonl", "sha256": ""}]
+            }]
+        })
+    }
+
+    #[test]
+    fn placeholders_are_rendered() {
+        assert_eq!(
+            run("checkout").render("{date}-{testid}-{git_sha}-{profile}"),
+            "2024-05-01T12-30-00Z-checkout-1a2b3c4-unknown"
+        );
+    }
+
+    #[test]
+    fn rendered_names_stay_in_the_root_directory() {
+        assert_eq!(run("../../etc").render("{testid}"), ".._.._etc");
+        assert_eq!(run("a/b\\c:d").render("{testid}"), "a_b_c_d");
+        assert_eq!(run("..").render("{testid}"), "_..");
+        assert_eq!(run(".").render("{testid}"), "_.");
+        assert_eq!(run("").render("{testid}"), "_");
+
+        for testid in ["..", ".", "", "../x", "/etc"] {
+            let directory = run_directory(
+                "/tmp/reports",
+                &Some(String::from("{testid}")),
+                &run(testid),
+            )
+            .unwrap();
+            assert_eq!(directory.parent(), Some(Path::new("/tmp/reports")));
+        }
+        assert_eq!(
+            run_directory("./reports", &None, &run("x")).unwrap(),
+            PathBuf::from("./reports")
+        );
+    }
+
+    #[tokio::test]
+    async fn manifests_and_metric_files_mark_an_export() {
+        let directory = test_directory("layout", "exported");
+        std::fs::write(directory.join("notes.csv"), "").unwrap();
+        std::fs::write(directory.join("vus.txt"), "").unwrap();
+        assert!(ensure_not_exported(&directory).await.is_ok());
+        assert!(ensure_not_exported(&directory.join("missing"))
+            .await
+            .is_ok());
+
+        std::fs::write(directory.join("vus.csv.gz"), "").unwrap();
+        assert!(matches!(
+            ensure_not_exported(&directory).await,
+            Err(Error::OutputExists(_))
+        ));
+
+        std::fs::remove_file(directory.join("vus.csv.gz")).unwrap();
+        std::fs::write(directory.join(MANIFEST_FILENAME), "{}").unwrap();
+        assert!(matches!(
+            ensure_not_exported(&directory).await,
+            Err(Error::OutputExists(_))
+        ));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn runs_with_broken_manifests_are_skipped() {
+        let directory = test_directory("layout", "discover");
+        let run_directory = directory.join("run-1");
+        std::fs::create_dir_all(&run_directory).unwrap();
+        std::fs::write(run_directory.join(MANIFEST_FILENAME), "{").unwrap();
+        std::fs::write(directory.join(MANIFEST_FILENAME), manifest().to_string()).unwrap();
+
+        let runs = discover_runs(&directory).await.unwrap();
+        let names: Vec<&str> = runs.iter().map(|run| run.name.as_str()).collect();
+        assert_eq!(names, [ROOT_RUN_NAME]);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_of_the_previous_export_are_removed() {
+        let directory = test_directory("layout", "remove");
+        let manifest = manifest();
+        std::fs::write(directory.join(MANIFEST_FILENAME), manifest.to_string()).unwrap();
+        for file in ["vus.csv", "vus.jsonl", "notes.csv"] {
+            std::fs::write(directory.join(file), "").unwrap();
+        }
+
+        remove_exported_files(&directory).await.unwrap();
+        let mut left: Vec<String> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["notes.csv"]);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
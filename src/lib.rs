@@ -0,0 +1,64 @@
+//! Exports results of [k6](https://k6.io) load tests into CSV files and
+//! analyses them. The `k6-reports` executable is a thin command line wrapper
+//! around this crate, so everything it does can be embedded into other tools:
+//!
+//! - [`report::K6Report`] exports a test run from a [`source::MetricSource`],
+//!   e.g. [`influx::InfluxSource`], [`prometheus::PrometheusSource`] or
+//!   [`k6_output::K6OutputSource`] for files of the k6 JSON and CSV outputs,
+//!   or [`postgres::PostgresSource`] for TimescaleDB, along with the manifest
+//!   and the summary.
+//! - [`exporter`] writes the exported metrics in CSV, JSON or Parquet, and
+//!   custom formats can be added by implementing [`exporter::Exporter`].
+//! - [`metrics`] contains types of the built-in k6 metrics.
+//! - [`dataset`] and [`layout`] read the exported runs back, and
+//!   [`postgres::PostgresHistory`] keeps them in PostgreSQL for long-term
+//!   history, while [`import::RunImporter`] writes them back into InfluxDB.
+//! - [`summary`], [`trend`], [`comparison`], [`timeline`], [`saturation`],
+//!   [`statuses`], [`waterfall`], [`slo`], [`checks`] and [`groups`] analyse
+//!   the exported runs and render the results with [`output::OutputFormat`].
+// The derived metric types refer to `::k6_reports`, which has to resolve
+// inside this crate as well
+extern crate self as k6_reports;
+
+pub mod archive;
+mod chart;
+pub mod checks;
+pub mod comparison;
+pub mod compression;
+pub mod dataset;
+pub mod error;
+pub mod exporter;
+pub mod groups;
+mod html;
+pub mod import;
+pub mod influx;
+pub mod k6_output;
+pub mod layout;
+pub mod live;
+pub mod manifest;
+pub mod metrics;
+pub mod output;
+pub mod postgres;
+pub mod prometheus;
+pub mod report;
+pub mod saturation;
+pub mod server;
+pub mod slo;
+pub mod source;
+mod statistics;
+pub mod statuses;
+pub mod summary;
+mod table;
+#[cfg(test)]
+mod test_support;
+pub mod timeline;
+pub mod trend;
+pub mod trim;
+pub mod waterfall;
+
+/// Items used by the code generated by `#[derive(K6Metric)]`, so that the
+/// crates using it don't need to depend on chrono directly.
+#[doc(hidden)]
+pub mod __private {
+    pub use chrono::{DateTime, Utc};
+}
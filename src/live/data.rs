@@ -0,0 +1,362 @@
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
+use influxdb::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::influx::{execute_query, quote_literal};
+use crate::metrics::{
+    ChecksMetric, HttpReqDurationMetric, HttpReqFailedMetric, K6Metric, VusMetric,
+};
+
+/// The latest state of the running test, aggregated by InfluxDB.
+pub struct LiveSnapshot {
+    pub updated_at: DateTime<Utc>,
+    pub vus: Vec<f64>,
+    pub requests_per_second: Vec<f64>,
+    pub error_rate: Vec<f64>,
+    pub p95_duration: Vec<f64>,
+    pub slow_endpoints: Vec<EndpointLatency>,
+    pub failing_checks: Vec<CheckFailures>,
+}
+
+pub struct EndpointLatency {
+    pub method: String,
+    pub name: String,
+    pub p95_duration: f64,
+    pub requests: u64,
+}
+
+pub struct CheckFailures {
+    pub check: String,
+    pub passes: u64,
+    pub fails: u64,
+}
+
+#[derive(Deserialize)]
+struct Bucket {
+    value: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct EndpointTags {
+    method: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EndpointAggregate {
+    p95: Option<f64>,
+    requests: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct CheckTags {
+    check: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CheckAggregate {
+    total: Option<f64>,
+    passes: Option<f64>,
+}
+
+pub struct LiveQuery {
+    db_client: Client,
+    retention_policy_name: String,
+    window: u64,
+    bucket: u64,
+    testid: Option<String>,
+    top: usize,
+}
+
+impl LiveQuery {
+    pub fn new(db_client: Client, window: u64, bucket: u64, testid: &Option<String>) -> Self {
+        LiveQuery {
+            db_client,
+            retention_policy_name: String::from("autogen"),
+            window,
+            bucket,
+            testid: testid.to_owned(),
+            top: 10,
+        }
+    }
+
+    pub async fn fetch(&self) -> Result<LiveSnapshot> {
+        let requests = self
+            .fetch_buckets::<HttpReqDurationMetric>("count(value)", "fill(0)")
+            .await?;
+        let requests_per_second = complete_buckets(requests)
+            .into_iter()
+            .map(|count| count / self.bucket as f64)
+            .collect();
+        let error_rate = self
+            .fetch_buckets::<HttpReqFailedMetric>("mean(value)", "fill(none)")
+            .await?
+            .into_iter()
+            .map(|rate| rate * 100.0)
+            .collect();
+
+        Ok(LiveSnapshot {
+            updated_at: Utc::now(),
+            vus: self
+                .fetch_buckets::<VusMetric>("last(value)", "fill(previous)")
+                .await?,
+            requests_per_second,
+            error_rate,
+            p95_duration: self
+                .fetch_buckets::<HttpReqDurationMetric>("percentile(value, 95)", "fill(none)")
+                .await?,
+            slow_endpoints: self.fetch_slow_endpoints().await?,
+            failing_checks: self.fetch_failing_checks().await?,
+        })
+    }
+
+    async fn fetch_buckets<T: K6Metric>(&self, aggregate: &str, fill: &str) -> Result<Vec<f64>> {
+        let raw_query = format!(
+            "SELECT {0} AS value FROM {1} WHERE {2} GROUP BY time({3}s) {4}",
+            aggregate,
+            self.measurement::<T>(),
+            self.where_clause(),
+            self.bucket,
+            fill
+        );
+        let mut response = execute_query(&self.db_client, &raw_query).await?;
+        let data = response
+            .deserialize_next::<Bucket>()
+            .map_err(|error| Error::Query(error.to_string()))?;
+
+        Ok(data
+            .series
+            .into_iter()
+            .flat_map(|series| series.values)
+            .filter_map(|bucket| bucket.value)
+            .collect())
+    }
+
+    async fn fetch_slow_endpoints(&self) -> Result<Vec<EndpointLatency>> {
+        let raw_query = format!(
+            r#"SELECT percentile(value, 95) AS p95, count(value) AS requests FROM {0} WHERE {1} GROUP BY "method", "name""#,
+            self.measurement::<HttpReqDurationMetric>(),
+            self.where_clause(),
+        );
+        let series = self
+            .fetch_series::<EndpointTags, EndpointAggregate>(&raw_query)
+            .await?;
+
+        Ok(slowest_endpoints(series, self.top))
+    }
+
+    async fn fetch_failing_checks(&self) -> Result<Vec<CheckFailures>> {
+        let raw_query = format!(
+            r#"SELECT count(value) AS total, sum(value) AS passes FROM {0} WHERE {1} GROUP BY "check""#,
+            self.measurement::<ChecksMetric>(),
+            self.where_clause(),
+        );
+        let series = self
+            .fetch_series::<CheckTags, CheckAggregate>(&raw_query)
+            .await?;
+
+        Ok(failing_checks(series, self.top))
+    }
+
+    async fn fetch_series<TAG, T>(&self, raw_query: &str) -> Result<Vec<(TAG, Vec<T>)>>
+    where
+        TAG: DeserializeOwned + Send + 'static,
+        T: DeserializeOwned + Send + 'static,
+    {
+        let mut response = execute_query(&self.db_client, raw_query).await?;
+        let data = response
+            .deserialize_next_tagged::<TAG, T>()
+            .map_err(|error| Error::Query(error.to_string()))?;
+
+        Ok(data
+            .series
+            .into_iter()
+            .map(|series| (series.tags, series.values))
+            .collect())
+    }
+
+    fn measurement<T: K6Metric>(&self) -> String {
+        format!(
+            "{0}.{1}.{2}",
+            self.db_client.database_name(),
+            self.retention_policy_name,
+            T::metric_table_name()
+        )
+    }
+
+    fn where_clause(&self) -> String {
+        let mut filters = vec![format!("time > now() - {}m", self.window)];
+        if let Some(testid) = &self.testid {
+            filters.push(format!(r#""testid"={}"#, quote_literal(testid)));
+        }
+        filters.join(" AND ")
+    }
+}
+
+/// Drops the latest bucket, because it's still being filled with data.
+fn complete_buckets(mut buckets: Vec<f64>) -> Vec<f64> {
+    if buckets.len() > 1 {
+        buckets.pop();
+    }
+    buckets
+}
+
+/// Ranks the endpoints by their p95 duration, slowest first.
+fn slowest_endpoints(
+    series: Vec<(EndpointTags, Vec<EndpointAggregate>)>,
+    top: usize,
+) -> Vec<EndpointLatency> {
+    let mut endpoints: Vec<EndpointLatency> = series
+        .into_iter()
+        .filter_map(|(tags, values)| {
+            let aggregate = values.into_iter().next()?;
+            Some(EndpointLatency {
+                method: tags.method.unwrap_or_default(),
+                name: tags.name.unwrap_or_default(),
+                p95_duration: aggregate.p95.unwrap_or(0.0),
+                requests: aggregate.requests.unwrap_or(0.0) as u64,
+            })
+        })
+        .collect();
+    endpoints.sort_by(|left, right| right.p95_duration.total_cmp(&left.p95_duration));
+    endpoints.truncate(top);
+    endpoints
+}
+
+/// Keeps the checks that failed at least once, most failures first.
+fn failing_checks(series: Vec<(CheckTags, Vec<CheckAggregate>)>, top: usize) -> Vec<CheckFailures> {
+    let mut checks: Vec<CheckFailures> = series
+        .into_iter()
+        .filter_map(|(tags, values)| {
+            let aggregate = values.into_iter().next()?;
+            let total = aggregate.total.unwrap_or(0.0) as u64;
+            let passes = aggregate.passes.unwrap_or(0.0) as u64;
+            Some(CheckFailures {
+                check: tags.check.unwrap_or_default(),
+                passes,
+                fails: total.saturating_sub(passes),
+            })
+        })
+        .filter(|check| check.fails > 0)
+        .collect();
+    checks.sort_by_key(|check| Reverse(check.fails));
+    checks.truncate(top);
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(
+        name: &str,
+        p95: Option<f64>,
+        requests: f64,
+    ) -> (EndpointTags, Vec<EndpointAggregate>) {
+        let tags = EndpointTags {
+            method: Some(String::from("GET")),
+            name: Some(name.to_string()),
+        };
+        let aggregate = EndpointAggregate {
+            p95,
+            requests: Some(requests),
+        };
+        (tags, vec![aggregate])
+    }
+
+    fn check(name: &str, total: f64, passes: f64) -> (CheckTags, Vec<CheckAggregate>) {
+        let tags = CheckTags {
+            check: Some(name.to_string()),
+        };
+        let aggregate = CheckAggregate {
+            total: Some(total),
+            passes: Some(passes),
+        };
+        (tags, vec![aggregate])
+    }
+
+    #[test]
+    fn the_latest_bucket_is_dropped() {
+        assert_eq!(complete_buckets(vec![3.0, 5.0, 1.0]), vec![3.0, 5.0]);
+        assert_eq!(complete_buckets(vec![3.0]), vec![3.0]);
+        assert!(complete_buckets(vec![]).is_empty());
+    }
+
+    #[test]
+    fn the_slowest_endpoints_come_first() {
+        let series = vec![
+            endpoint("/home", Some(120.0), 50.0),
+            endpoint("/cart", Some(480.0), 10.0),
+            endpoint("/empty", None, 0.0),
+            endpoint("/login", Some(260.0), 20.0),
+            (
+                EndpointTags {
+                    method: None,
+                    name: None,
+                },
+                vec![],
+            ),
+        ];
+
+        let endpoints = slowest_endpoints(series, 3);
+        let ranking: Vec<(&str, f64, u64)> = endpoints
+            .iter()
+            .map(|endpoint| {
+                (
+                    endpoint.name.as_str(),
+                    endpoint.p95_duration,
+                    endpoint.requests,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranking,
+            vec![
+                ("/cart", 480.0, 10),
+                ("/login", 260.0, 20),
+                ("/home", 120.0, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn only_failing_checks_are_kept() {
+        let series = vec![
+            check("status is 200", 100.0, 100.0),
+            check("has body", 100.0, 97.0),
+            check("is fast", 40.0, 10.0),
+            check("has cookie", 5.0, 4.0),
+        ];
+
+        let checks = failing_checks(series, 2);
+        let ranking: Vec<(&str, u64, u64)> = checks
+            .iter()
+            .map(|check| (check.check.as_str(), check.passes, check.fails))
+            .collect();
+        assert_eq!(ranking, vec![("is fast", 10, 30), ("has body", 97, 3)]);
+    }
+
+    #[test]
+    fn the_window_is_filtered_by_the_test_id() {
+        let db_client = Client::new("http://localhost:8086", "k6");
+        let query = LiveQuery::new(db_client.clone(), 5, 10, &None);
+        assert_eq!(query.where_clause(), "time > now() - 5m");
+        assert_eq!(
+            query.measurement::<VusMetric>(),
+            format!("k6.autogen.{}", VusMetric::metric_table_name())
+        );
+
+        let query = LiveQuery::new(db_client, 5, 10, &Some(String::from("it's-1")));
+        assert_eq!(
+            query.where_clause(),
+            format!(
+                r#"time > now() - 5m AND "testid"={}"#,
+                quote_literal("it's-1")
+            )
+        );
+    }
+}
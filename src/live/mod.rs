@@ -0,0 +1,118 @@
+mod data;
+mod ui;
+
+use std::io::{stdout, Stdout};
+use std::time::{Duration, Instant};
+
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::Terminal;
+
+use crate::error::Result;
+use crate::influx::{create_client, ConnectionOptions};
+use crate::live::data::LiveQuery;
+use crate::live::ui::{draw, DashboardState};
+
+/// Terminal dashboard that periodically polls InfluxDB for the state of a
+/// running test. It works in any terminal, including SSH sessions.
+pub struct LiveDashboard {
+    query: LiveQuery,
+    title: String,
+    refresh_interval: Duration,
+}
+
+impl LiveDashboard {
+    pub fn new(
+        connection: &ConnectionOptions,
+        refresh_interval: &u64,
+        window: &u64,
+        bucket: &u64,
+        testid: &Option<String>,
+    ) -> Self {
+        let (connection_url, db_client) = create_client(connection);
+        let title = format!(
+            "k6 live: `{0}` database at {1} (last {2} min, q to quit)",
+            connection.database, connection_url, window
+        );
+
+        LiveDashboard {
+            query: LiveQuery::new(db_client, *window, *bucket, testid),
+            title,
+            refresh_interval: Duration::from_secs(*refresh_interval),
+        }
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        // Fail early with a regular error message when the database isn't reachable
+        let mut state = DashboardState {
+            title: self.title.clone(),
+            snapshot: Some(self.query.fetch().await?),
+            status: String::new(),
+        };
+
+        let mut terminal = TerminalGuard::enter()?;
+        loop {
+            if let Some(snapshot) = &state.snapshot {
+                state.status = format!("Updated at {}", snapshot.updated_at.format("%H:%M:%S UTC"));
+            }
+            terminal.0.draw(|frame| draw(frame, &state))?;
+
+            if self.wait_for_exit()? {
+                break;
+            }
+
+            match self.query.fetch().await {
+                Ok(snapshot) => state.snapshot = Some(snapshot),
+                Err(error) => state.status = format!("Refresh failed: {}", error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits until the next refresh. Returns true when the user asked to quit.
+    fn wait_for_exit(&self) -> Result<bool> {
+        let started_at = Instant::now();
+        while started_at.elapsed() < self.refresh_interval {
+            let timeout = self.refresh_interval - started_at.elapsed();
+            if !event::poll(timeout)? {
+                break;
+            }
+
+            if let Event::Key(key) = event::read()? {
+                let is_ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if is_ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Restores the terminal on exit, even when the dashboard fails.
+struct TerminalGuard(Terminal<CrosstermBackend<Stdout>>);
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+        Ok(TerminalGuard(Terminal::new(CrosstermBackend::new(
+            stdout(),
+        ))?))
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.0.backend_mut(), LeaveAlternateScreen);
+        let _ = self.0.show_cursor();
+    }
+}
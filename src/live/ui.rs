@@ -0,0 +1,167 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Sparkline, Table};
+use ratatui::Frame;
+
+use crate::live::data::LiveSnapshot;
+
+/// What is shown on the screen: the latest snapshot (if any was fetched) and
+/// the status of the last refresh.
+pub struct DashboardState {
+    pub title: String,
+    pub snapshot: Option<LiveSnapshot>,
+    pub status: String,
+}
+
+pub fn draw(frame: &mut Frame, state: &DashboardState) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Min(6),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+
+    let title = Paragraph::new(Line::from(state.title.as_str()))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    frame.render_widget(title, rows[0]);
+
+    let status = Paragraph::new(Line::from(state.status.as_str()))
+        .style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(status, rows[4]);
+
+    let snapshot = match &state.snapshot {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+
+    let top_charts = split_horizontally(rows[1]);
+    draw_sparkline(frame, top_charts[0], "VUs", &snapshot.vus, "", Color::Cyan);
+    draw_sparkline(
+        frame,
+        top_charts[1],
+        "Requests/s",
+        &snapshot.requests_per_second,
+        "",
+        Color::Green,
+    );
+
+    let bottom_charts = split_horizontally(rows[2]);
+    draw_sparkline(
+        frame,
+        bottom_charts[0],
+        "Error rate",
+        &snapshot.error_rate,
+        "%",
+        Color::Red,
+    );
+    draw_sparkline(
+        frame,
+        bottom_charts[1],
+        "p95 latency",
+        &snapshot.p95_duration,
+        " ms",
+        Color::Yellow,
+    );
+
+    let tables = split_horizontally(rows[3]);
+    draw_slow_endpoints(frame, tables[0], snapshot);
+    draw_failing_checks(frame, tables[1], snapshot);
+}
+
+fn split_horizontally(area: Rect) -> Vec<Rect> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area)
+        .to_vec()
+}
+
+fn draw_sparkline(
+    frame: &mut Frame,
+    area: Rect,
+    name: &str,
+    values: &[f64],
+    unit: &str,
+    color: Color,
+) {
+    let current = values
+        .last()
+        .map(|value| format!("{:.1}{}", value, unit))
+        .unwrap_or_else(|| String::from("n/a"));
+    let title = format!(" {}: {} ", name, current);
+
+    // Sparklines render integers only, so keep two decimal places for small values
+    let scaled_values: Vec<u64> = values
+        .iter()
+        .map(|value| (value * 100.0).round().max(0.0) as u64)
+        .collect();
+    let visible_points = area.width.saturating_sub(2) as usize;
+    let start = scaled_values.len().saturating_sub(visible_points);
+
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .data(&scaled_values[start..])
+        .style(Style::default().fg(color));
+    frame.render_widget(sparkline, area);
+}
+
+fn draw_slow_endpoints(frame: &mut Frame, area: Rect, snapshot: &LiveSnapshot) {
+    let rows = snapshot.slow_endpoints.iter().map(|endpoint| {
+        Row::new(vec![
+            endpoint.method.clone(),
+            endpoint.name.clone(),
+            format!("{:.1}", endpoint.p95_duration),
+            endpoint.requests.to_string(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(7),
+            Constraint::Min(10),
+            Constraint::Length(10),
+            Constraint::Length(9),
+        ],
+    )
+    .header(header_row(&["Method", "Name", "p95 (ms)", "Requests"]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Top slow endpoints "),
+    );
+    frame.render_widget(table, area);
+}
+
+fn draw_failing_checks(frame: &mut Frame, area: Rect, snapshot: &LiveSnapshot) {
+    let rows = snapshot.failing_checks.iter().map(|check| {
+        Row::new(vec![
+            check.check.clone(),
+            check.fails.to_string(),
+            check.passes.to_string(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(10),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ],
+    )
+    .header(header_row(&["Check", "Fails", "Passes"]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Failing checks "),
+    );
+    frame.render_widget(table, area);
+}
+
+fn header_row<'a>(columns: &[&'a str]) -> Row<'a> {
+    Row::new(columns.to_vec()).style(Style::default().add_modifier(Modifier::BOLD))
+}
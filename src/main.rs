@@ -1,8 +1,5 @@
 mod app;
 mod cli;
-mod error;
-mod metrics;
-mod report;
 
 use structopt::StructOpt;
 
@@ -13,5 +10,7 @@ use crate::cli::Command;
 async fn main() {
     let command = Command::from_args();
     let app = App::new();
-    app.run(&command).await;
+    if let Err(error) = app.run(&command).await {
+        error.exit();
+    }
 }
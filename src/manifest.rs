@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs::{read, write, File};
+use tokio::io::AsyncReadExt;
+
+use crate::error::{Error, Result};
+use crate::layout::RunMetadata;
+
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+const CHECKSUM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Describes the content of an exported directory, so that the results can be
+/// trusted and reproduced long after the test run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Manifest {
+    pub tool_version: String,
+    pub created_at: DateTime<Utc>,
+    pub run: RunMetadata,
+    pub source: Source,
+    pub time_window: TimeWindow,
+    pub filters: Vec<String>,
+    pub metrics: Vec<MetricEntry>,
+}
+
+/// Location of the exported data. Credentials are never stored here.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub kind: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_policy: Option<String>,
+}
+
+impl Source {
+    /// Prometheus keeps only aggregates of the data points, so an exported
+    /// data point doesn't stand for a single request.
+    pub fn is_aggregated(&self) -> bool {
+        self.kind == "prometheus"
+    }
+
+    /// Fails for the sources with aggregates only, since the report needs a
+    /// data point per request. `data` names what is aggregated.
+    pub fn ensure_not_aggregated(&self, data: &str) -> Result<()> {
+        match self.is_aggregated() {
+            true => Err(Error::AggregatedData(format!(
+                "the run was exported from {}, which keeps only aggregates of {}",
+                self.kind, data
+            ))),
+            false => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeWindow {
+    pub from: Option<DateTime<Utc>>,
+    pub to: DateTime<Utc>,
+    /// Part of the test left after cutting off the warm-up and ramp-down
+    /// phases. All metrics were exported only for this part.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trimmed: Option<TrimmedWindow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrimmedWindow {
+    /// Either "fixed", "steady-state" or "window" when the times were given.
+    pub method: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetricEntry {
+    pub measurement: String,
+    pub file: String,
+    pub query: String,
+    pub rows: u64,
+    pub first_timestamp: Option<DateTime<Utc>>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+    /// Empty while the follow mode keeps appending to the file.
+    pub sha256: String,
+    /// Files written by the exporters other than the first one, when several
+    /// formats are exported at once.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_files: Vec<ExportedFile>,
+}
+
+/// File of a metric written in one of the additional export formats.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedFile {
+    pub format: String,
+    pub file: String,
+    pub sha256: String,
+}
+
+impl Manifest {
+    pub fn new(
+        run: RunMetadata,
+        source: Source,
+        time_window: TimeWindow,
+        filters: Vec<String>,
+    ) -> Self {
+        Manifest {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            run,
+            source,
+            time_window,
+            filters,
+            metrics: vec![],
+        }
+    }
+
+    pub fn exists(directory: &Path) -> bool {
+        directory.join(MANIFEST_FILENAME).exists()
+    }
+
+    pub async fn load(directory: &Path) -> Result<Self> {
+        let content = read(directory.join(MANIFEST_FILENAME)).await?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    pub async fn save(&self, directory: &Path) -> Result<()> {
+        let content = serde_json::to_vec_pretty(self)?;
+        write(directory.join(MANIFEST_FILENAME), content).await?;
+        Ok(())
+    }
+}
+
+/// Hashes the file in chunks, so big exported files don't have to fit into
+/// memory.
+pub async fn sha256_checksum(path: &Path) -> Result<String> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; CHECKSUM_CHUNK_SIZE];
+    loop {
+        let read_bytes = file.read(&mut buffer).await?;
+        if read_bytes == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read_bytes]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hashes_files_in_chunks() {
+        let path = std::env::temp_dir().join(format!(
+            "k6-reports-manifest-checksum-{}",
+            std::process::id()
+        ));
+        let content: Vec<u8> = (0..CHECKSUM_CHUNK_SIZE * 2 + 100)
+            .map(|index| (index % 251) as u8)
+            .collect();
+        write(&path, &content).await.unwrap();
+
+        let checksum = sha256_checksum(&path).await.unwrap();
+        assert_eq!(checksum, hex::encode(Sha256::digest(&content)));
+
+        write(&path, b"abc").await.unwrap();
+        assert_eq!(
+            sha256_checksum(&path).await.unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
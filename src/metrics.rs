@@ -1,15 +1,28 @@
-﻿use chrono::{DateTime, Utc};
+﻿use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+pub use k6_reports_derive::K6Metric;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
 
+use crate::error;
+
+/// Tags are stored as strings in InfluxDB and CSV files, while the JSON
+/// files keep the values of their own types.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOr<T> {
+    Value(T),
+    String(String),
+}
+
 fn custom_deserialize_bool_from_str<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
-    match bool::from_str(&s) {
-        Ok(value) => Ok(value),
-        _ => Ok(false),
+    match StringOr::<bool>::deserialize(deserializer)? {
+        StringOr::Value(value) => Ok(value),
+        StringOr::String(s) => Ok(bool::from_str(&s).unwrap_or(false)),
     }
 }
 
@@ -17,455 +30,822 @@ fn custom_deserialize_u16_from_str<'de, D>(deserializer: D) -> Result<u16, D::Er
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
-    match u16::from_str(&s) {
-        Ok(value) => Ok(value),
-        _ => Ok(0),
+    match StringOr::<u16>::deserialize(deserializer)? {
+        StringOr::Value(value) => Ok(value),
+        StringOr::String(s) => Ok(u16::from_str(&s).unwrap_or(0)),
     }
 }
 
+/// Implemented with `#[derive(K6Metric)]`. The `time` field is the timestamp
+/// of a data point, while the rest of the fields are marked with `#[k6(tag)]`
+/// or `#[k6(field)]`.
 pub trait K6Metric {
     fn metric_table_name() -> &'static str;
     fn query_fields() -> &'static [&'static str];
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+    /// Describes the columns for output formats with a typed schema.
+    fn schema() -> &'static [Column];
+    fn time(&self) -> DateTime<Utc>;
+}
+
+/// Column of the exported metric, in the order of the struct fields.
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: ColumnKind,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Time,
+    Tag,
+    Field,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Timestamp,
+    String,
+    Boolean,
+    Integer,
+    Float,
+}
+
+/// Receives each of the built-in metric types in turn, e.g. to export or
+/// import all of them. `optional` metrics are produced only by some k6
+/// modules and executors.
+#[async_trait]
+pub trait MetricVisitor: Send {
+    type Output: Send;
+
+    async fn visit<T>(&mut self, optional: bool) -> error::Result<Self::Output>
+    where
+        T: 'static + K6Metric + Serialize + DeserializeOwned + Send + Sync;
+}
+
+/// Passes every built-in metric to the visitor, the ones written by every
+/// test first.
+pub async fn visit_metrics<V: MetricVisitor>(visitor: &mut V) -> error::Result<Vec<V::Output>> {
+    Ok(vec![
+        visitor.visit::<ChecksMetric>(false).await?,
+        visitor.visit::<DataReceivedMetric>(false).await?,
+        visitor.visit::<DataSentMetric>(false).await?,
+        visitor.visit::<GroupDurationMetric>(false).await?,
+        visitor.visit::<HttpReqBlockedMetric>(false).await?,
+        visitor.visit::<HttpReqConnectingMetric>(false).await?,
+        visitor.visit::<HttpReqDurationMetric>(false).await?,
+        visitor.visit::<HttpReqFailedMetric>(false).await?,
+        visitor.visit::<HttpReqReceivingMetric>(false).await?,
+        visitor.visit::<HttpReqSendingMetric>(false).await?,
+        visitor.visit::<HttpReqTlsHandshakingMetric>(false).await?,
+        visitor.visit::<HttpReqWaitingMetric>(false).await?,
+        visitor.visit::<IterationMetric>(false).await?,
+        visitor.visit::<IterationsDurationMetric>(false).await?,
+        visitor.visit::<VusMetric>(false).await?,
+        visitor.visit::<VusMaxMetric>(false).await?,
+        visitor.visit::<BrowserDataReceivedMetric>(true).await?,
+        visitor.visit::<BrowserDataSentMetric>(true).await?,
+        visitor.visit::<BrowserHttpReqDurationMetric>(true).await?,
+        visitor.visit::<BrowserHttpReqFailedMetric>(true).await?,
+        visitor.visit::<BrowserWebVitalClsMetric>(true).await?,
+        visitor.visit::<BrowserWebVitalFcpMetric>(true).await?,
+        visitor.visit::<BrowserWebVitalFidMetric>(true).await?,
+        visitor.visit::<BrowserWebVitalInpMetric>(true).await?,
+        visitor.visit::<BrowserWebVitalLcpMetric>(true).await?,
+        visitor.visit::<BrowserWebVitalTtfbMetric>(true).await?,
+        visitor.visit::<DroppedIterationsMetric>(true).await?,
+        visitor.visit::<GrpcReqDurationMetric>(true).await?,
+        visitor.visit::<GrpcStreamsMetric>(true).await?,
+        visitor.visit::<GrpcStreamsMsgsReceivedMetric>(true).await?,
+        visitor.visit::<GrpcStreamsMsgsSentMetric>(true).await?,
+        visitor.visit::<HttpReqsMetric>(true).await?,
+        visitor.visit::<WsConnectingMetric>(true).await?,
+        visitor.visit::<WsMsgsReceivedMetric>(true).await?,
+        visitor.visit::<WsMsgsSentMetric>(true).await?,
+        visitor.visit::<WsPingMetric>(true).await?,
+        visitor.visit::<WsSessionDurationMetric>(true).await?,
+        visitor.visit::<WsSessionsMetric>(true).await?,
+    ])
+}
+
+/// Names of the measurements of all built-in metrics.
+pub async fn builtin_measurements() -> error::Result<Vec<&'static str>> {
+    struct Measurements;
+
+    #[async_trait]
+    impl MetricVisitor for Measurements {
+        type Output = &'static str;
+
+        async fn visit<T>(&mut self, _optional: bool) -> error::Result<&'static str>
+        where
+            T: 'static + K6Metric + Serialize + DeserializeOwned + Send + Sync,
+        {
+            Ok(T::metric_table_name())
+        }
+    }
+
+    visit_metrics(&mut Measurements).await
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_data_received")]
+pub struct BrowserDataReceivedMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The amount of data received by the browser
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_data_sent")]
+pub struct BrowserDataSentMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The amount of data sent by the browser
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_http_req_duration")]
+pub struct BrowserHttpReqDurationMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub resource_type: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Duration of a request made by the browser
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_http_req_failed")]
+pub struct BrowserHttpReqFailedMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub resource_type: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The rate of failed requests made by the browser
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_web_vital_cls")]
+pub struct BrowserWebVitalClsMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub rating: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Cumulative Layout Shift score
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_web_vital_fcp")]
+pub struct BrowserWebVitalFcpMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub rating: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // First Contentful Paint time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_web_vital_fid")]
+pub struct BrowserWebVitalFidMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub rating: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // First Input Delay time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_web_vital_inp")]
+pub struct BrowserWebVitalInpMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub rating: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Interaction to Next Paint time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_web_vital_lcp")]
+pub struct BrowserWebVitalLcpMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub rating: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Largest Contentful Paint time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "browser_web_vital_ttfb")]
+pub struct BrowserWebVitalTtfbMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub rating: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Time to First Byte
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "checks")]
 pub struct ChecksMetric {
-    time: DateTime<Utc>,
-    check: Option<String>,
-    group: Option<String>,
-    method: Option<String>,
-    scenario: Option<String>,
-    value: f64, // The rate of successful checks
-}
-
-impl K6Metric for ChecksMetric {
-    fn metric_table_name() -> &'static str {
-        "checks"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""check""#,
-            r#""group""#,
-            r#""method""#,
-            r#""scenario""#,
-            "value",
-        ]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub check: Option<String>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The rate of successful checks
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "data_received")]
 pub struct DataReceivedMetric {
-    time: DateTime<Utc>,
-    group: Option<String>,
-    scenario: Option<String>,
-    value: f64, // The amount of received data
-}
-
-impl K6Metric for DataReceivedMetric {
-    fn metric_table_name() -> &'static str {
-        "data_received"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &["time", r#""group""#, r#""scenario""#, "value"]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The amount of received data
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "data_sent")]
 pub struct DataSentMetric {
-    time: DateTime<Utc>,
-    group: Option<String>,
-    scenario: Option<String>,
-    value: f64, // The amount of data sent
-}
-
-impl K6Metric for DataSentMetric {
-    fn metric_table_name() -> &'static str {
-        "data_sent"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &["time", r#""group""#, r#""scenario""#, "value"]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The amount of data sent
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "dropped_iterations")]
+pub struct DroppedIterationsMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of iterations that weren't started due to the lack of VUs or time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "group_duration")]
+pub struct GroupDurationMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Time it took to execute the group function
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "grpc_req_duration")]
+pub struct GrpcReqDurationMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Time to receive the response from the remote host
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "grpc_streams")]
+pub struct GrpcStreamsMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of started streams
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "grpc_streams_msgs_received")]
+pub struct GrpcStreamsMsgsReceivedMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of messages received over streams
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "grpc_streams_msgs_sent")]
+pub struct GrpcStreamsMsgsSentMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of messages sent over streams
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_connecting")]
 pub struct HttpReqConnectingMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // Time spent establishing TCP connection to the remote host
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // Time spent establishing TCP connection to the remote host
 }
 
-impl K6Metric for HttpReqConnectingMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_connecting"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_blocked")]
 pub struct HttpReqBlockedMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // Time spent blocked (waiting for a free TCP connection slot) before initiating the request
-}
-
-impl K6Metric for HttpReqBlockedMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_blocked"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // Time spent blocked (waiting for a free TCP connection slot) before initiating the request
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_duration")]
 pub struct HttpReqDurationMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // duration
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // duration
 }
 
-impl K6Metric for HttpReqDurationMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_duration"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_failed")]
 pub struct HttpReqFailedMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // The rate of failed requests according to setResponseCallback
-}
-
-impl K6Metric for HttpReqFailedMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_failed"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // The rate of failed requests according to setResponseCallback
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_receiving")]
 pub struct HttpReqReceivingMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // Time spent receiving response data from the remote host
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // Time spent receiving response data from the remote host
 }
 
-impl K6Metric for HttpReqReceivingMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_receiving"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_sending")]
 pub struct HttpReqSendingMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // Time spent sending data to the remote host
-}
-
-impl K6Metric for HttpReqSendingMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_sending"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // Time spent sending data to the remote host
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_tls_handshaking")]
 pub struct HttpReqTlsHandshakingMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // Time spent handshaking TLS session with remote host
-}
-
-impl K6Metric for HttpReqTlsHandshakingMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_tls_handshaking"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // Time spent handshaking TLS session with remote host
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_req_waiting")]
 pub struct HttpReqWaitingMetric {
-    time: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
-    expected_response: bool,
-    group: Option<String>,
-    method: Option<String>,
-    name: Option<String>,
-    proto: Option<String>,
-    scenario: Option<String>,
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
     #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
-    status: u16,
-    tls_version: Option<String>,
-    url: String,
-    value: f64, // Time spent waiting for response from remote host
-}
-
-impl K6Metric for HttpReqWaitingMetric {
-    fn metric_table_name() -> &'static str {
-        "http_req_waiting"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &[
-            "time",
-            r#""expected_response""#,
-            r#""group""#,
-            r#""method""#,
-            r#""name""#,
-            r#""proto""#,
-            r#""scenario""#,
-            r#""status""#,
-            r#""tls_version""#,
-            r#""url""#,
-            "value",
-        ]
-    }
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // Time spent waiting for response from remote host
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "http_reqs")]
+pub struct HttpReqsMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    #[serde(deserialize_with = "custom_deserialize_bool_from_str")]
+    pub expected_response: bool,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub method: Option<String>,
+    #[k6(tag)]
+    pub name: Option<String>,
+    #[k6(tag)]
+    pub proto: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    #[serde(deserialize_with = "custom_deserialize_u16_from_str")]
+    pub status: u16,
+    #[k6(tag)]
+    pub tls_version: Option<String>,
+    #[k6(tag)]
+    pub url: String,
+    #[k6(field)]
+    pub value: f64, // The number of HTTP requests
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "iterations")]
 pub struct IterationMetric {
-    time: DateTime<Utc>,
-    scenario: Option<String>,
-    value: f64, // The aggregate number of times the VUs in the test have executed the JS script (the default function)
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The aggregate number of times the VUs in the test have executed the JS script (the default function)
 }
 
-impl K6Metric for IterationMetric {
-    fn metric_table_name() -> &'static str {
-        "iterations"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &["time", r#""scenario""#, "value"]
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "iteration_duration")]
 pub struct IterationsDurationMetric {
-    time: DateTime<Utc>,
-    scenario: Option<String>,
-    value: f64, // The time it took to complete one full iteration. It includes the time spent in setup and teardown as well
-}
-
-impl K6Metric for IterationsDurationMetric {
-    fn metric_table_name() -> &'static str {
-        "iteration_duration"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &["time", r#""group""#, r#""scenario""#, "value"]
-    }
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The time it took to complete one full iteration. It includes the time spent in setup and teardown as well
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "vus")]
 pub struct VusMetric {
-    time: DateTime<Utc>,
-    value: f64, // Current number of active virtual users
-}
-
-impl K6Metric for VusMetric {
-    fn metric_table_name() -> &'static str {
-        "vus"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &["time", "value"]
-    }
+    pub time: DateTime<Utc>,
+    #[k6(field)]
+    pub value: f64, // Current number of active virtual users
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "vus_max")]
 pub struct VusMaxMetric {
-    time: DateTime<Utc>,
-    value: f64, // Max possible number of virtual users (VU resources are pre-allocated, to ensure performance will not be affected when scaling up the load level)
-}
-
-impl K6Metric for VusMaxMetric {
-    fn metric_table_name() -> &'static str {
-        "vus_max"
-    }
-
-    fn query_fields() -> &'static [&'static str] {
-        &["time", "value"]
-    }
+    pub time: DateTime<Utc>,
+    #[k6(field)]
+    pub value: f64, // Max possible number of virtual users (VU resources are pre-allocated, to ensure performance will not be affected when scaling up the load level)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "ws_connecting")]
+pub struct WsConnectingMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub subproto: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Duration of the WebSocket connection request
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "ws_msgs_received")]
+pub struct WsMsgsReceivedMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub subproto: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of received messages
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "ws_msgs_sent")]
+pub struct WsMsgsSentMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub subproto: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of sent messages
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "ws_ping")]
+pub struct WsPingMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub subproto: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Time between a ping request and its pong reception
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "ws_session_duration")]
+pub struct WsSessionDurationMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub subproto: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // Duration of the WebSocket session
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, K6Metric)]
+#[k6(measurement = "ws_sessions")]
+pub struct WsSessionsMetric {
+    pub time: DateTime<Utc>,
+    #[k6(tag)]
+    pub group: Option<String>,
+    #[k6(tag)]
+    pub scenario: Option<String>,
+    #[k6(tag)]
+    pub status: Option<String>,
+    #[k6(tag)]
+    pub subproto: Option<String>,
+    #[k6(tag)]
+    pub url: Option<String>,
+    #[k6(field)]
+    pub value: f64, // The number of started WebSocket sessions
 }
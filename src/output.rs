@@ -0,0 +1,42 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use tokio::fs::write;
+
+use crate::error::Result;
+
+/// Format of the reports produced by the analysis commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Console,
+    Markdown,
+    Html,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "console" => Ok(OutputFormat::Console),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unsupported output format: {}", value)),
+        }
+    }
+}
+
+/// Prints the rendered report or writes it to the file, if one is given.
+pub async fn write_output(content: &str, output_path: &Option<String>) -> Result<()> {
+    match output_path {
+        Some(output_path) => {
+            write(Path::new(output_path), content).await?;
+            println!("The report was saved to `{0}`", output_path);
+        }
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,467 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::manifest::Source;
+use crate::source::{
+    point_row, MetricQuery, MetricSource, QueryFilters, Row, RowBatches, SingleBatch,
+};
+
+/// Prefix of the series written by the k6 Prometheus remote-write output.
+const SERIES_PREFIX: &str = "k6_";
+
+const DEFAULT_STEP: u64 = 5;
+const DEFAULT_TREND_STAT: &str = "p99";
+const DEFAULT_LOOKBACK_MINUTES: i64 = 60;
+
+/// Counters are written as cumulative `_total` series.
+const COUNTERS: &[&str] = &[
+    "browser_data_received",
+    "browser_data_sent",
+    "data_received",
+    "data_sent",
+    "dropped_iterations",
+    "grpc_streams",
+    "grpc_streams_msgs_received",
+    "grpc_streams_msgs_sent",
+    "http_reqs",
+    "iterations",
+    "ws_msgs_received",
+    "ws_msgs_sent",
+    "ws_sessions",
+];
+
+/// Gauges are written as is.
+const GAUGES: &[&str] = &["vus", "vus_max"];
+
+/// Rates are written as `_rate` series with the share of non-zero values.
+const RATES: &[&str] = &["browser_http_req_failed", "checks", "http_req_failed"];
+
+/// Trends which aren't durations, so their values aren't converted from
+/// seconds.
+const UNITLESS_TRENDS: &[&str] = &["browser_web_vital_cls"];
+
+/// Suffixes of the series which are stripped to get the measurement name.
+const SERIES_SUFFIXES: &[&str] = &[
+    "_total", "_rate", "_avg", "_min", "_max", "_med", "_count", "_sum", "_p90", "_p95", "_p99",
+];
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    status: String,
+    data: Option<T>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Matrix {
+    result: Vec<MatrixSeries>,
+}
+
+#[derive(Deserialize)]
+struct MatrixSeries {
+    metric: HashMap<String, String>,
+    values: Vec<(f64, String)>,
+}
+
+/// Prometheus-compatible server (e.g. Prometheus or Mimir) which k6 writes
+/// into with the `experimental-prometheus-rw` output.
+///
+/// Prometheus keeps only aggregates of the flushed data points: trends are
+/// represented by the series of a single statistic (`p99` by default, see
+/// `K6_PROMETHEUS_RW_TREND_STATS`), counters by increments between steps,
+/// and gauges and rates by their sampled values. Each sample of a series
+/// becomes one data point, so the exported metrics are approximations of
+/// the raw data points exported from the other sources.
+pub struct PrometheusSource {
+    url: String,
+    client: Client,
+    step: u64,
+    trend_stat: String,
+    lookback: Duration,
+}
+
+impl PrometheusSource {
+    pub fn new(url: &str) -> Self {
+        PrometheusSource {
+            url: url.trim_end_matches('/').to_string(),
+            client: Client::new(),
+            step: DEFAULT_STEP,
+            trend_stat: String::from(DEFAULT_TREND_STAT),
+            lookback: Duration::minutes(DEFAULT_LOOKBACK_MINUTES),
+        }
+    }
+
+    /// Resolution of the queries in seconds, 5 seconds by default, the same
+    /// as the flush period of the k6 output.
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Statistic of the trend series to export, e.g. `p95` or `avg`.
+    pub fn with_trend_stat(mut self, trend_stat: &str) -> Self {
+        self.trend_stat = trend_stat.to_string();
+        self
+    }
+
+    /// How far to look back when the filters don't limit the time range,
+    /// an hour by default.
+    pub fn with_lookback(mut self, lookback: Duration) -> Self {
+        self.lookback = lookback;
+        self
+    }
+
+    fn series_name(&self, measurement: &str) -> String {
+        let suffix = match measurement {
+            _ if COUNTERS.contains(&measurement) => String::from("_total"),
+            _ if RATES.contains(&measurement) => String::from("_rate"),
+            _ if GAUGES.contains(&measurement) => String::new(),
+            _ => format!("_{}", self.trend_stat),
+        };
+        format!("{0}{1}{2}", SERIES_PREFIX, measurement, suffix)
+    }
+
+    fn build_query(&self, query: &MetricQuery) -> String {
+        let filters = &query.filters;
+        let mut matchers = vec![];
+        if let Some(testid) = &filters.testid {
+            matchers.push(format!("testid={}", quote_label(testid)));
+        }
+        if query.columns.iter().any(|column| column.name == "group") {
+            for group in &filters.excluded_groups {
+                matchers.push(format!("group!={}", quote_label(group)));
+            }
+        }
+
+        format!(
+            "{0}{{{1}}}",
+            self.series_name(query.measurement),
+            matchers.join(",")
+        )
+    }
+
+    /// Time range of the query, where the end is the current time unless the
+    /// test was trimmed or the follow mode waits for late data points. Tells
+    /// as well whether the range starts in the middle of the test.
+    fn time_range(&self, query: &MetricQuery) -> (DateTime<Utc>, DateTime<Utc>, bool) {
+        let filters = &query.filters;
+        let end = [
+            filters.trimmed_window.as_ref().map(|window| window.to),
+            filters.until,
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or_else(Utc::now);
+        let start = [
+            filters.from,
+            filters.trimmed_window.as_ref().map(|window| window.from),
+            query.since,
+        ]
+        .into_iter()
+        .flatten()
+        .max();
+        match start {
+            Some(start) => (start, end, true),
+            None => (end - self.lookback, end, false),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        let response = self
+            .client
+            .get(format!("{0}{1}", self.url, path))
+            .query(params)
+            .send()
+            .await
+            .map_err(|error| Error::Prometheus(error.to_string()))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|error| Error::Prometheus(error.to_string()))?;
+        parse_response(status, &body)
+    }
+}
+
+#[async_trait]
+impl MetricSource for PrometheusSource {
+    fn describe(&self) -> Source {
+        Source {
+            kind: String::from("prometheus"),
+            url: self.url.clone(),
+            database: None,
+            retention_policy: None,
+        }
+    }
+
+    fn describe_filters(&self, filters: &QueryFilters) -> Vec<String> {
+        let mut result = vec![];
+
+        if let Some(from) = filters.from {
+            result.push(format!("start={}", format_time(from)));
+        }
+
+        if let Some(trimmed_window) = &filters.trimmed_window {
+            result.push(format!(
+                "start={} end={}",
+                format_time(trimmed_window.from),
+                format_time(trimmed_window.to)
+            ));
+        }
+
+        if let Some(testid) = &filters.testid {
+            result.push(format!("testid={}", quote_label(testid)));
+        }
+
+        for group in &filters.excluded_groups {
+            result.push(format!("group!={}", quote_label(group)));
+        }
+
+        result
+    }
+
+    fn describe_query(&self, query: &MetricQuery) -> String {
+        format!("{0} step={1}s", self.build_query(query), self.step)
+    }
+
+    async fn measurements(&self, filters: &QueryFilters) -> Result<HashSet<String>> {
+        let params = match &filters.testid {
+            Some(testid) => vec![("match[]", format!("{{testid={}}}", quote_label(testid)))],
+            None => vec![],
+        };
+        let names: Vec<String> = self.get("/api/v1/label/__name__/values", &params).await?;
+
+        // Gauges like `k6_vus_max` look the same as trend statistics, so both
+        // the plain and the stripped names are listed
+        let mut result = HashSet::new();
+        for name in names {
+            if let Some(name) = name.strip_prefix(SERIES_PREFIX) {
+                result.insert(name.to_string());
+                for suffix in SERIES_SUFFIXES {
+                    if let Some(measurement) = name.strip_suffix(suffix) {
+                        result.insert(measurement.to_string());
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// The series are sampled at the step, so they're fetched in one batch.
+    async fn fetch(&self, query: &MetricQuery) -> Result<Box<dyn RowBatches + '_>> {
+        let (start, end, is_partial) = self.time_range(query);
+        let matrix: Matrix = self
+            .get(
+                "/api/v1/query_range",
+                &[
+                    ("query", self.build_query(query)),
+                    ("start", format_time(start)),
+                    ("end", format_time(end)),
+                    ("step", format!("{}s", self.step)),
+                ],
+            )
+            .await?;
+
+        let rows = matrix_rows(&matrix, query, is_partial);
+        Ok(Box::new(SingleBatch::new(rows)))
+    }
+}
+
+/// Converts the sampled series into the rows of the metric, sorted by time.
+/// Counters become increments between the samples and durations are
+/// converted from seconds into milliseconds. A partial range (see
+/// `time_range`) starts with the totals of the earlier data points, so the
+/// first sample of each counter is only the baseline.
+fn matrix_rows(matrix: &Matrix, query: &MetricQuery, is_partial: bool) -> Vec<Row> {
+    let measurement = query.measurement;
+    let is_counter = COUNTERS.contains(&measurement);
+    let is_duration = !COUNTERS.contains(&measurement)
+        && !GAUGES.contains(&measurement)
+        && !RATES.contains(&measurement)
+        && !UNITLESS_TRENDS.contains(&measurement);
+
+    let mut points = vec![];
+    for series in &matrix.result {
+        let mut previous_total = None;
+        for (timestamp, value) in &series.values {
+            let value: f64 = match value.parse() {
+                Ok(value) if f64::is_finite(value) => value,
+                _ => continue,
+            };
+            let value = match is_counter {
+                true => {
+                    // The first total of a partial range includes the
+                    // data points before it, so it's only the baseline
+                    let increment = match previous_total {
+                        // A counter going down means that it was reset
+                        Some(previous) if value >= previous => Some(value - previous),
+                        Some(_) => Some(value),
+                        None if is_partial => None,
+                        None => Some(value),
+                    };
+                    previous_total = Some(value);
+                    match increment {
+                        Some(increment) => increment,
+                        None => continue,
+                    }
+                }
+                false if is_duration => value * 1000.0,
+                false => value,
+            };
+
+            let time = Utc.timestamp_millis_opt((timestamp * 1000.0).round() as i64);
+            if let Some(time) = time.single() {
+                points.push((time, value, &series.metric));
+            }
+        }
+    }
+    points.sort_by_key(|(time, _, _)| *time);
+
+    points
+        .into_iter()
+        .filter(|(time, _, _)| query.since.is_none_or(|since| *time > since))
+        .filter(|(time, _, _)| query.filters.until.is_none_or(|until| *time <= until))
+        .map(|(time, value, labels)| point_row(query.columns, time, value, labels))
+        .collect()
+}
+
+/// Reads the data of an API response. Rejected queries come with the `error`
+/// status and a message, while proxies in front of the API answer with a bare
+/// status code, e.g. when the credentials are missing.
+fn parse_response<T: DeserializeOwned>(status: StatusCode, body: &[u8]) -> Result<T> {
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(Error::Authentication(format!(
+            "Prometheus answered {}",
+            status
+        )));
+    }
+
+    let response: ApiResponse<T> = match serde_json::from_slice(body) {
+        Ok(response) => response,
+        Err(_) if !status.is_success() => {
+            return Err(Error::Prometheus(format!("the API answered {}", status)))
+        }
+        Err(error) => {
+            return Err(Error::Deserialization {
+                measurement: String::from("unknown"),
+                reason: error.to_string(),
+            })
+        }
+    };
+    match (response.status.as_str(), response.data) {
+        ("success", Some(data)) => Ok(data),
+        _ => Err(Error::Query(response.error.unwrap_or(response.status))),
+    }
+}
+
+/// Quotes the value for using it in a label matcher of PromQL.
+fn quote_label(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::error::{EXIT_AUTHENTICATION_ERROR, EXIT_CONNECTION_ERROR, EXIT_QUERY_ERROR};
+    use crate::metrics::{HttpReqDurationMetric, HttpReqsMetric};
+
+    /// Parses a `query_range` response with the given series.
+    fn matrix(result: &str) -> Matrix {
+        let body = format!(
+            r#"{{"status":"success","data":{{"resultType":"matrix","result":{}}}}}"#,
+            result
+        );
+        parse_response(StatusCode::OK, body.as_bytes()).unwrap()
+    }
+
+    fn values(rows: &[Row]) -> Vec<f64> {
+        rows.iter()
+            .map(|row| row["value"].as_f64().unwrap())
+            .collect()
+    }
+
+    fn exit_code(status: StatusCode, body: &str) -> i32 {
+        parse_response::<Matrix>(status, body.as_bytes())
+            .err()
+            .unwrap()
+            .exit_code()
+    }
+
+    #[test]
+    fn responses_are_mapped_to_exit_codes() {
+        assert_eq!(
+            exit_code(StatusCode::UNAUTHORIZED, "Unauthorized"),
+            EXIT_AUTHENTICATION_ERROR
+        );
+        assert_eq!(
+            exit_code(StatusCode::FORBIDDEN, ""),
+            EXIT_AUTHENTICATION_ERROR
+        );
+        assert_eq!(
+            exit_code(
+                StatusCode::BAD_REQUEST,
+                r#"{"status":"error","errorType":"bad_data","error":"parse error"}"#
+            ),
+            EXIT_QUERY_ERROR
+        );
+        assert_eq!(
+            exit_code(StatusCode::BAD_GATEWAY, "<html>Bad Gateway</html>"),
+            EXIT_CONNECTION_ERROR
+        );
+    }
+
+    #[test]
+    fn counters_become_increments() {
+        let matrix = matrix(
+            r#"[{"metric": {"__name__": "k6_http_reqs_total", "method": "GET", "name": "/a"},
+                 "values": [[1714564800, "10"], [1714564805, "25"], [1714564810, "5"]]}]"#,
+        );
+        let query = MetricQuery::new::<HttpReqsMetric>(QueryFilters::default());
+
+        let rows = matrix_rows(&matrix, &query, false);
+        // The counter was reset before the last sample
+        assert_eq!(values(&rows), [10.0, 15.0, 5.0]);
+        assert_eq!(rows[0]["time"], "2024-05-01T12:00:00Z");
+        assert_eq!(rows[0]["method"], "GET");
+        assert_eq!(rows[0]["name"], "/a");
+
+        // The first total of a partial range is only the baseline
+        let rows = matrix_rows(&matrix, &query, true);
+        assert_eq!(values(&rows), [15.0, 5.0]);
+    }
+
+    #[test]
+    fn durations_are_converted_and_sorted() {
+        let matrix = matrix(
+            r#"[{"metric": {"name": "/a"},
+                 "values": [[1714564800, "0.25"], [1714564810, "NaN"], [1714564815, "0.5"]]},
+                {"metric": {"name": "/b"},
+                 "values": [[1714564805, "1.5"], [1714564815.5, "0.125"]]}]"#,
+        );
+        let mut query = MetricQuery::new::<HttpReqDurationMetric>(QueryFilters::default());
+
+        let rows = matrix_rows(&matrix, &query, false);
+        assert_eq!(values(&rows), [250.0, 1500.0, 500.0, 125.0]);
+        assert_eq!(rows[3]["time"], "2024-05-01T12:00:15.500Z");
+        assert_eq!(rows[3]["name"], "/b");
+        assert_eq!(rows[3]["method"], Value::Null);
+
+        query.since = Some(Utc.timestamp_opt(1714564800, 0).unwrap());
+        query.filters.until = Some(Utc.timestamp_opt(1714564815, 0).unwrap());
+        let rows = matrix_rows(&matrix, &query, false);
+        assert_eq!(values(&rows), [1500.0, 500.0]);
+    }
+}
@@ -1,161 +1,679 @@
-﻿use std::fmt::Write;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Duration, SecondsFormat, Utc};
-use csv_async::AsyncSerializer;
-use influxdb::{Client, ReadQuery};
+use std::time::{Duration as StdDuration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use tokio::fs::{create_dir_all, File};
-
-use crate::error::Result;
-use crate::metrics::{
-    ChecksMetric, DataReceivedMetric, DataSentMetric, HttpReqBlockedMetric,
-    HttpReqConnectingMetric, HttpReqDurationMetric, HttpReqFailedMetric, HttpReqReceivingMetric,
-    HttpReqSendingMetric, HttpReqTlsHandshakingMetric, HttpReqWaitingMetric, IterationMetric,
-    IterationsDurationMetric, K6Metric, VusMaxMetric, VusMetric,
+use tokio::fs::create_dir_all;
+use tokio::time::sleep;
+
+use crate::archive::create_archive;
+use crate::compression::Compression;
+use crate::error::{Error, Result};
+use crate::exporter::{CsvExporter, Exporter};
+use crate::layout::{
+    ensure_not_exported, remove_exported_files, run_directory, RunIndex, RunMetadata,
 };
+use crate::manifest::{
+    sha256_checksum, ExportedFile, Manifest, MetricEntry, TimeWindow, TrimmedWindow,
+};
+use crate::metrics::{visit_metrics, K6Metric, MetricVisitor, VusMetric};
+use crate::slo::{SloConfig, SloReport};
+use crate::source::{fetch_batches, fetch_metric, to_row, MetricQuery, MetricSource, QueryFilters};
+use crate::summary::Summary;
+use crate::trim::{is_trimmed, trimmed_window, TrimOptions};
+
+/// Output directory used when the builder isn't given one, the same as the
+/// default of the `--output` option.
+pub const DEFAULT_OUTPUT_DIRECTORY: &str = "./reports";
 
+/// Formats of the exported files which the reports can read back.
+const READABLE_FORMATS: &[&str] = &["csv", "json"];
+
+/// Exports metrics of a test run from the source into files along with
+/// the manifest and the summary. Created with [`K6Report::builder`].
 pub struct K6Report {
     invoked_at: DateTime<Utc>,
-    output_directory: String,
-    db_client: Client,
-    retention_policy_name: String,
+    root_directory: String,
+    output_directory: PathBuf,
+    layout_template: Option<String>,
+    force: bool,
+    run: RunMetadata,
+    source: Box<dyn MetricSource>,
     from: Option<u64>,
     exclude_setup_steps: bool,
     exclude_teardown_steps: bool,
+    testid: Option<String>,
+    trim: TrimOptions,
+    window: Option<TrimmedWindow>,
+    slo_config: Option<SloConfig>,
+    exporters: Vec<Box<dyn Exporter>>,
+    archive_path: Option<String>,
+    quiet: bool,
 }
 
 impl K6Report {
-    pub fn new(
-        host: &String,
-        port: &String,
-        database: &String,
-        username: &Option<String>,
-        password: &Option<String>,
-        https: &bool,
-        from: &Option<u64>,
-        exclude_setup_steps: &bool,
-        exclude_teardown_steps: &bool,
-        output_directory: &String,
-    ) -> Self {
-        let invoked_at = Utc::now();
-        let connection_url = match https {
-            true => format! {"https://{host}:{port}", host = host, port = port},
-            false => format!("http://{host}:{port}", host = host, port = port),
-        };
-        let db_client = match username.is_some() && password.is_some() {
-            true => {
-                let auth_username = username.clone().unwrap();
-                let auth_password = password.clone().unwrap();
-                Client::new(connection_url, database).with_auth(auth_username, auth_password)
-            }
-            false => Client::new(connection_url, database),
-        };
+    pub fn builder(source: impl MetricSource + 'static) -> K6ReportBuilder {
+        K6ReportBuilder {
+            source: Box::new(source),
+            output_directory: String::from(DEFAULT_OUTPUT_DIRECTORY),
+            layout_template: None,
+            testid: None,
+            profile: None,
+            git_sha: None,
+            force: false,
+            from: None,
+            exclude_setup_steps: false,
+            exclude_teardown_steps: false,
+            trim: TrimOptions::default(),
+            window: None,
+            slo_config: None,
+            compression: None,
+            exporters: vec![],
+            archive_path: None,
+            quiet: false,
+        }
+    }
 
-        K6Report {
-            invoked_at,
-            output_directory: output_directory.to_owned(),
-            db_client,
-            retention_policy_name: String::from("autogen"),
-            from: from.to_owned(),
-            exclude_setup_steps: exclude_setup_steps.to_owned(),
-            exclude_teardown_steps: exclude_teardown_steps.to_owned(),
+    pub async fn extract_metrics(&self) -> Result<Summary> {
+        self.check_exporters()?;
+        match self.force {
+            true => remove_exported_files(&self.output_directory).await?,
+            false => ensure_not_exported(&self.output_directory).await?,
         }
+        create_dir_all(&self.output_directory).await?;
+
+        let trimmed_window = match (&self.window, is_trimmed(&self.trim)) {
+            (Some(window), _) => Some(window.clone()),
+            (None, true) => Some(self.detect_trimmed_window(self.start_timestamp()).await?),
+            (None, false) => None,
+        };
+        let mut manifest = self.create_manifest(trimmed_window);
+        manifest.metrics = self.export_metrics(&manifest, None).await?;
+        self.finish_export(&mut manifest).await
     }
 
-    pub async fn extract_metrics(&self) -> Result<()> {
+    /// Keeps exporting new data points of a running test until the `vus`
+    /// metric doesn't get updates during the idle timeout. Data points newer
+    /// than the lag aren't exported until the next poll, so the ones written
+    /// into the source late aren't skipped. The progress is tracked in the
+    /// manifest, so the export can be resumed after a restart with the same
+    /// source and filters. The test is trimmed once it stops, in which case
+    /// the files are exported again for the trimmed window.
+    pub async fn follow_metrics(
+        &self,
+        poll_interval: u64,
+        idle_timeout: u64,
+        lag: u64,
+    ) -> Result<Summary> {
+        self.check_exporters()?;
+        if let Some(exporter) = self
+            .exporters
+            .iter()
+            .find(|exporter| !exporter.supports_append())
+        {
+            return Err(Error::Export(format!(
+                "the {} format can't be used in the follow mode",
+                exporter.format()
+            )));
+        }
+
+        let mut manifest = match self.resumable_manifest().await? {
+            Some(manifest) => {
+                if !self.quiet {
+                    println!(
+                        "Resuming the export into `{0}`",
+                        self.output_directory.display()
+                    );
+                }
+                manifest
+            }
+            None => {
+                match self.force {
+                    true => remove_exported_files(&self.output_directory).await?,
+                    false => ensure_not_exported(&self.output_directory).await?,
+                }
+                self.create_manifest(None)
+            }
+        };
         create_dir_all(&self.output_directory).await?;
+        let idle_timeout = StdDuration::from_secs(idle_timeout);
+        let lag = Duration::seconds(lag as i64);
+        let mut last_activity = Instant::now();
 
-        self.export_metric::<ChecksMetric>(true).await?;
+        loop {
+            let until = Utc::now() - lag;
+            let previous_vus_rows = vus_rows(&manifest.metrics);
+            manifest.metrics = self.export_metrics(&manifest, Some(until)).await?;
+            manifest.time_window.to = until;
+            manifest.save(&self.output_directory).await?;
 
-        self.export_metric::<DataReceivedMetric>(true).await?;
-        self.export_metric::<DataSentMetric>(true).await?;
+            if vus_rows(&manifest.metrics) > previous_vus_rows {
+                last_activity = Instant::now();
+            } else if last_activity.elapsed() >= idle_timeout {
+                if !self.quiet {
+                    println!(
+                        "The `vus` metric hasn't been updated for {0:?}",
+                        idle_timeout
+                    );
+                }
+                break;
+            }
 
-        self.export_metric::<HttpReqBlockedMetric>(true).await?;
-        self.export_metric::<HttpReqConnectingMetric>(true).await?;
-        self.export_metric::<HttpReqDurationMetric>(true).await?;
-        self.export_metric::<HttpReqFailedMetric>(true).await?;
-        self.export_metric::<HttpReqReceivingMetric>(true).await?;
-        self.export_metric::<HttpReqSendingMetric>(true).await?;
-        self.export_metric::<HttpReqTlsHandshakingMetric>(true)
-            .await?;
-        self.export_metric::<HttpReqWaitingMetric>(true).await?;
+            sleep(StdDuration::from_secs(poll_interval)).await;
+        }
 
-        self.export_metric::<IterationMetric>(false).await?;
-        self.export_metric::<IterationsDurationMetric>(true).await?;
+        // The test has stopped, so the data points within the lag are final
+        manifest.metrics = self.export_metrics(&manifest, None).await?;
+        manifest.time_window.to = Utc::now();
+        if is_trimmed(&self.trim) {
+            let trimmed_window = self
+                .detect_trimmed_window(manifest.time_window.from)
+                .await?;
+            manifest.filters = self.source.describe_filters(
+                &self.query_filters(manifest.time_window.from, Some(trimmed_window.clone())),
+            );
+            manifest.time_window.trimmed = Some(trimmed_window);
+            manifest.metrics = vec![];
+            manifest.metrics = self.export_metrics(&manifest, None).await?;
+        }
+        self.finish_export(&mut manifest).await
+    }
 
-        self.export_metric::<VusMetric>(false).await?;
-        self.export_metric::<VusMaxMetric>(false).await?;
+    /// Loads the manifest left in the output directory by an interrupted
+    /// export with the same source and filters. Another export is refused
+    /// unless it can be overwritten.
+    async fn resumable_manifest(&self) -> Result<Option<Manifest>> {
+        if !Manifest::exists(&self.output_directory) {
+            return Ok(None);
+        }
 
-        Ok(())
+        let manifest = Manifest::load(&self.output_directory).await?;
+        let filters = self.source.describe_filters(&self.query_filters(
+            manifest.time_window.from,
+            manifest.time_window.trimmed.clone(),
+        ));
+        let is_same_export = manifest.source == self.source.describe()
+            && manifest.run.testid == self.testid
+            && manifest.filters == filters;
+        match (is_same_export, self.force) {
+            (true, _) => Ok(Some(manifest)),
+            (false, true) => Ok(None),
+            (false, false) => {
+                println!(
+                    "`{0}` was exported from another source or with other filters",
+                    self.output_directory.display()
+                );
+                Err(Error::OutputExists(self.output_directory.clone()))
+            }
+        }
     }
 
-    pub async fn export_metric<T: 'static>(&self, apply_exclude: bool) -> Result<()>
+    /// Records checksums of the finished files, writes the manifest and the
+    /// summary and packages the files when requested.
+    async fn finish_export(&self, manifest: &mut Manifest) -> Result<Summary> {
+        for entry in &mut manifest.metrics {
+            entry.sha256 = sha256_checksum(&self.output_directory.join(&entry.file)).await?;
+            for extra in &mut entry.extra_files {
+                extra.sha256 = sha256_checksum(&self.output_directory.join(&extra.file)).await?;
+            }
+        }
+        let summary = self.save_results(manifest).await?;
+        self.create_archive().await?;
+        Ok(summary)
+    }
+
+    /// Exports all metrics up to the given time. When the manifest lists
+    /// earlier exported files, only newer data points are appended to them.
+    async fn export_metrics(
+        &self,
+        manifest: &Manifest,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<MetricEntry>> {
+        let previous = &manifest.metrics;
+        let filters = QueryFilters {
+            until,
+            ..self.query_filters(
+                manifest.time_window.from,
+                manifest.time_window.trimmed.clone(),
+            )
+        };
+        // Metrics of the optional k6 modules and executors are exported only
+        // when the test has produced them
+        let measurements = self.source.measurements(&filters).await?;
+        let mut export = MetricExport {
+            report: self,
+            previous,
+            filters: &filters,
+            measurements: &measurements,
+        };
+        let entries = visit_metrics(&mut export).await?;
+        Ok(entries.into_iter().flatten().collect())
+    }
+
+    pub async fn export_metric<T>(
+        &self,
+        previous: &[MetricEntry],
+        filters: &QueryFilters,
+    ) -> Result<MetricEntry>
     where
-        T: K6Metric + Serialize + DeserializeOwned + Send,
+        T: 'static + K6Metric + Serialize + DeserializeOwned + Send,
     {
         let table_name = T::metric_table_name();
-        println!("Exporting data for the `{0}` metrics", table_name);
+        let previous = previous
+            .iter()
+            .find(|entry| entry.measurement == table_name);
+        if previous.is_none() && !self.quiet {
+            println!("Exporting data for the `{0}` metrics", table_name);
+        }
 
-        let query = self.build_query::<T>(apply_exclude)?;
-        let mut response = self.db_client.json_query(query).await?;
-        let data = response.deserialize_next::<T>()?;
+        let query = MetricQuery::new::<T>(filters.clone());
+        let raw_query = self.source.describe_query(&query);
+        let since = previous.and_then(|entry| entry.last_timestamp);
+        let mut batches = fetch_batches::<T>(self.source.as_ref(), &query.since(since)).await?;
 
-        let filename = format!("{0}.csv", table_name);
-        let filepath = Path::new(&self.output_directory).join(&filename);
-        let output_file = File::create(filepath).await?;
-        let mut csv_writer = AsyncSerializer::from_writer(output_file);
+        // Every exporter gets the same batches, so the data is fetched once
+        let mut sinks = Vec::with_capacity(self.exporters.len());
+        for (index, exporter) in self.exporters.iter().enumerate() {
+            let append_to = previous.and_then(|entry| match index {
+                0 => Some(entry.file.as_str()),
+                _ => entry
+                    .extra_files
+                    .iter()
+                    .find(|extra| extra.format == exporter.format())
+                    .map(|extra| extra.file.as_str()),
+            });
+            sinks.push(
+                exporter
+                    .open(&self.output_directory, table_name, T::schema(), append_to)
+                    .await?,
+            );
+        }
 
-        for series in data.series {
-            for record in series.values {
-                csv_writer.serialize(record).await?;
+        let mut rows = previous.map(|entry| entry.rows).unwrap_or(0);
+        let mut first_timestamp = previous.and_then(|entry| entry.first_timestamp);
+        let mut last_timestamp = since;
+        while let Some(records) = batches.next_batch().await? {
+            let mut batch = Vec::with_capacity(records.len());
+            for record in &records {
+                let time = record.time();
+                first_timestamp = Some(first_timestamp.map_or(time, |first| first.min(time)));
+                last_timestamp = Some(last_timestamp.map_or(time, |last| last.max(time)));
+                rows += 1;
+                batch.push(to_row(record)?);
+            }
+            for sink in sinks.iter_mut() {
+                sink.write_batch(&batch).await?;
             }
         }
 
-        Ok(())
+        let mut files = vec![];
+        for (sink, exporter) in sinks.into_iter().zip(&self.exporters) {
+            let filename = sink.finalize().await?;
+            // Checksums are recorded once the file is finished
+            files.push(ExportedFile {
+                format: exporter.format().to_string(),
+                file: filename,
+                sha256: String::new(),
+            });
+        }
+        let primary_file = files.remove(0);
+
+        Ok(MetricEntry {
+            measurement: table_name.to_string(),
+            sha256: primary_file.sha256,
+            file: primary_file.file,
+            query: raw_query,
+            rows,
+            first_timestamp,
+            last_timestamp,
+            extra_files: files,
+        })
     }
 
-    fn build_query<T: K6Metric>(&self, apply_exclude: bool) -> Result<ReadQuery> {
-        let mut raw_query = String::from("SELECT ");
-        let selected_fields = T::query_fields().join(", ");
-        write!(&mut raw_query, "{}", selected_fields)?;
+    /// Makes sure the summary can be computed from the exported files.
+    fn check_exporters(&self) -> Result<()> {
+        let is_readable = self
+            .exporters
+            .iter()
+            .any(|exporter| READABLE_FORMATS.contains(&exporter.format()));
+        match is_readable {
+            true => Ok(()),
+            false => Err(Error::Export(format!(
+                "one of the {} formats has to be exported for the reports",
+                READABLE_FORMATS.join(", ")
+            ))),
+        }
+    }
 
-        let from_statement = format!(
-            " FROM {0}.{1}.{2}",
-            self.db_client.database_name(),
-            self.retention_policy_name,
-            T::metric_table_name()
-        );
-        write!(&mut raw_query, "{}", from_statement)?;
+    async fn save_results(&self, manifest: &Manifest) -> Result<Summary> {
+        manifest.save(&self.output_directory).await?;
+        let mut summary = Summary::from_directory(&self.output_directory).await?;
+        if let Some(slo_config) = &self.slo_config {
+            summary.slo =
+                Some(SloReport::from_directory(&self.output_directory, slo_config).await?);
+        }
+        summary.save(&self.output_directory).await?;
+        self.register_run().await?;
+        Ok(summary)
+    }
+
+    async fn create_archive(&self) -> Result<()> {
+        if let Some(archive_path) = &self.archive_path {
+            if !self.quiet {
+                println!("Packaging the exported data into `{0}`", archive_path);
+            }
+            create_archive(&self.output_directory, Path::new(archive_path)).await?;
+        }
+
+        Ok(())
+    }
 
-        let mut filters: Vec<String> = vec![];
+    /// Queries the `vus` metric of the whole test to find the part of it
+    /// left after trimming.
+    async fn detect_trimmed_window(&self, from: Option<DateTime<Utc>>) -> Result<TrimmedWindow> {
+        let query = MetricQuery::new::<VusMetric>(self.query_filters(from, None));
+        let vus = fetch_metric::<VusMetric>(self.source.as_ref(), &query).await?;
 
-        if self.from.is_some() {
-            let minutes_offset = self.from.unwrap_or(0);
-            let start_timestamp = self.invoked_at - Duration::minutes(minutes_offset as i64);
-            let filter_clause = format!(
-                "time > '{}'",
-                start_timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        let trimmed_window = trimmed_window(&self.trim, &vus)?;
+        if !self.quiet {
+            println!(
+                "Exporting data from {0} to {1} ({2} trimming)",
+                trimmed_window.from.to_rfc3339(),
+                trimmed_window.to.to_rfc3339(),
+                trimmed_window.method
             );
-            filters.push(filter_clause);
         }
+        Ok(trimmed_window)
+    }
 
-        if apply_exclude {
-            if self.exclude_setup_steps {
-                filters.push(r#""group"!='::setup'"#.to_string());
-            }
+    fn create_manifest(&self, trimmed_window: Option<TrimmedWindow>) -> Manifest {
+        let source = self.source.describe();
+        let filters = self
+            .source
+            .describe_filters(&self.query_filters(self.start_timestamp(), trimmed_window.clone()));
+        let time_window = TimeWindow {
+            from: self.start_timestamp(),
+            to: self.invoked_at,
+            trimmed: trimmed_window,
+        };
+        Manifest::new(self.run.clone(), source, time_window, filters)
+    }
 
-            if self.exclude_teardown_steps {
-                filters.push(r#""group"!='::teardown'"#.to_string());
-            }
+    /// Adds the exported run to the index of the root output directory. It's
+    /// maintained only when each run is exported into its own subdirectory.
+    async fn register_run(&self) -> Result<()> {
+        if self.layout_template.is_none() {
+            return Ok(());
+        }
+
+        let root_directory = Path::new(&self.root_directory);
+        let directory = self
+            .output_directory
+            .strip_prefix(root_directory)
+            .unwrap_or(&self.output_directory)
+            .to_string_lossy()
+            .to_string();
+
+        let mut run_index = RunIndex::load(root_directory).await?;
+        run_index.register(&directory, &self.run);
+        run_index.save(root_directory).await
+    }
+
+    fn start_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.from
+            .map(|minutes_offset| self.invoked_at - Duration::minutes(minutes_offset as i64))
+    }
+
+    /// Filters applied to every exported metric.
+    fn query_filters(
+        &self,
+        from: Option<DateTime<Utc>>,
+        trimmed_window: Option<TrimmedWindow>,
+    ) -> QueryFilters {
+        let mut excluded_groups = vec![];
+        if self.exclude_setup_steps {
+            excluded_groups.push(String::from("::setup"));
+        }
+        if self.exclude_teardown_steps {
+            excluded_groups.push(String::from("::teardown"));
+        }
+
+        QueryFilters {
+            from,
+            trimmed_window,
+            testid: self.testid.clone(),
+            excluded_groups,
+            until: None,
         }
+    }
+}
+
+/// Exports each of the built-in metrics, see [`K6Report::export_metrics`].
+struct MetricExport<'a> {
+    report: &'a K6Report,
+    previous: &'a [MetricEntry],
+    filters: &'a QueryFilters,
+    measurements: &'a HashSet<String>,
+}
 
-        if !filters.is_empty() {
-            let where_clause = filters.join(" AND ");
-            write!(&mut raw_query, "{}", format!(" WHERE {0}", where_clause))?;
+#[async_trait]
+impl MetricVisitor for MetricExport<'_> {
+    type Output = Option<MetricEntry>;
+
+    async fn visit<T>(&mut self, optional: bool) -> Result<Option<MetricEntry>>
+    where
+        T: 'static + K6Metric + Serialize + DeserializeOwned + Send + Sync,
+    {
+        if optional && !self.measurements.contains(T::metric_table_name()) {
+            return Ok(None);
         }
+        let entry = self
+            .report
+            .export_metric::<T>(self.previous, self.filters)
+            .await?;
+        Ok(Some(entry))
+    }
+}
+
+/// Settings of the export. Everything except for the source is optional:
+///
+/// ```no_run
+/// # async fn export() -> k6_reports::error::Result<()> {
+/// use k6_reports::influx::{ConnectionOptions, InfluxSource};
+/// use k6_reports::report::K6Report;
+///
+/// let connection = ConnectionOptions {
+///     host: String::from("localhost"),
+///     port: String::from("8086"),
+///     database: String::from("k6"),
+///     username: None,
+///     password: None,
+///     https: false,
+/// };
+/// let summary = K6Report::builder(InfluxSource::new(&connection))
+///     .output_directory("./reports")
+///     .layout_template(String::from("{date}-{testid}"))
+///     .testid(String::from("checkout-42"))
+///     .exclude_setup_steps(true)
+///     .build()?
+///     .extract_metrics()
+///     .await?;
+/// println!("p95: {} ms", summary.http_req_duration.p95);
+/// # Ok(())
+/// # }
+/// ```
+pub struct K6ReportBuilder {
+    source: Box<dyn MetricSource>,
+    output_directory: String,
+    layout_template: Option<String>,
+    testid: Option<String>,
+    profile: Option<String>,
+    git_sha: Option<String>,
+    force: bool,
+    from: Option<u64>,
+    exclude_setup_steps: bool,
+    exclude_teardown_steps: bool,
+    trim: TrimOptions,
+    window: Option<TrimmedWindow>,
+    slo_config: Option<SloConfig>,
+    compression: Option<Compression>,
+    exporters: Vec<Box<dyn Exporter>>,
+    archive_path: Option<String>,
+    quiet: bool,
+}
+
+impl K6ReportBuilder {
+    /// Directory to export into, `./reports` by default.
+    pub fn output_directory(mut self, output_directory: &str) -> Self {
+        self.output_directory = output_directory.to_string();
+        self
+    }
+
+    /// Exports into a subdirectory named by the template with the `{date}`,
+    /// `{testid}`, `{git_sha}` and `{profile}` placeholders.
+    pub fn layout_template(mut self, layout_template: impl Into<Option<String>>) -> Self {
+        self.layout_template = layout_template.into();
+        self
+    }
+
+    /// Exports only data tagged with the given `testid` tag.
+    pub fn testid(mut self, testid: impl Into<Option<String>>) -> Self {
+        self.testid = testid.into();
+        self
+    }
+
+    /// Name of the test profile stored with the run metadata.
+    pub fn profile(mut self, profile: impl Into<Option<String>>) -> Self {
+        self.profile = profile.into();
+        self
+    }
 
-        Ok(ReadQuery::new(raw_query))
+    /// Commit of the tested code stored with the run metadata.
+    pub fn git_sha(mut self, git_sha: impl Into<Option<String>>) -> Self {
+        self.git_sha = git_sha.into();
+        self
     }
+
+    /// Overwrites data exported into the same directory earlier. Files of
+    /// the earlier export are removed first.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Exports only the last minutes of data.
+    pub fn from(mut self, minutes: impl Into<Option<u64>>) -> Self {
+        self.from = minutes.into();
+        self
+    }
+
+    pub fn exclude_setup_steps(mut self, exclude: bool) -> Self {
+        self.exclude_setup_steps = exclude;
+        self
+    }
+
+    pub fn exclude_teardown_steps(mut self, exclude: bool) -> Self {
+        self.exclude_teardown_steps = exclude;
+        self
+    }
+
+    /// Cuts the warm-up and ramp-down phases off the exported data.
+    pub fn trim(mut self, trim: TrimOptions) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Exports only data points between the given times instead of trimming
+    /// the test.
+    pub fn window(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.window = Some(TrimmedWindow {
+            method: String::from("window"),
+            from,
+            to,
+        });
+        self
+    }
+
+    /// Adds the Apdex and SLO report to the summary.
+    pub fn slo_config(mut self, slo_config: impl Into<Option<SloConfig>>) -> Self {
+        self.slo_config = slo_config.into();
+        self
+    }
+
+    /// Compresses the CSV files when no exporters are added.
+    pub fn compression(mut self, compression: impl Into<Option<Compression>>) -> Self {
+        self.compression = compression.into();
+        self
+    }
+
+    /// Adds an exporter writing the metrics in its format. The first added
+    /// exporter writes the main file of each metric, and CSV is used when
+    /// none is added. The reports read the data back from the CSV or JSON
+    /// files, so one of them has to be exported.
+    pub fn exporter(mut self, exporter: Box<dyn Exporter>) -> Self {
+        self.exporters.push(exporter);
+        self
+    }
+
+    /// Packages the exported files into the archive after the export.
+    pub fn archive_path(mut self, archive_path: impl Into<Option<String>>) -> Self {
+        self.archive_path = archive_path.into();
+        self
+    }
+
+    /// Doesn't print the progress of the export, e.g. which metrics are
+    /// being exported.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Fails when the layout template doesn't render into a directory name,
+    /// or when the SLO report is requested from a source with aggregates only.
+    pub fn build(self) -> Result<K6Report> {
+        if self.slo_config.is_some() {
+            self.source
+                .describe()
+                .ensure_not_aggregated("the request durations")?;
+        }
+        let invoked_at = Utc::now();
+        let exporters = match self.exporters.is_empty() {
+            true => vec![Box::new(CsvExporter::new(self.compression)) as Box<dyn Exporter>],
+            false => self.exporters,
+        };
+        let run =
+            RunMetadata::new(invoked_at, &self.testid, &self.profile).with_git_sha(self.git_sha);
+
+        Ok(K6Report {
+            invoked_at,
+            output_directory: run_directory(&self.output_directory, &self.layout_template, &run)?,
+            root_directory: self.output_directory,
+            layout_template: self.layout_template,
+            force: self.force,
+            run,
+            source: self.source,
+            from: self.from,
+            exclude_setup_steps: self.exclude_setup_steps,
+            exclude_teardown_steps: self.exclude_teardown_steps,
+            testid: self.testid,
+            trim: self.trim,
+            window: self.window,
+            slo_config: self.slo_config,
+            exporters,
+            archive_path: self.archive_path,
+            quiet: self.quiet,
+        })
+    }
+}
+
+fn vus_rows(metrics: &[MetricEntry]) -> u64 {
+    metrics
+        .iter()
+        .find(|entry| entry.measurement == VusMetric::metric_table_name())
+        .map(|entry| entry.rows)
+        .unwrap_or(0)
 }
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::html::{format_duration, format_rate, render_saturation};
+use crate::metrics::{HttpReqDurationMetric, VusMetric};
+use crate::output::OutputFormat;
+use crate::statistics::{percentile, sort_values};
+use crate::table::TextTable;
+
+/// Throughput per VU below this share of the baseline means that adding VUs
+/// doesn't add throughput anymore.
+pub const THROUGHPUT_EFFICIENCY_THRESHOLD: f64 = 0.8;
+
+/// p95 latency above this multiple of the baseline means that requests queue
+/// up somewhere in the system.
+pub const LATENCY_DEGRADATION_THRESHOLD: f64 = 1.5;
+
+/// Levels with fewer requests are too noisy to be used as the baseline.
+const MIN_BASELINE_REQUESTS: u64 = 20;
+
+/// Default number of VU levels when the step isn't given.
+const DEFAULT_LEVELS: f64 = 20.0;
+
+/// Load levels of a ramping test and the point where the system saturates.
+#[derive(Serialize, Debug, Clone)]
+pub struct Saturation {
+    /// Width of each level in VUs.
+    pub step: u64,
+    pub levels: Vec<LoadLevel>,
+    /// Index of the first level where throughput stopped scaling and latency
+    /// climbed, if there is one.
+    pub knee: Option<usize>,
+    /// The highest level before the knee or the highest tested level when the
+    /// system didn't saturate.
+    pub max_sustainable: Option<LoadLevel>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LoadLevel {
+    /// Average number of active VUs while the test stayed at the level.
+    pub vus: f64,
+    /// How long the test stayed at the level.
+    pub duration_seconds: f64,
+    pub requests: u64,
+    pub requests_per_second: f64,
+    pub requests_per_second_per_vu: f64,
+    pub error_rate: f64,
+    pub p50_duration: f64,
+    pub p95_duration: f64,
+    /// Throughput per VU relative to the baseline level.
+    pub efficiency: f64,
+    /// p95 latency relative to the baseline level.
+    pub latency_ratio: f64,
+}
+
+#[derive(Default)]
+struct LevelData {
+    duration_seconds: f64,
+    vu_seconds: f64,
+    durations: Vec<f64>,
+    failed_requests: u64,
+}
+
+impl Saturation {
+    /// Splits the test into levels by the number of active VUs at the time of
+    /// each request, so runs exported from Prometheus are refused.
+    pub async fn from_directory(directory: &Path, step: &Option<u64>) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the requests")?;
+        let mut vus = dataset.load_metric::<VusMetric>().await?;
+        let durations = dataset.load_metric::<HttpReqDurationMetric>().await?;
+        vus.sort_by_key(|point| point.time);
+
+        let peak_vus = vus.iter().map(|point| point.value).fold(0.0, f64::max);
+        let step = step
+            .unwrap_or_else(|| (peak_vus / DEFAULT_LEVELS).ceil() as u64)
+            .max(1);
+        let level_of = |value: f64| (value.round() as u64 / step) * step;
+
+        let mut levels: BTreeMap<u64, LevelData> = BTreeMap::new();
+        for pair in vus.windows(2) {
+            let elapsed = (pair[1].time - pair[0].time).num_milliseconds() as f64 / 1000.0;
+            let level = levels.entry(level_of(pair[0].value)).or_default();
+            level.duration_seconds += elapsed;
+            level.vu_seconds += pair[0].value * elapsed;
+        }
+        for record in &durations {
+            // The number of VUs reported last before the request
+            let index = vus.partition_point(|point| point.time <= record.time);
+            if index == 0 {
+                continue;
+            }
+            let level = levels.entry(level_of(vus[index - 1].value)).or_default();
+            level.durations.push(record.value);
+            if !record.expected_response {
+                level.failed_requests += 1;
+            }
+        }
+
+        let mut levels: Vec<LoadLevel> = levels
+            .into_values()
+            .filter(|data| data.vu_seconds > 0.0 && !data.durations.is_empty())
+            .map(|mut data| {
+                sort_values(&mut data.durations);
+                let vus = data.vu_seconds / data.duration_seconds;
+                let requests = data.durations.len() as u64;
+                let requests_per_second = requests as f64 / data.duration_seconds;
+                LoadLevel {
+                    vus,
+                    duration_seconds: data.duration_seconds,
+                    requests,
+                    requests_per_second,
+                    requests_per_second_per_vu: requests_per_second / vus,
+                    error_rate: data.failed_requests as f64 / requests as f64,
+                    p50_duration: percentile(&data.durations, 50.0),
+                    p95_duration: percentile(&data.durations, 95.0),
+                    efficiency: 1.0,
+                    latency_ratio: 1.0,
+                }
+            })
+            .collect();
+
+        let baseline = levels
+            .iter()
+            .find(|level| level.requests >= MIN_BASELINE_REQUESTS)
+            .or_else(|| levels.first())
+            .cloned();
+        if let Some(baseline) = baseline {
+            for level in levels.iter_mut() {
+                if baseline.requests_per_second_per_vu > 0.0 {
+                    level.efficiency =
+                        level.requests_per_second_per_vu / baseline.requests_per_second_per_vu;
+                }
+                if baseline.p95_duration > 0.0 {
+                    level.latency_ratio = level.p95_duration / baseline.p95_duration;
+                }
+            }
+        }
+
+        let knee = levels.iter().position(|level| {
+            level.efficiency < THROUGHPUT_EFFICIENCY_THRESHOLD
+                && level.latency_ratio > LATENCY_DEGRADATION_THRESHOLD
+        });
+        let max_sustainable = match knee {
+            Some(0) => None,
+            Some(knee) => Some(levels[knee - 1].clone()),
+            None => levels.last().cloned(),
+        };
+
+        Ok(Saturation {
+            step,
+            levels,
+            knee,
+            max_sustainable,
+        })
+    }
+
+    /// Short conclusion of the analysis.
+    pub fn conclusion(&self) -> String {
+        if self.levels.is_empty() {
+            return String::from("No data to analyze");
+        }
+
+        match (self.knee, &self.max_sustainable) {
+            (Some(knee), Some(level)) => format!(
+                "Saturation starts at {:.0} VUs; the estimated max sustainable load is {:.0} VUs ({:.2} requests/s, p95 {} ms)",
+                self.levels[knee].vus,
+                level.vus,
+                level.requests_per_second,
+                format_duration(level.p95_duration)
+            ),
+            (Some(knee), None) => format!(
+                "The system is saturated already at {:.0} VUs",
+                self.levels[knee].vus
+            ),
+            (None, Some(level)) => format!(
+                "No saturation detected up to {:.0} VUs ({:.2} requests/s, p95 {} ms)",
+                level.vus,
+                level.requests_per_second,
+                format_duration(level.p95_duration)
+            ),
+            (None, None) => String::from("No saturation detected"),
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_saturation(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut table = TextTable::new(&[
+            "VUs",
+            "Duration (s)",
+            "Requests/s",
+            "Requests/s per VU",
+            "Efficiency",
+            "p50 (ms)",
+            "p95 (ms)",
+            "p95 ratio",
+            "Error rate",
+            "",
+        ]);
+        for (index, level) in self.levels.iter().enumerate() {
+            table.add_row(vec![
+                format!("{:.1}", level.vus),
+                format!("{:.0}", level.duration_seconds),
+                format!("{:.2}", level.requests_per_second),
+                format!("{:.3}", level.requests_per_second_per_vu),
+                format_rate(level.efficiency),
+                format_duration(level.p50_duration),
+                format_duration(level.p95_duration),
+                format!("{:.2}x", level.latency_ratio),
+                format_rate(level.error_rate),
+                match Some(index) == self.knee {
+                    true => String::from("<- knee"),
+                    false => String::new(),
+                },
+            ]);
+        }
+
+        let title = format!("Saturation ({} VUs per level)", self.step);
+        match format {
+            OutputFormat::Markdown => format!(
+                "## {}\n\n{}\n{}\n",
+                title,
+                table.to_markdown(),
+                self.conclusion()
+            ),
+            _ => format!("{}\n{}\n{}\n", title, table.to_console(), self.conclusion()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::test_support::test_directory;
+
+    const HEADER: &str =
+        "time,expected_response,group,method,name,proto,scenario,status,tls_version,url,value\n";
+
+    /// Writes a ramping run staying 10 seconds at each level of VUs, given
+    /// with the number of requests and their duration.
+    fn write_ramp(directory: &Path, levels: &[(u64, u64, f64)]) {
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let time = |milliseconds: i64| {
+            (start + Duration::milliseconds(milliseconds))
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string()
+        };
+
+        let mut vus = String::from("time,value\n");
+        let mut durations = String::from(HEADER);
+        for (index, (level_vus, requests, duration)) in levels.iter().enumerate() {
+            let level_start = index as i64 * 10_000;
+            vus.push_str(&format!("{},{}\n", time(level_start), level_vus));
+            for request in 0..*requests as i64 {
+                durations.push_str(&format!(
+                    "{},true,,GET,/a,HTTP/1.1,default,200,,/a,{}\n",
+                    time(level_start + request * 10_000 / *requests as i64),
+                    duration
+                ));
+            }
+        }
+        vus.push_str(&format!("{},0\n", time(levels.len() as i64 * 10_000)));
+        std::fs::write(directory.join("vus.csv"), vus).unwrap();
+        std::fs::write(directory.join("http_req_duration.csv"), durations).unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_knee_is_where_throughput_stops_scaling_and_latency_climbs() {
+        let directory = test_directory("saturation", "knee");
+        write_ramp(
+            &directory,
+            &[(10, 100, 100.0), (20, 200, 110.0), (40, 240, 400.0)],
+        );
+
+        let saturation = Saturation::from_directory(&directory, &Some(10))
+            .await
+            .unwrap();
+        let levels: Vec<(f64, f64)> = saturation
+            .levels
+            .iter()
+            .map(|level| (level.vus, level.requests_per_second))
+            .collect();
+        assert_eq!(levels, [(10.0, 10.0), (20.0, 20.0), (40.0, 24.0)]);
+        assert_eq!(saturation.levels[2].efficiency, 0.6);
+        assert_eq!(saturation.levels[2].latency_ratio, 4.0);
+        assert_eq!(saturation.knee, Some(2));
+        assert_eq!(saturation.max_sustainable.as_ref().unwrap().vus, 20.0);
+        assert!(saturation
+            .conclusion()
+            .starts_with("Saturation starts at 40 VUs"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn slower_requests_alone_are_no_knee() {
+        let directory = test_directory("saturation", "scaling");
+        // Latency climbs, but throughput keeps scaling with the VUs
+        write_ramp(&directory, &[(10, 100, 100.0), (20, 200, 400.0)]);
+
+        let saturation = Saturation::from_directory(&directory, &Some(10))
+            .await
+            .unwrap();
+        assert_eq!(saturation.knee, None);
+        assert_eq!(saturation.max_sustainable.as_ref().unwrap().vus, 20.0);
+        assert!(saturation
+            .conclusion()
+            .starts_with("No saturation detected up to 20 VUs"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use serde::Deserialize;
+use tokio::fs::read;
+use tokio::net::TcpListener;
+
+use crate::comparison::{Comparison, ComparisonOptions};
+use crate::error::{Error, Result};
+use crate::html::{escape, page, render_comparison, render_index, render_run};
+use crate::layout::{discover_runs, ExportedRun};
+use crate::manifest::MANIFEST_FILENAME;
+use crate::summary::{Summary, SUMMARY_FILENAME};
+
+/// HTTP server for browsing exported runs. Everything is rendered on demand
+/// from the exported files, so new runs show up without a restart.
+pub struct ReportServer {
+    root_directory: PathBuf,
+    address: SocketAddr,
+}
+
+struct ServerState {
+    root_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct CompareParams {
+    base: String,
+    target: String,
+}
+
+/// Error page returned by the handlers.
+struct HttpError(StatusCode, String);
+
+impl From<Error> for HttpError {
+    fn from(error: Error) -> Self {
+        HttpError(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let body = page("Error", &format!("<p>{}</p>\n", escape(&self.1)));
+        (self.0, Html(body)).into_response()
+    }
+}
+
+type HttpResult<T> = std::result::Result<T, HttpError>;
+
+impl ReportServer {
+    pub fn new(root_directory: &String, address: &SocketAddr) -> Self {
+        ReportServer {
+            root_directory: PathBuf::from(root_directory),
+            address: address.to_owned(),
+        }
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let state = Arc::new(ServerState {
+            root_directory: self.root_directory.clone(),
+        });
+        let router = Router::new()
+            .route("/", get(index))
+            .route("/runs/:name", get(run_report))
+            .route("/runs/:name/files/:file", get(run_file))
+            .route("/compare", get(compare))
+            .with_state(state);
+
+        let listener = TcpListener::bind(self.address).await?;
+        println!(
+            "Serving reports from `{0}` at http://{1}",
+            self.root_directory.display(),
+            self.address
+        );
+        axum::serve(listener, router).await?;
+        Ok(())
+    }
+}
+
+async fn index(State(state): State<Arc<ServerState>>) -> HttpResult<Html<String>> {
+    let mut runs = vec![];
+    for run in discover_runs(&state.root_directory).await? {
+        // A broken run shouldn't hide all others from the index
+        let summary = Summary::load(&run.path).await.ok();
+        runs.push((run, summary));
+    }
+    Ok(Html(render_index(&runs)))
+}
+
+async fn run_report(
+    State(state): State<Arc<ServerState>>,
+    Path(name): Path<String>,
+) -> HttpResult<Html<String>> {
+    let run = find_run(&state, &name).await?;
+    let summary = Summary::load(&run.path).await?;
+    Ok(Html(render_run(&run, &summary)))
+}
+
+async fn run_file(
+    State(state): State<Arc<ServerState>>,
+    Path((name, file)): Path<(String, String)>,
+) -> HttpResult<Response> {
+    let run = find_run(&state, &name).await?;
+
+    // Only files described by the manifest can be downloaded
+    let is_known_file = file == MANIFEST_FILENAME
+        || file == SUMMARY_FILENAME
+        || run.manifest.metrics.iter().any(|metric| {
+            metric.file == file || metric.extra_files.iter().any(|extra| extra.file == file)
+        });
+    if !is_known_file {
+        return Err(HttpError(
+            StatusCode::NOT_FOUND,
+            format!("file `{}` not found", file),
+        ));
+    }
+
+    let content = read(run.path.join(&file)).await.map_err(Error::from)?;
+    let content_disposition = format!("attachment; filename=\"{}\"", file);
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                String::from("application/octet-stream"),
+            ),
+            (header::CONTENT_DISPOSITION, content_disposition),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+async fn compare(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<CompareParams>,
+) -> HttpResult<Html<String>> {
+    let base = find_run(&state, &params.base).await?;
+    let target = find_run(&state, &params.target).await?;
+    let comparison = Comparison::load(&base, &target, &ComparisonOptions::default()).await?;
+    Ok(Html(render_comparison(&comparison)))
+}
+
+/// Looks up the run among the discovered ones, so that requests can't reach
+/// anything outside of the exported runs.
+async fn find_run(state: &ServerState, name: &str) -> HttpResult<ExportedRun> {
+    discover_runs(&state.root_directory)
+        .await?
+        .into_iter()
+        .find(|run| run.name == name)
+        .ok_or_else(|| HttpError(StatusCode::NOT_FOUND, format!("run `{}` not found", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::layout::RunMetadata;
+    use crate::manifest::{Manifest, MetricEntry, Source, TimeWindow};
+    use crate::test_support::test_directory;
+
+    /// Exports an empty run into the `run-1` subdirectory of the root.
+    async fn serve_run(name: &str) -> Arc<ServerState> {
+        let root_directory = test_directory("server", name);
+        let run_directory = root_directory.join("run-1");
+        std::fs::create_dir_all(&run_directory).unwrap();
+        std::fs::write(run_directory.join("vus.csv"), "time,value\n").unwrap();
+        std::fs::write(run_directory.join("notes.txt"), "private").unwrap();
+
+        let mut manifest = Manifest::new(
+            RunMetadata::new(Utc::now(), &None, &None),
+            Source {
+                kind: String::from("influxdb"),
+                url: String::from("http://localhost:8086"),
+                database: None,
+                retention_policy: None,
+            },
+            TimeWindow {
+                from: None,
+                to: Utc::now(),
+                trimmed: None,
+            },
+            vec![],
+        );
+        manifest.metrics.push(MetricEntry {
+            measurement: String::from("vus"),
+            file: String::from("vus.csv"),
+            query: String::new(),
+            rows: 0,
+            first_timestamp: None,
+            last_timestamp: None,
+            sha256: String::new(),
+            extra_files: vec![],
+        });
+        manifest.save(&run_directory).await.unwrap();
+
+        Arc::new(ServerState { root_directory })
+    }
+
+    async fn download(state: &Arc<ServerState>, name: &str, file: &str) -> StatusCode {
+        let path = Path((name.to_string(), file.to_string()));
+        match run_file(State(state.clone()), path).await {
+            Ok(response) => response.status(),
+            Err(HttpError(status, _)) => status,
+        }
+    }
+
+    #[tokio::test]
+    async fn only_discovered_runs_are_found() {
+        let state = serve_run("runs").await;
+
+        assert!(find_run(&state, "run-1").await.is_ok());
+        for name in ["run-2", "..", "../run-1", "."] {
+            let status = find_run(&state, name).await.err().map(|error| error.0);
+            assert_eq!(status, Some(StatusCode::NOT_FOUND), "{}", name);
+        }
+
+        std::fs::remove_dir_all(&state.root_directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn only_files_of_the_manifest_are_downloaded() {
+        let state = serve_run("files").await;
+
+        assert_eq!(download(&state, "run-1", "vus.csv").await, StatusCode::OK);
+        assert_eq!(
+            download(&state, "run-1", MANIFEST_FILENAME).await,
+            StatusCode::OK
+        );
+        for file in ["notes.txt", "../run-1/vus.csv", "/etc/passwd"] {
+            assert_eq!(
+                download(&state, "run-1", file).await,
+                StatusCode::NOT_FOUND,
+                "{}",
+                file
+            );
+        }
+        assert_eq!(
+            download(&state, "run-2", "vus.csv").await,
+            StatusCode::NOT_FOUND
+        );
+
+        std::fs::remove_dir_all(&state.root_directory).unwrap();
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::read;
+
+use crate::dataset::Dataset;
+use crate::error::{Error, Result};
+use crate::html::{format_rate, render_slo};
+use crate::metrics::HttpReqDurationMetric;
+use crate::output::OutputFormat;
+use crate::summary::{endpoint_method, endpoint_name};
+use crate::table::TextTable;
+
+/// Apdex T used when the configuration doesn't set one (in milliseconds).
+const DEFAULT_SATISFIED_THRESHOLD: f64 = 500.0;
+
+/// Requests slower than T are tolerated up to 4T by the Apdex definition.
+const TOLERATING_MULTIPLIER: f64 = 4.0;
+
+/// Share of requests under the latency threshold when the target isn't set.
+const DEFAULT_LATENCY_TARGET: f64 = 0.99;
+
+/// Apdex thresholds and SLO targets, loaded from a JSON file. The top-level
+/// values apply to endpoints that don't match any of the rules.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SloConfig {
+    #[serde(default)]
+    pub apdex: ApdexThresholds,
+    #[serde(default)]
+    pub slo: Option<SloTarget>,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointRule>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApdexThresholds {
+    /// Requests up to this duration (in milliseconds) are satisfying.
+    pub satisfied: f64,
+    /// Requests up to this duration are tolerable. Four times the satisfied
+    /// threshold by default.
+    pub tolerating: Option<f64>,
+    /// The lowest acceptable score, if the score is used as a gate.
+    pub min_score: Option<f64>,
+}
+
+impl Default for ApdexThresholds {
+    fn default() -> Self {
+        ApdexThresholds {
+            satisfied: DEFAULT_SATISFIED_THRESHOLD,
+            tolerating: None,
+            min_score: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SloTarget {
+    /// Latency threshold in milliseconds.
+    pub latency: Option<f64>,
+    /// Share of requests that must be faster than the threshold, e.g. 0.99.
+    pub latency_target: Option<f64>,
+    /// Share of failed requests which the error rate has to stay below, e.g.
+    /// 0.005 for less than 0.5%. Zero allows no failed requests at all.
+    pub max_error_rate: Option<f64>,
+}
+
+/// Overrides the thresholds for requests matching all of the given fields.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EndpointRule {
+    /// Exact value of the `name` tag.
+    pub name: Option<String>,
+    /// URL pattern where `*` matches any characters.
+    pub url: Option<String>,
+    /// Group path (e.g. `::auth`), which includes its subgroups.
+    pub group: Option<String>,
+    pub apdex: Option<ApdexThresholds>,
+    pub slo: Option<SloTarget>,
+}
+
+impl EndpointRule {
+    fn matches(&self, record: &HttpReqDurationMetric) -> bool {
+        let name_matches = match &self.name {
+            Some(name) => record.name.as_deref() == Some(name.as_str()),
+            None => true,
+        };
+        let url_matches = match &self.url {
+            Some(pattern) => wildcard_match(pattern, &record.url),
+            None => true,
+        };
+        let group_matches = match (&self.group, &record.group) {
+            (Some(group), Some(record_group)) => {
+                record_group == group || record_group.starts_with(&format!("{}::", group))
+            }
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        name_matches && url_matches && group_matches
+    }
+
+    fn description(&self) -> String {
+        let mut fields = vec![];
+        if let Some(name) = &self.name {
+            fields.push(format!("name={}", name));
+        }
+        if let Some(url) = &self.url {
+            fields.push(format!("url={}", url));
+        }
+        if let Some(group) = &self.group {
+            fields.push(format!("group={}", group));
+        }
+        fields.join(" ")
+    }
+}
+
+impl SloConfig {
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = read(path).await?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Index of the first rule matching the request.
+    fn find_rule(&self, record: &HttpReqDurationMetric) -> Option<usize> {
+        self.endpoints.iter().position(|rule| rule.matches(record))
+    }
+
+    fn apdex_thresholds(&self, rule: Option<usize>) -> &ApdexThresholds {
+        rule.and_then(|index| self.endpoints[index].apdex.as_ref())
+            .unwrap_or(&self.apdex)
+    }
+
+    fn slo_target(&self, rule: Option<usize>) -> Option<&SloTarget> {
+        rule.and_then(|index| self.endpoints[index].slo.as_ref())
+            .or(self.slo.as_ref())
+    }
+}
+
+/// Apdex scores and SLO compliance per endpoint. Requests of an endpoint
+/// matching different rules are reported separately.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SloReport {
+    pub endpoints: Vec<EndpointSlo>,
+    /// Number of Apdex scores below the minimum and SLOs out of the budget.
+    pub breaches: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EndpointSlo {
+    pub method: String,
+    pub name: String,
+    /// Description of the matched rule. Empty for the default thresholds.
+    pub rule: String,
+    pub requests: u64,
+    pub apdex: Apdex,
+    pub slo: Option<SloCompliance>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Apdex {
+    pub satisfied_threshold: f64,
+    pub tolerating_threshold: f64,
+    pub satisfied: u64,
+    pub tolerating: u64,
+    /// Failed requests are always frustrating.
+    pub frustrated: u64,
+    pub score: f64,
+    pub rating: String,
+    pub min_score: Option<f64>,
+    pub passed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SloCompliance {
+    pub target: SloTarget,
+    /// Share of requests faster than the latency threshold.
+    pub latency_compliance: Option<f64>,
+    /// Share of the allowed slow requests that was used up. Values above 1
+    /// mean that the budget is exceeded. Missing for an empty budget (a
+    /// latency target of 100%) with slow requests.
+    pub latency_budget_consumed: Option<f64>,
+    /// Fewer requests were fast than the latency target requires.
+    #[serde(default)]
+    pub latency_budget_exhausted: bool,
+    pub error_rate: f64,
+    pub error_budget_consumed: Option<f64>,
+    /// The error rate reached the limit, so even the whole budget (a consumed
+    /// share of 1) fails the SLO.
+    #[serde(default)]
+    pub error_budget_exhausted: bool,
+    pub passed: bool,
+}
+
+impl SloCompliance {
+    pub fn latency_budget(&self) -> String {
+        format_budget(self.latency_budget_consumed, self.latency_budget_exhausted)
+    }
+
+    pub fn error_budget(&self) -> String {
+        format_budget(self.error_budget_consumed, self.error_budget_exhausted)
+    }
+}
+
+impl SloReport {
+    pub async fn from_directory(directory: &Path, config: &SloConfig) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the request durations")?;
+        let durations = dataset.load_metric::<HttpReqDurationMetric>().await?;
+        Ok(SloReport::new(&durations, config))
+    }
+
+    pub fn new(durations: &[HttpReqDurationMetric], config: &SloConfig) -> Self {
+        let mut groups: BTreeMap<(String, String, Option<usize>), Vec<&HttpReqDurationMetric>> =
+            BTreeMap::new();
+        for record in durations {
+            let key = (
+                endpoint_method(record),
+                endpoint_name(record),
+                config.find_rule(record),
+            );
+            groups.entry(key).or_default().push(record);
+        }
+
+        let endpoints: Vec<EndpointSlo> = groups
+            .into_iter()
+            .map(|((method, name, rule), records)| EndpointSlo {
+                method,
+                name,
+                rule: rule
+                    .map(|index| config.endpoints[index].description())
+                    .unwrap_or_default(),
+                requests: records.len() as u64,
+                apdex: compute_apdex(&records, config.apdex_thresholds(rule)),
+                slo: config
+                    .slo_target(rule)
+                    .map(|target| compute_compliance(&records, target)),
+            })
+            .collect();
+        let breaches = endpoints
+            .iter()
+            .map(|endpoint| {
+                let slo_failed = endpoint
+                    .slo
+                    .as_ref()
+                    .map(|slo| !slo.passed)
+                    .unwrap_or(false);
+                usize::from(!endpoint.apdex.passed) + usize::from(slo_failed)
+            })
+            .sum();
+
+        SloReport {
+            endpoints,
+            breaches,
+        }
+    }
+
+    /// Fails when any of the Apdex scores or SLOs is breached, so the report
+    /// can be used as a gate in CI.
+    pub fn check(&self) -> Result<()> {
+        match self.breaches {
+            0 => Ok(()),
+            breaches => Err(Error::ThresholdsFailed(breaches)),
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_slo(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut table = TextTable::new(&[
+            "Endpoint",
+            "Rule",
+            "Requests",
+            "Apdex",
+            "Rating",
+            "Latency SLI",
+            "Latency budget",
+            "Error rate",
+            "Error budget",
+            "Status",
+        ]);
+        for endpoint in &self.endpoints {
+            let slo = endpoint.slo.as_ref();
+            let passed = endpoint.apdex.passed && slo.map(|slo| slo.passed).unwrap_or(true);
+            table.add_row(vec![
+                format!("{} {}", endpoint.method, endpoint.name),
+                endpoint.rule.clone(),
+                endpoint.requests.to_string(),
+                format!("{:.2}", endpoint.apdex.score),
+                endpoint.apdex.rating.clone(),
+                optional_rate(slo.and_then(|slo| slo.latency_compliance)),
+                optional_budget(slo, SloCompliance::latency_budget),
+                optional_rate(slo.map(|slo| slo.error_rate)),
+                optional_budget(slo, SloCompliance::error_budget),
+                String::from(if passed { "ok" } else { "BREACHED" }),
+            ]);
+        }
+
+        let conclusion = match self.breaches {
+            0 => String::from("All Apdex scores and SLOs are met"),
+            breaches => format!("{} Apdex score(s) or SLO(s) breached", breaches),
+        };
+        match format {
+            OutputFormat::Markdown => format!(
+                "## Apdex and SLOs\n\n{}\n{}\n",
+                table.to_markdown(),
+                conclusion
+            ),
+            _ => format!("Apdex and SLOs\n{}\n{}\n", table.to_console(), conclusion),
+        }
+    }
+}
+
+pub fn optional_rate(value: Option<f64>) -> String {
+    value.map(format_rate).unwrap_or_else(|| String::from("-"))
+}
+
+pub fn optional_budget(
+    slo: Option<&SloCompliance>,
+    budget: fn(&SloCompliance) -> String,
+) -> String {
+    slo.map(budget).unwrap_or_else(|| String::from("-"))
+}
+
+/// Formats the consumed share of a budget, or tells that an empty budget was
+/// exhausted, where the share can't be computed.
+fn format_budget(consumed: Option<f64>, exhausted: bool) -> String {
+    match (consumed, exhausted) {
+        (Some(consumed), _) => format_rate(consumed),
+        (None, true) => String::from("exhausted"),
+        (None, false) => String::from("-"),
+    }
+}
+
+fn compute_apdex(records: &[&HttpReqDurationMetric], thresholds: &ApdexThresholds) -> Apdex {
+    let tolerating_threshold = thresholds
+        .tolerating
+        .unwrap_or(thresholds.satisfied * TOLERATING_MULTIPLIER);
+    let mut satisfied = 0;
+    let mut tolerating = 0;
+    let mut frustrated = 0;
+    for record in records {
+        match record.value {
+            _ if !record.expected_response => frustrated += 1,
+            value if value <= thresholds.satisfied => satisfied += 1,
+            value if value <= tolerating_threshold => tolerating += 1,
+            _ => frustrated += 1,
+        }
+    }
+
+    let score = match records.len() {
+        0 => 1.0,
+        total => (satisfied as f64 + tolerating as f64 / 2.0) / total as f64,
+    };
+    Apdex {
+        satisfied_threshold: thresholds.satisfied,
+        tolerating_threshold,
+        satisfied,
+        tolerating,
+        frustrated,
+        score,
+        rating: apdex_rating(score).to_string(),
+        min_score: thresholds.min_score,
+        passed: thresholds
+            .min_score
+            .map(|min_score| score >= min_score)
+            .unwrap_or(true),
+    }
+}
+
+/// Standard Apdex rating names.
+fn apdex_rating(score: f64) -> &'static str {
+    match score {
+        score if score >= 0.94 => "excellent",
+        score if score >= 0.85 => "good",
+        score if score >= 0.7 => "fair",
+        score if score >= 0.5 => "poor",
+        _ => "unacceptable",
+    }
+}
+
+fn compute_compliance(records: &[&HttpReqDurationMetric], target: &SloTarget) -> SloCompliance {
+    let total = records.len().max(1) as f64;
+    let failed = records
+        .iter()
+        .filter(|record| !record.expected_response)
+        .count() as f64;
+    let error_rate = failed / total;
+
+    let latency_compliance = target.latency.map(|threshold| {
+        records
+            .iter()
+            .filter(|record| record.value <= threshold)
+            .count() as f64
+            / total
+    });
+    let latency_target = target.latency_target.unwrap_or(DEFAULT_LATENCY_TARGET);
+    let (latency_budget_consumed, latency_budget_exhausted) = match latency_compliance {
+        Some(compliance) => (
+            budget_consumed(1.0 - compliance, 1.0 - latency_target),
+            compliance < latency_target,
+        ),
+        None => (None, false),
+    };
+    let (error_budget_consumed, error_budget_exhausted) = match target.max_error_rate {
+        Some(max_error_rate) => (
+            budget_consumed(error_rate, max_error_rate),
+            error_rate > 0.0 && error_rate >= max_error_rate,
+        ),
+        None => (None, false),
+    };
+
+    SloCompliance {
+        target: SloTarget {
+            latency: target.latency,
+            latency_target: target.latency.map(|_| latency_target),
+            max_error_rate: target.max_error_rate,
+        },
+        latency_compliance,
+        latency_budget_consumed,
+        latency_budget_exhausted,
+        error_rate,
+        error_budget_consumed,
+        error_budget_exhausted,
+        passed: !latency_budget_exhausted && !error_budget_exhausted,
+    }
+}
+
+/// Share of the error budget used by the bad events. The share of an empty
+/// budget is unknown once there's any bad event.
+fn budget_consumed(bad_share: f64, allowed_share: f64) -> Option<f64> {
+    match allowed_share > 0.0 {
+        true => Some(bad_share / allowed_share),
+        false if bad_share > 0.0 => None,
+        false => Some(0.0),
+    }
+}
+
+/// Matches the text against the pattern, where `*` stands for any sequence of
+/// characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn request(duration: f64, expected_response: bool) -> HttpReqDurationMetric {
+        HttpReqDurationMetric {
+            time: Utc::now(),
+            expected_response,
+            group: None,
+            method: Some(String::from("GET")),
+            name: Some(String::from("https://test.k6.io/")),
+            proto: None,
+            scenario: None,
+            status: if expected_response { 200 } else { 500 },
+            tls_version: None,
+            url: String::from("https://test.k6.io/"),
+            value: duration,
+        }
+    }
+
+    fn target(latency_target: f64, max_error_rate: f64) -> SloTarget {
+        SloTarget {
+            latency: Some(500.0),
+            latency_target: Some(latency_target),
+            max_error_rate: Some(max_error_rate),
+        }
+    }
+
+    #[test]
+    fn computes_consumed_budgets() {
+        let requests = [
+            request(100.0, true),
+            request(200.0, true),
+            request(300.0, true),
+            request(900.0, false),
+        ];
+        let records: Vec<&HttpReqDurationMetric> = requests.iter().collect();
+
+        let compliance = compute_compliance(&records, &target(0.5, 0.5));
+        assert_eq!(compliance.latency_compliance, Some(0.75));
+        assert_eq!(compliance.latency_budget_consumed, Some(0.5));
+        assert_eq!(compliance.error_budget_consumed, Some(0.5));
+        assert!(!compliance.latency_budget_exhausted);
+        assert!(compliance.passed);
+
+        let compliance = compute_compliance(&records, &target(0.9, 0.1));
+        assert!(compliance.latency_budget_exhausted);
+        assert!(compliance.error_budget_exhausted);
+        assert!(!compliance.passed);
+    }
+
+    #[test]
+    fn error_rate_at_the_limit_fails() {
+        let requests = [
+            request(100.0, true),
+            request(100.0, true),
+            request(100.0, true),
+            request(100.0, false),
+        ];
+        let records: Vec<&HttpReqDurationMetric> = requests.iter().collect();
+
+        // An error rate of 25% isn't below 25%, while 75% of requests under
+        // the latency threshold meet a target of 75%
+        let compliance = compute_compliance(&records, &target(0.75, 0.25));
+        assert_eq!(compliance.error_budget_consumed, Some(1.0));
+        assert!(compliance.error_budget_exhausted);
+        assert!(!compliance.latency_budget_exhausted);
+        assert!(!compliance.passed);
+
+        let compliance = compute_compliance(&records, &target(0.75, 0.26));
+        assert!(!compliance.error_budget_exhausted);
+        assert!(compliance.passed);
+    }
+
+    #[test]
+    fn exhausts_empty_budgets() {
+        let requests = [request(100.0, true), request(900.0, false)];
+        let records: Vec<&HttpReqDurationMetric> = requests.iter().collect();
+
+        let compliance = compute_compliance(&records, &target(1.0, 0.0));
+        assert_eq!(compliance.latency_budget_consumed, None);
+        assert!(compliance.latency_budget_exhausted);
+        assert_eq!(compliance.latency_budget(), "exhausted");
+        assert_eq!(compliance.error_budget(), "exhausted");
+        assert!(!compliance.passed);
+
+        // The budget stays serializable instead of turning into null
+        let json = serde_json::to_value(&compliance).unwrap();
+        assert_eq!(
+            json["latency_budget_exhausted"],
+            serde_json::Value::Bool(true)
+        );
+
+        let records = vec![&requests[0]];
+        let compliance = compute_compliance(&records, &target(1.0, 0.0));
+        assert_eq!(compliance.latency_budget_consumed, Some(0.0));
+        assert_eq!(compliance.error_budget_consumed, Some(0.0));
+        assert!(compliance.passed);
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(wildcard_match("/api/*", "/api/users"));
+        assert!(wildcard_match("*/users/*/orders", "/api/users/1/orders"));
+        assert!(!wildcard_match("/api/*", "/health"));
+        assert!(!wildcard_match("/a*a", "/a"));
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+use crate::manifest::{Source, TrimmedWindow};
+use crate::metrics::{Column, ColumnKind, K6Metric};
+
+/// Data point of a metric as a map of column names to their values, e.g.
+/// `{"time": "2022-01-01T00:00:00Z", "group": "::login", "value": 12.5}`.
+pub type Row = Map<String, Value>;
+
+/// Number of data points fetched and written at a time, so that big
+/// measurements don't have to fit into memory.
+pub const FETCH_BATCH_SIZE: usize = 10_000;
+
+/// Storage of the test results which the metrics are exported from.
+#[async_trait]
+pub trait MetricSource: Send + Sync {
+    /// Location of the data recorded into the manifest.
+    fn describe(&self) -> Source;
+
+    /// Filters in the query language of the source, recorded into the manifest.
+    fn describe_filters(&self, filters: &QueryFilters) -> Vec<String>;
+
+    /// Query of the whole metric in the query language of the source.
+    fn describe_query(&self, query: &MetricQuery) -> String;
+
+    /// Names of the measurements which have any data. Sources which can
+    /// check it cheaply count only the data matching the filters, e.g. of
+    /// the given `testid`.
+    async fn measurements(&self, filters: &QueryFilters) -> Result<HashSet<String>>;
+
+    /// Fetches data points of the metric ordered by time, batch by batch.
+    /// Files written by k6 are only sorted within each batch.
+    async fn fetch(&self, query: &MetricQuery) -> Result<Box<dyn RowBatches + '_>>;
+}
+
+/// Data points of a metric which the source reads one batch at a time.
+#[async_trait]
+pub trait RowBatches: Send {
+    /// Reads up to [`FETCH_BATCH_SIZE`] data points, `None` after the last one.
+    async fn next_batch(&mut self) -> Result<Option<Vec<Row>>>;
+}
+
+/// Data points which the source gets all at once, e.g. already aggregated
+/// into a few of them.
+pub struct SingleBatch(Option<Vec<Row>>);
+
+impl SingleBatch {
+    pub fn new(rows: Vec<Row>) -> Self {
+        SingleBatch(Some(rows))
+    }
+}
+
+#[async_trait]
+impl RowBatches for SingleBatch {
+    async fn next_batch(&mut self) -> Result<Option<Vec<Row>>> {
+        Ok(self.0.take().filter(|rows| !rows.is_empty()))
+    }
+}
+
+#[async_trait]
+impl<T: MetricSource + ?Sized> MetricSource for Box<T> {
+    fn describe(&self) -> Source {
+        (**self).describe()
+    }
+
+    fn describe_filters(&self, filters: &QueryFilters) -> Vec<String> {
+        (**self).describe_filters(filters)
+    }
+
+    fn describe_query(&self, query: &MetricQuery) -> String {
+        (**self).describe_query(query)
+    }
+
+    async fn measurements(&self, filters: &QueryFilters) -> Result<HashSet<String>> {
+        (**self).measurements(filters).await
+    }
+
+    async fn fetch(&self, query: &MetricQuery) -> Result<Box<dyn RowBatches + '_>> {
+        (**self).fetch(query).await
+    }
+}
+
+/// Conditions applied to every exported metric.
+#[derive(Debug, Clone, Default)]
+pub struct QueryFilters {
+    /// Skip data points up to this time.
+    pub from: Option<DateTime<Utc>>,
+    /// Keep only data points within the trimmed part of the test.
+    pub trimmed_window: Option<TrimmedWindow>,
+    /// Keep only data points with the given `testid` tag.
+    pub testid: Option<String>,
+    /// Skip data points of these groups, e.g. `::setup`.
+    pub excluded_groups: Vec<String>,
+    /// Skip data points after this time, which the follow mode uses to leave
+    /// room for data points written late. It isn't recorded into the manifest.
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Data points of a single metric to fetch.
+#[derive(Debug, Clone)]
+pub struct MetricQuery {
+    pub measurement: &'static str,
+    /// Columns in the InfluxQL notation, where tags are quoted.
+    pub fields: &'static [&'static str],
+    pub columns: &'static [Column],
+    pub filters: QueryFilters,
+    /// Fetch only data points newer than this time, e.g. exported earlier.
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl MetricQuery {
+    pub fn new<T: K6Metric>(filters: QueryFilters) -> Self {
+        MetricQuery {
+            measurement: T::metric_table_name(),
+            fields: T::query_fields(),
+            columns: T::schema(),
+            filters,
+            since: None,
+        }
+    }
+
+    pub fn since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self
+    }
+}
+
+/// Batches of data points deserialized into the type of the metric.
+pub struct MetricBatches<'a, T> {
+    rows: Box<dyn RowBatches + 'a>,
+    measurement: &'static str,
+    metric: PhantomData<T>,
+}
+
+impl<T: K6Metric + DeserializeOwned> MetricBatches<'_, T> {
+    pub async fn next_batch(&mut self) -> Result<Option<Vec<T>>> {
+        let rows = match self.rows.next_batch().await? {
+            Some(rows) => rows,
+            None => return Ok(None),
+        };
+        rows.into_iter()
+            .map(|row| {
+                serde_json::from_value(Value::Object(row)).map_err(|error| Error::Deserialization {
+                    measurement: self.measurement.to_string(),
+                    reason: error.to_string(),
+                })
+            })
+            .collect::<Result<Vec<T>>>()
+            .map(Some)
+    }
+}
+
+/// Fetches data points of the metric in batches of its type.
+pub async fn fetch_batches<'a, T>(
+    source: &'a dyn MetricSource,
+    query: &MetricQuery,
+) -> Result<MetricBatches<'a, T>>
+where
+    T: K6Metric + DeserializeOwned,
+{
+    Ok(MetricBatches {
+        rows: source.fetch(query).await?,
+        measurement: query.measurement,
+        metric: PhantomData,
+    })
+}
+
+/// Fetches every data point of the metric, e.g. of the `vus` metric, which
+/// is small enough to keep in memory.
+pub async fn fetch_metric<T>(source: &dyn MetricSource, query: &MetricQuery) -> Result<Vec<T>>
+where
+    T: K6Metric + DeserializeOwned,
+{
+    let mut batches = fetch_batches::<T>(source, query).await?;
+    let mut result = vec![];
+    while let Some(batch) = batches.next_batch().await? {
+        result.extend(batch);
+    }
+    Ok(result)
+}
+
+/// Converts a typed data point back into a row, e.g. for the exporters.
+pub fn to_row<T: Serialize>(record: &T) -> Result<Row> {
+    match serde_json::to_value(record)? {
+        Value::Object(row) => Ok(row),
+        value => Err(Error::Export(format!(
+            "expected a data point, got `{}`",
+            value
+        ))),
+    }
+}
+
+/// Shapes a data point of a source without a fixed schema into the columns of
+/// the metric. Missing tags are empty, or null in nullable columns, the same as
+/// in InfluxDB.
+pub fn point_row(
+    columns: &[Column],
+    time: DateTime<Utc>,
+    value: f64,
+    tags: &HashMap<String, String>,
+) -> Row {
+    columns
+        .iter()
+        .map(|column| {
+            let value = match column.kind {
+                ColumnKind::Time => {
+                    Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+                }
+                ColumnKind::Field => Value::from(value),
+                ColumnKind::Tag => match tags.get(column.name) {
+                    Some(tag) => Value::String(tag.clone()),
+                    None if column.nullable => Value::Null,
+                    None => Value::String(String::new()),
+                },
+            };
+            (column.name.to_string(), value)
+        })
+        .collect()
+}
@@ -0,0 +1,322 @@
+use rand::Rng;
+
+/// Returns the percentile (0..=100) of the sorted values, using the linear
+/// interpolation between the closest ranks.
+pub fn percentile(sorted_values: &[f64], percent: f64) -> f64 {
+    if sorted_values.is_empty() {
+        return 0.0;
+    }
+
+    let rank = percent / 100.0 * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted_values[lower] * (1.0 - weight) + sorted_values[upper] * weight
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    match values.is_empty() {
+        true => 0.0,
+        false => values.iter().sum::<f64>() / values.len() as f64,
+    }
+}
+
+pub fn sort_values(values: &mut [f64]) {
+    values.sort_by(|left, right| left.total_cmp(right));
+}
+
+/// Sample standard deviation.
+pub fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+
+    let values_mean = mean(values);
+    let variance = values
+        .iter()
+        .map(|value| (value - values_mean).powi(2))
+        .sum::<f64>()
+        / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+
+/// How many standard deviations the value is away from the mean of the
+/// reference values. Not defined when the reference values don't vary.
+pub fn z_score(value: f64, reference_values: &[f64]) -> Option<f64> {
+    let deviation = std_dev(reference_values);
+    match deviation > 0.0 {
+        true => Some((value - mean(reference_values)) / deviation),
+        false => None,
+    }
+}
+
+/// Result of the two-sided Mann–Whitney U test.
+#[derive(Debug, Clone, Copy)]
+pub struct MannWhitney {
+    /// Positive when values of the second sample tend to be greater.
+    pub z: f64,
+    pub p_value: f64,
+}
+
+/// Checks whether values of one sample tend to be greater than values of the
+/// other one. Uses the normal approximation with the tie correction, which is
+/// accurate for samples of load tests (tens of values and more). Not defined
+/// for empty samples and samples consisting of one repeated value.
+pub fn mann_whitney_u(first: &[f64], second: &[f64]) -> Option<MannWhitney> {
+    if first.is_empty() || second.is_empty() {
+        return None;
+    }
+
+    let mut values: Vec<(f64, bool)> = first
+        .iter()
+        .map(|value| (*value, false))
+        .chain(second.iter().map(|value| (*value, true)))
+        .collect();
+    values.sort_by(|left, right| left.0.total_cmp(&right.0));
+
+    // Tied values share the average of their ranks
+    let mut second_rank_sum = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < values.len() {
+        let mut end = start;
+        while end + 1 < values.len() && values[end + 1].0 == values[start].0 {
+            end += 1;
+        }
+        let ties = (end - start + 1) as f64;
+        let average_rank = (start + end) as f64 / 2.0 + 1.0;
+        second_rank_sum += average_rank
+            * values[start..=end]
+                .iter()
+                .filter(|(_, is_second)| *is_second)
+                .count() as f64;
+        tie_correction += ties.powi(3) - ties;
+        start = end + 1;
+    }
+
+    let first_size = first.len() as f64;
+    let second_size = second.len() as f64;
+    let total_size = first_size + second_size;
+    let u = second_rank_sum - second_size * (second_size + 1.0) / 2.0;
+    let expected_u = first_size * second_size / 2.0;
+    let variance = first_size * second_size / 12.0
+        * ((total_size + 1.0) - tie_correction / (total_size * (total_size - 1.0)));
+    if variance <= 0.0 {
+        return None;
+    }
+
+    let difference = u - expected_u;
+    let continuity_correction = 0.5_f64.min(difference.abs());
+    let z = (difference - continuity_correction * difference.signum()) / variance.sqrt();
+    Some(MannWhitney {
+        z,
+        p_value: (2.0 * (1.0 - normal_cdf(z.abs()))).clamp(0.0, 1.0),
+    })
+}
+
+/// Cumulative distribution function of the standard normal distribution.
+pub fn normal_cdf(value: f64) -> f64 {
+    0.5 * erfc(-value / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function with the fractional error below 1.2e-7
+/// (Chebyshev approximation from Numerical Recipes).
+fn erfc(value: f64) -> f64 {
+    let z = value.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let result = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    match value >= 0.0 {
+        true => result,
+        false => 2.0 - result,
+    }
+}
+
+/// Bootstrap confidence intervals of the differences between percentiles of
+/// two samples (second minus first), one for each of the percentiles. Each
+/// sample is resampled with replacement the given number of times, and all
+/// percentiles are taken from the same resamples. Not defined for empty
+/// samples and a confidence outside of (0, 1).
+pub fn bootstrap_percentile_differences<R: Rng>(
+    first: &[f64],
+    second: &[f64],
+    percents: &[f64],
+    confidence: f64,
+    resamples: usize,
+    rng: &mut R,
+) -> Option<Vec<(f64, f64)>> {
+    if first.is_empty() || second.is_empty() || resamples == 0 {
+        return None;
+    }
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return None;
+    }
+
+    let mut first_buffer = vec![0.0; first.len()];
+    let mut second_buffer = vec![0.0; second.len()];
+    let mut differences = vec![Vec::with_capacity(resamples); percents.len()];
+    for _ in 0..resamples {
+        resample(first, &mut first_buffer, rng);
+        resample(second, &mut second_buffer, rng);
+        for (percent, percent_differences) in percents.iter().zip(&mut differences) {
+            percent_differences.push(
+                select_percentile(&mut second_buffer, *percent)
+                    - select_percentile(&mut first_buffer, *percent),
+            );
+        }
+    }
+
+    let tail = (1.0 - confidence) / 2.0 * 100.0;
+    Some(
+        differences
+            .into_iter()
+            .map(|mut percent_differences| {
+                sort_values(&mut percent_differences);
+                (
+                    percentile(&percent_differences, tail),
+                    percentile(&percent_differences, 100.0 - tail),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn resample<R: Rng>(values: &[f64], buffer: &mut [f64], rng: &mut R) {
+    for item in buffer.iter_mut() {
+        *item = values[rng.gen_range(0..values.len())];
+    }
+}
+
+/// Same as `percentile`, but for unsorted values. Runs in linear time, which
+/// matters for bootstrapping large samples. The values get reordered.
+fn select_percentile(values: &mut [f64], percent: f64) -> f64 {
+    let rank = percent / 100.0 * (values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let weight = rank - lower as f64;
+    let (_, lower_value, greater_values) =
+        values.select_nth_unstable_by(lower, |left, right| left.total_cmp(right));
+    let lower_value = *lower_value;
+    match weight > 0.0 && !greater_values.is_empty() {
+        true => {
+            let upper_value = greater_values
+                .iter()
+                .fold(f64::INFINITY, |min, value| min.min(*value));
+            lower_value * (1.0 - weight) + upper_value * weight
+        }
+        false => lower_value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} isn't within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn erfc_matches_reference_values() {
+        // Values of the C library `erfc`
+        for (value, expected) in [
+            (0.0, 1.0),
+            (0.5, 0.4795001221869535),
+            (1.0, 0.15729920705028513),
+            (2.0, 0.004677734981047265),
+            (-1.0, 1.842700792949715),
+        ] {
+            assert_close(erfc(value), expected, 1.2e-7 * expected);
+        }
+        assert_close(normal_cdf(1.96), 0.9750021048517795, 1e-7);
+    }
+
+    #[test]
+    fn mann_whitney_u_without_ties() {
+        // scipy.stats.mannwhitneyu(method="asymptotic") gives p = 0.0121857
+        let test = mann_whitney_u(&[1.0, 2.0, 3.0, 4.0, 5.0], &[6.0, 7.0, 8.0, 9.0, 10.0]).unwrap();
+        assert_close(test.z, 2.5067182457620487, 1e-9);
+        assert_close(test.p_value, 0.012185780355344818, 1e-6);
+
+        let reversed =
+            mann_whitney_u(&[6.0, 7.0, 8.0, 9.0, 10.0], &[1.0, 2.0, 3.0, 4.0, 5.0]).unwrap();
+        assert_close(reversed.z, -test.z, 1e-12);
+    }
+
+    #[test]
+    fn mann_whitney_u_with_ties() {
+        // U = 30 with the variance reduced by the ties of 2, 3 and 5
+        let test = mann_whitney_u(
+            &[1.0, 2.0, 2.0, 3.0, 5.0, 5.0],
+            &[2.0, 3.0, 3.0, 4.0, 5.0, 6.0, 7.0],
+        )
+        .unwrap();
+        assert_close(test.z, 1.23481030668945, 1e-9);
+        assert_close(test.p_value, 0.21690111061729794, 1e-6);
+    }
+
+    #[test]
+    fn mann_whitney_u_is_not_defined_for_degenerate_samples() {
+        assert!(mann_whitney_u(&[], &[1.0]).is_none());
+        assert!(mann_whitney_u(&[5.0, 5.0], &[5.0, 5.0, 5.0]).is_none());
+
+        let same = mann_whitney_u(&[10.0, 20.0, 30.0], &[10.0, 20.0, 30.0]).unwrap();
+        assert_eq!(same.z, 0.0);
+        assert_close(same.p_value, 1.0, 1.2e-7);
+    }
+
+    #[test]
+    fn percentiles_are_interpolated() {
+        let values = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&values, 50.0), 2.5);
+        assert_eq!(percentile(&values, 100.0), 4.0);
+
+        let mut unsorted = [4.0, 1.0, 3.0, 2.0];
+        assert_eq!(select_percentile(&mut unsorted, 50.0), 2.5);
+        assert_close(select_percentile(&mut unsorted, 95.0), 3.85, 1e-12);
+    }
+
+    #[test]
+    fn bootstrap_intervals_cover_every_percentile() {
+        let base: Vec<f64> = (0..200).map(f64::from).collect();
+        let target: Vec<f64> = base.iter().map(|value| value + 100.0).collect();
+        let mut rng = SmallRng::seed_from_u64(1);
+        let intervals =
+            bootstrap_percentile_differences(&base, &target, &[50.0, 95.0], 0.95, 500, &mut rng)
+                .unwrap();
+        assert_eq!(intervals.len(), 2);
+        for (low, high) in intervals {
+            assert!(low > 50.0 && high < 150.0, "[{}, {}]", low, high);
+        }
+
+        assert!(
+            bootstrap_percentile_differences(&base, &target, &[95.0], 1.5, 10, &mut rng).is_none()
+        );
+        assert!(
+            bootstrap_percentile_differences(&[], &target, &[95.0], 0.95, 10, &mut rng).is_none()
+        );
+    }
+
+    #[test]
+    fn z_score_needs_varying_reference_values() {
+        assert_eq!(z_score(5.0, &[1.0, 1.0]), None);
+        assert_close(z_score(4.0, &[1.0, 2.0, 3.0]).unwrap(), 2.0, 1e-12);
+    }
+}
@@ -0,0 +1,395 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::html::{format_rate, render_statuses};
+use crate::metrics::HttpReqDurationMetric;
+use crate::output::OutputFormat;
+use crate::summary::{endpoint_method, endpoint_name};
+use crate::table::TextTable;
+use crate::timeline::Intervals;
+
+/// k6 reports status 0 when no response was received at all: DNS failures,
+/// refused connections, timeouts and so on.
+pub const TRANSPORT_ERROR: &str = "transport error";
+
+/// Requests broken down by response status codes.
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusReport {
+    pub requests: u64,
+    /// Responses that didn't match the expected statuses of the test script.
+    pub unexpected_responses: u64,
+    pub unexpected_rate: f64,
+    pub classes: Vec<StatusCount>,
+    pub codes: Vec<StatusCount>,
+    pub endpoints: Vec<EndpointStatuses>,
+    /// Length of each time bucket in seconds.
+    pub interval: u64,
+    pub buckets: Vec<StatusBucket>,
+    pub top_failing_urls: Vec<FailingUrl>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusCount {
+    /// Status code, its class (e.g. "5xx") or the transport error.
+    pub status: String,
+    pub requests: u64,
+    pub share: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EndpointStatuses {
+    pub method: String,
+    pub name: String,
+    pub requests: u64,
+    pub unexpected_responses: u64,
+    pub unexpected_rate: f64,
+    pub codes: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusBucket {
+    pub start: DateTime<Utc>,
+    pub requests: u64,
+    pub unexpected_responses: u64,
+    pub classes: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FailingUrl {
+    pub method: String,
+    pub url: String,
+    pub requests: u64,
+    pub unexpected_responses: u64,
+    pub unexpected_rate: f64,
+    pub codes: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+struct Counter {
+    requests: u64,
+    unexpected_responses: u64,
+    codes: BTreeMap<String, u64>,
+}
+
+impl Counter {
+    fn add(&mut self, status: &str, expected_response: bool) {
+        self.requests += 1;
+        if !expected_response {
+            self.unexpected_responses += 1;
+        }
+        *self.codes.entry(status.to_string()).or_default() += 1;
+    }
+
+    fn unexpected_rate(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            requests => self.unexpected_responses as f64 / requests as f64,
+        }
+    }
+}
+
+/// Groups status codes by the first digit. Status 0 gets its own group.
+pub fn status_class(status: u16) -> String {
+    match status {
+        0 => String::from(TRANSPORT_ERROR),
+        100..=599 => format!("{}xx", status / 100),
+        _ => String::from("other"),
+    }
+}
+
+fn status_label(status: u16) -> String {
+    match status {
+        0 => String::from(TRANSPORT_ERROR),
+        _ => status.to_string(),
+    }
+}
+
+impl StatusReport {
+    pub async fn from_directory(directory: &Path, interval: u64, top: usize) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the requests")?;
+        let durations = dataset.load_metric::<HttpReqDurationMetric>().await?;
+        let intervals = Intervals::new(durations.iter().map(|record| record.time), interval);
+
+        let mut totals = Counter::default();
+        let mut classes: BTreeMap<String, u64> = BTreeMap::new();
+        let mut endpoints: BTreeMap<(String, String), Counter> = BTreeMap::new();
+        let mut urls: BTreeMap<(String, String), Counter> = BTreeMap::new();
+        let mut buckets: Vec<Counter> = vec![];
+        buckets.resize_with(
+            intervals
+                .as_ref()
+                .map(|intervals| intervals.count)
+                .unwrap_or(0),
+            Counter::default,
+        );
+        for record in &durations {
+            let code = status_label(record.status);
+            let class = status_class(record.status);
+            totals.add(&code, record.expected_response);
+            *classes.entry(class.clone()).or_default() += 1;
+            endpoints
+                .entry((endpoint_method(record), endpoint_name(record)))
+                .or_default()
+                .add(&code, record.expected_response);
+            urls.entry((endpoint_method(record), record.url.clone()))
+                .or_default()
+                .add(&code, record.expected_response);
+            if let Some(intervals) = &intervals {
+                buckets[intervals.index(record.time)].add(&class, record.expected_response);
+            }
+        }
+
+        let share = |requests: u64| match totals.requests {
+            0 => 0.0,
+            total => requests as f64 / total as f64,
+        };
+        let mut top_failing_urls: Vec<FailingUrl> = urls
+            .into_iter()
+            .filter(|(_, counter)| counter.unexpected_responses > 0)
+            .map(|((method, url), counter)| FailingUrl {
+                method,
+                url,
+                requests: counter.requests,
+                unexpected_responses: counter.unexpected_responses,
+                unexpected_rate: counter.unexpected_rate(),
+                codes: counter.codes,
+            })
+            .collect();
+        top_failing_urls.sort_by_key(|url| Reverse(url.unexpected_responses));
+        top_failing_urls.truncate(top);
+
+        Ok(StatusReport {
+            requests: totals.requests,
+            unexpected_responses: totals.unexpected_responses,
+            unexpected_rate: totals.unexpected_rate(),
+            classes: classes
+                .into_iter()
+                .map(|(status, requests)| StatusCount {
+                    status,
+                    requests,
+                    share: share(requests),
+                })
+                .collect(),
+            codes: totals
+                .codes
+                .iter()
+                .map(|(status, requests)| StatusCount {
+                    status: status.clone(),
+                    requests: *requests,
+                    share: share(*requests),
+                })
+                .collect(),
+            endpoints: endpoints
+                .into_iter()
+                .map(|((method, name), counter)| EndpointStatuses {
+                    method,
+                    name,
+                    requests: counter.requests,
+                    unexpected_responses: counter.unexpected_responses,
+                    unexpected_rate: counter.unexpected_rate(),
+                    codes: counter.codes,
+                })
+                .collect(),
+            interval,
+            buckets: buckets
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, counter)| {
+                    let start = intervals.as_ref()?.start(index);
+                    Some(StatusBucket {
+                        start,
+                        requests: counter.requests,
+                        unexpected_responses: counter.unexpected_responses,
+                        classes: counter.codes,
+                    })
+                })
+                .collect(),
+            top_failing_urls,
+        })
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_statuses(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// Names of all status classes seen in the report.
+    pub fn class_names(&self) -> Vec<String> {
+        self.classes
+            .iter()
+            .map(|class| class.status.clone())
+            .collect()
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut sections: Vec<(String, TextTable)> = vec![];
+
+        for (title, counts) in [
+            ("Status classes", &self.classes),
+            ("Status codes", &self.codes),
+        ] {
+            let mut table = TextTable::new(&["Status", "Requests", "Share"]);
+            for count in counts {
+                table.add_row(vec![
+                    count.status.clone(),
+                    count.requests.to_string(),
+                    format_rate(count.share),
+                ]);
+            }
+            sections.push((title.to_string(), table));
+        }
+
+        let codes: Vec<String> = self.codes.iter().map(|code| code.status.clone()).collect();
+        let mut headers = vec!["Endpoint", "Requests", "Unexpected"];
+        headers.extend(codes.iter().map(String::as_str));
+        let mut table = TextTable::new(&headers);
+        for endpoint in &self.endpoints {
+            let mut row = vec![
+                format!("{} {}", endpoint.method, endpoint.name),
+                endpoint.requests.to_string(),
+                format_rate(endpoint.unexpected_rate),
+            ];
+            row.extend(codes.iter().map(|code| count_of(&endpoint.codes, code)));
+            table.add_row(row);
+        }
+        sections.push((String::from("Endpoints"), table));
+
+        let classes = self.class_names();
+        let mut headers = vec!["Time", "Requests", "Unexpected"];
+        headers.extend(classes.iter().map(String::as_str));
+        let mut table = TextTable::new(&headers);
+        for bucket in &self.buckets {
+            let mut row = vec![
+                bucket.start.format("%Y-%m-%d %H:%M:%S").to_string(),
+                bucket.requests.to_string(),
+                bucket.unexpected_responses.to_string(),
+            ];
+            row.extend(classes.iter().map(|class| count_of(&bucket.classes, class)));
+            table.add_row(row);
+        }
+        sections.push((format!("Timeline ({} s intervals)", self.interval), table));
+
+        let mut table = TextTable::new(&[
+            "Method",
+            "URL",
+            "Requests",
+            "Unexpected",
+            "Rate",
+            "Statuses",
+        ]);
+        for url in &self.top_failing_urls {
+            table.add_row(vec![
+                url.method.clone(),
+                url.url.clone(),
+                url.requests.to_string(),
+                url.unexpected_responses.to_string(),
+                format_rate(url.unexpected_rate),
+                format_codes(&url.codes),
+            ]);
+        }
+        sections.push((String::from("Top failing URLs"), table));
+
+        let mut result = format!(
+            "{} requests, {} unexpected responses ({})\n\n",
+            self.requests,
+            self.unexpected_responses,
+            format_rate(self.unexpected_rate)
+        );
+        for (title, table) in sections {
+            match format {
+                OutputFormat::Markdown => {
+                    result.push_str(&format!("## {}\n\n{}\n", title, table.to_markdown()))
+                }
+                _ => result.push_str(&format!("{}\n{}\n", title, table.to_console())),
+            }
+        }
+        result
+    }
+}
+
+pub fn count_of(counts: &BTreeMap<String, u64>, key: &str) -> String {
+    counts.get(key).copied().unwrap_or(0).to_string()
+}
+
+/// Formats the counts as "500: 3, 404: 1", most frequent statuses first.
+pub fn format_codes(codes: &BTreeMap<String, u64>) -> String {
+    let mut codes: Vec<(&String, &u64)> = codes.iter().collect();
+    codes.sort_by_key(|(_, count)| Reverse(**count));
+    codes
+        .iter()
+        .map(|(code, count)| format!("{}: {}", code, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_directory;
+
+    const HEADER: &str =
+        "time,expected_response,group,method,name,proto,scenario,status,tls_version,url,value\n";
+
+    #[test]
+    fn status_zero_is_a_transport_error() {
+        assert_eq!(status_class(0), TRANSPORT_ERROR);
+        assert_eq!(status_label(0), TRANSPORT_ERROR);
+        assert_eq!(status_class(204), "2xx");
+        assert_eq!(status_class(503), "5xx");
+        assert_eq!(status_label(503), "503");
+        assert_eq!(status_class(999), "other");
+    }
+
+    #[tokio::test]
+    async fn requests_are_counted_by_their_statuses() {
+        let directory = test_directory("statuses", "counts");
+        let mut durations = String::from(HEADER);
+        for (time, expected_response, url, status) in [
+            ("2024-05-01T12:00:01Z", true, "/a", 200),
+            ("2024-05-01T12:00:02Z", true, "/a", 200),
+            ("2024-05-01T12:00:03Z", false, "/a", 0),
+            ("2024-05-01T12:00:12Z", false, "/b", 503),
+            ("2024-05-01T12:00:13Z", false, "/b", 0),
+        ] {
+            durations.push_str(&format!(
+                "{0},{1},,GET,{2},HTTP/1.1,default,{3},,{2},100\n",
+                time, expected_response, url, status
+            ));
+        }
+        std::fs::write(directory.join("http_req_duration.csv"), durations).unwrap();
+
+        let report = StatusReport::from_directory(&directory, 10, 1)
+            .await
+            .unwrap();
+        assert_eq!((report.requests, report.unexpected_responses), (5, 3));
+        let classes: Vec<(&str, u64)> = report
+            .classes
+            .iter()
+            .map(|class| (class.status.as_str(), class.requests))
+            .collect();
+        assert_eq!(classes, [("2xx", 2), ("5xx", 1), (TRANSPORT_ERROR, 2)]);
+        assert_eq!(report.codes[0].status, "200");
+        assert_eq!(report.codes[0].share, 0.4);
+
+        assert_eq!(report.buckets.len(), 2);
+        assert_eq!(report.buckets[0].classes[TRANSPORT_ERROR], 1);
+        assert_eq!(report.buckets[1].unexpected_responses, 2);
+
+        // Only the URL with the most unexpected responses is kept
+        assert_eq!(report.top_failing_urls.len(), 1);
+        assert_eq!(report.top_failing_urls[0].url, "/b");
+        assert_eq!(report.top_failing_urls[0].codes[TRANSPORT_ERROR], 1);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read, write};
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::metrics::{
+    BrowserWebVitalClsMetric, BrowserWebVitalFcpMetric, BrowserWebVitalFidMetric,
+    BrowserWebVitalInpMetric, BrowserWebVitalLcpMetric, BrowserWebVitalTtfbMetric, ChecksMetric,
+    DroppedIterationsMetric, GrpcReqDurationMetric, HttpReqDurationMetric, HttpReqsMetric,
+    IterationMetric, K6Metric, VusMetric, WsConnectingMetric, WsMsgsReceivedMetric,
+    WsMsgsSentMetric, WsSessionDurationMetric, WsSessionsMetric,
+};
+use crate::slo::SloReport;
+use crate::statistics::{mean, percentile, sort_values};
+
+pub const SUMMARY_FILENAME: &str = "summary.json";
+
+/// Aggregated results of a single test run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Summary {
+    pub generated_at: DateTime<Utc>,
+    pub first_timestamp: Option<DateTime<Utc>>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub error_rate: f64,
+    #[serde(default)]
+    pub requests_per_second: f64,
+    pub http_req_duration: DurationStats,
+    pub iterations: u64,
+    #[serde(default)]
+    pub dropped_iterations: u64,
+    pub max_vus: f64,
+    pub checks_passed: u64,
+    pub checks_failed: u64,
+    pub endpoints: Vec<EndpointSummary>,
+    #[serde(default)]
+    pub scenarios: Vec<ScenarioSummary>,
+    /// Present when the export was given an Apdex and SLO configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slo: Option<SloReport>,
+    /// Durations of gRPC requests in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_req_duration: Option<DurationStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websockets: Option<WebSocketSummary>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub web_vitals: Vec<WebVitalSummary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EndpointSummary {
+    pub method: String,
+    pub name: String,
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub error_rate: f64,
+    #[serde(default)]
+    pub requests_per_second: f64,
+    pub http_req_duration: DurationStats,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScenarioSummary {
+    pub name: String,
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub error_rate: f64,
+    pub requests_per_second: f64,
+    pub http_req_duration: DurationStats,
+    pub checks_passed: u64,
+    pub checks_failed: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebSocketSummary {
+    pub sessions: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub ws_connecting: DurationStats,
+    pub ws_session_duration: DurationStats,
+}
+
+/// Web vital measured by the browser module. The values are in milliseconds,
+/// except for the unitless Cumulative Layout Shift.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebVitalSummary {
+    pub name: String,
+    pub samples: u64,
+    pub values: DurationStats,
+    /// Share of the samples which were rated as `poor` by the browser.
+    pub poor_rate: f64,
+}
+
+/// Request durations in milliseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DurationStats {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl DurationStats {
+    pub fn from_values(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return DurationStats::default();
+        }
+
+        sort_values(&mut values);
+        DurationStats {
+            min: values[0],
+            mean: mean(&values),
+            p50: percentile(&values, 50.0),
+            p90: percentile(&values, 90.0),
+            p95: percentile(&values, 95.0),
+            p99: percentile(&values, 99.0),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+/// Number of all and failed requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct RequestCount {
+    requests: u64,
+    failed: u64,
+}
+
+impl RequestCount {
+    /// Counts the requests by their durations, one data point per request.
+    fn of(records: &[HttpReqDurationMetric]) -> Self {
+        RequestCount {
+            requests: records.len() as u64,
+            failed: records
+                .iter()
+                .filter(|record| !record.expected_response)
+                .count() as u64,
+        }
+    }
+
+    fn add(&mut self, record: &HttpReqsMetric) {
+        let requests = record.value.max(0.0).round() as u64;
+        self.requests += requests;
+        if !record.expected_response {
+            self.failed += requests;
+        }
+    }
+
+    fn error_rate(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            requests => self.failed as f64 / requests as f64,
+        }
+    }
+}
+
+/// Request counts taken from the `http_reqs` counter, for sources which keep
+/// aggregates, where a duration data point is a sample of many requests.
+#[derive(Debug, Default)]
+struct RequestCounts {
+    total: RequestCount,
+    endpoints: HashMap<(String, String), RequestCount>,
+    scenarios: HashMap<String, RequestCount>,
+}
+
+impl RequestCounts {
+    fn new(records: &[HttpReqsMetric]) -> Self {
+        let mut counts = RequestCounts::default();
+        for record in records {
+            counts.total.add(record);
+            let endpoint = (
+                record.method.clone().unwrap_or_default(),
+                record.name.clone().unwrap_or_else(|| record.url.clone()),
+            );
+            counts.endpoints.entry(endpoint).or_default().add(record);
+            let scenario = record.scenario.clone().unwrap_or_default();
+            counts.scenarios.entry(scenario).or_default().add(record);
+        }
+        counts
+    }
+
+    fn endpoint(&self, method: &str, name: &str) -> RequestCount {
+        let key = (method.to_string(), name.to_string());
+        self.endpoints.get(&key).copied().unwrap_or_default()
+    }
+
+    fn scenario(&self, name: &str) -> RequestCount {
+        self.scenarios.get(name).copied().unwrap_or_default()
+    }
+}
+
+impl Summary {
+    /// Builds the summary from the metrics exported into the directory. For
+    /// runs exported from Prometheus the requests are counted by `http_reqs`,
+    /// and the checks aren't counted at all, since only their pass rate is
+    /// kept.
+    pub async fn from_directory(directory: &Path) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        let is_aggregated = dataset
+            .manifest()
+            .is_some_and(|manifest| manifest.source.is_aggregated());
+        let request_counts = match is_aggregated {
+            true => Some(RequestCounts::new(
+                &dataset.load_metric::<HttpReqsMetric>().await?,
+            )),
+            false => None,
+        };
+        let durations = dataset.load_metric::<HttpReqDurationMetric>().await?;
+        let checks = match is_aggregated {
+            true => vec![],
+            false => dataset.load_metric::<ChecksMetric>().await?,
+        };
+        let iterations = dataset.load_metric::<IterationMetric>().await?;
+        let vus = dataset.load_metric::<VusMetric>().await?;
+        let dropped_iterations = dataset.load_metric::<DroppedIterationsMetric>().await?;
+        let grpc_durations = dataset.load_metric::<GrpcReqDurationMetric>().await?;
+
+        let checks_passed = count_passed(&checks);
+        let max_vus = vus.iter().map(|point| point.value).fold(0.0, f64::max);
+        let first_timestamp = durations.iter().map(|record| record.time).min();
+        let last_timestamp = durations.iter().map(|record| record.time).max();
+        let duration_seconds = match (first_timestamp, last_timestamp) {
+            (Some(first), Some(last)) => {
+                ((last - first).num_milliseconds() as f64 / 1000.0).max(1.0)
+            }
+            _ => 1.0,
+        };
+
+        let count = match &request_counts {
+            Some(counts) => counts.total,
+            None => RequestCount::of(&durations),
+        };
+        Ok(Summary {
+            generated_at: Utc::now(),
+            first_timestamp,
+            last_timestamp,
+            requests: count.requests,
+            failed_requests: count.failed,
+            error_rate: count.error_rate(),
+            requests_per_second: count.requests as f64 / duration_seconds,
+            http_req_duration: DurationStats::from_values(
+                durations.iter().map(|record| record.value).collect(),
+            ),
+            iterations: iterations.iter().map(|record| record.value).sum::<f64>() as u64,
+            dropped_iterations: dropped_iterations
+                .iter()
+                .map(|record| record.value)
+                .sum::<f64>() as u64,
+            max_vus,
+            checks_passed,
+            checks_failed: checks.len() as u64 - checks_passed,
+            scenarios: summarize_scenarios(
+                &durations,
+                &checks,
+                request_counts.as_ref(),
+                duration_seconds,
+            ),
+            endpoints: summarize_endpoints(durations, request_counts.as_ref(), duration_seconds),
+            slo: None,
+            grpc_req_duration: match grpc_durations.is_empty() {
+                true => None,
+                false => Some(DurationStats::from_values(
+                    grpc_durations.iter().map(|record| record.value).collect(),
+                )),
+            },
+            websockets: summarize_websockets(&dataset).await?,
+            web_vitals: summarize_web_vitals(&dataset).await?,
+        })
+    }
+
+    /// Loads the summary written during the export or builds it from the
+    /// exported metrics when there is none.
+    pub async fn load(directory: &Path) -> Result<Self> {
+        let path = directory.join(SUMMARY_FILENAME);
+        match path.exists() {
+            true => Ok(serde_json::from_slice(&read(path).await?)?),
+            false => Summary::from_directory(directory).await,
+        }
+    }
+
+    pub async fn save(&self, directory: &Path) -> Result<()> {
+        let content = serde_json::to_vec_pretty(self)?;
+        write(directory.join(SUMMARY_FILENAME), content).await?;
+        Ok(())
+    }
+}
+
+/// Groups requests by the method and the `name` tag, which is the URL unless
+/// it was overridden in the test script.
+fn summarize_endpoints(
+    durations: Vec<HttpReqDurationMetric>,
+    request_counts: Option<&RequestCounts>,
+    duration_seconds: f64,
+) -> Vec<EndpointSummary> {
+    let mut endpoints: BTreeMap<(String, String), Vec<HttpReqDurationMetric>> = BTreeMap::new();
+    for record in durations {
+        let key = (endpoint_method(&record), endpoint_name(&record));
+        endpoints.entry(key).or_default().push(record);
+    }
+
+    endpoints
+        .into_iter()
+        .map(|((method, name), records)| {
+            let count = match request_counts {
+                Some(counts) => counts.endpoint(&method, &name),
+                None => RequestCount::of(&records),
+            };
+            EndpointSummary {
+                method,
+                name,
+                requests: count.requests,
+                failed_requests: count.failed,
+                error_rate: count.error_rate(),
+                requests_per_second: count.requests as f64 / duration_seconds,
+                http_req_duration: DurationStats::from_values(
+                    records.iter().map(|record| record.value).collect(),
+                ),
+            }
+        })
+        .collect()
+}
+
+fn summarize_scenarios(
+    durations: &[HttpReqDurationMetric],
+    checks: &[ChecksMetric],
+    request_counts: Option<&RequestCounts>,
+    duration_seconds: f64,
+) -> Vec<ScenarioSummary> {
+    let mut scenarios: BTreeMap<String, (Vec<HttpReqDurationMetric>, Vec<ChecksMetric>)> =
+        BTreeMap::new();
+    for record in durations {
+        let key = record.scenario.clone().unwrap_or_default();
+        scenarios.entry(key).or_default().0.push(record.clone());
+    }
+    for record in checks {
+        let key = record.scenario.clone().unwrap_or_default();
+        scenarios.entry(key).or_default().1.push(record.clone());
+    }
+
+    scenarios
+        .into_iter()
+        .map(|(name, (records, checks))| {
+            let checks_passed = count_passed(&checks);
+            let count = match request_counts {
+                Some(counts) => counts.scenario(&name),
+                None => RequestCount::of(&records),
+            };
+            ScenarioSummary {
+                name,
+                requests: count.requests,
+                failed_requests: count.failed,
+                error_rate: count.error_rate(),
+                requests_per_second: count.requests as f64 / duration_seconds,
+                http_req_duration: DurationStats::from_values(
+                    records.iter().map(|record| record.value).collect(),
+                ),
+                checks_passed,
+                checks_failed: checks.len() as u64 - checks_passed,
+            }
+        })
+        .collect()
+}
+
+async fn summarize_websockets(dataset: &Dataset) -> Result<Option<WebSocketSummary>> {
+    let sessions = dataset.load_metric::<WsSessionsMetric>().await?;
+    if sessions.is_empty() {
+        return Ok(None);
+    }
+
+    let messages_sent = dataset.load_metric::<WsMsgsSentMetric>().await?;
+    let messages_received = dataset.load_metric::<WsMsgsReceivedMetric>().await?;
+    let connecting = dataset.load_metric::<WsConnectingMetric>().await?;
+    let session_durations = dataset.load_metric::<WsSessionDurationMetric>().await?;
+    Ok(Some(WebSocketSummary {
+        sessions: sessions.iter().map(|record| record.value).sum::<f64>() as u64,
+        messages_sent: messages_sent.iter().map(|record| record.value).sum::<f64>() as u64,
+        messages_received: messages_received
+            .iter()
+            .map(|record| record.value)
+            .sum::<f64>() as u64,
+        ws_connecting: DurationStats::from_values(
+            connecting.iter().map(|record| record.value).collect(),
+        ),
+        ws_session_duration: DurationStats::from_values(
+            session_durations
+                .iter()
+                .map(|record| record.value)
+                .collect(),
+        ),
+    }))
+}
+
+async fn summarize_web_vitals(dataset: &Dataset) -> Result<Vec<WebVitalSummary>> {
+    let web_vitals = vec![
+        load_web_vital::<BrowserWebVitalClsMetric>(dataset, |record| (record.value, record.rating))
+            .await?,
+        load_web_vital::<BrowserWebVitalFcpMetric>(dataset, |record| (record.value, record.rating))
+            .await?,
+        load_web_vital::<BrowserWebVitalFidMetric>(dataset, |record| (record.value, record.rating))
+            .await?,
+        load_web_vital::<BrowserWebVitalInpMetric>(dataset, |record| (record.value, record.rating))
+            .await?,
+        load_web_vital::<BrowserWebVitalLcpMetric>(dataset, |record| (record.value, record.rating))
+            .await?,
+        load_web_vital::<BrowserWebVitalTtfbMetric>(dataset, |record| {
+            (record.value, record.rating)
+        })
+        .await?,
+    ];
+    Ok(web_vitals.into_iter().flatten().collect())
+}
+
+/// Summarizes the web vital from its values and ratings. Returns nothing
+/// when the metric wasn't exported.
+async fn load_web_vital<T>(
+    dataset: &Dataset,
+    value_and_rating: fn(T) -> (f64, Option<String>),
+) -> Result<Option<WebVitalSummary>>
+where
+    T: K6Metric + DeserializeOwned + 'static,
+{
+    let records = dataset.load_metric::<T>().await?;
+    if records.is_empty() {
+        return Ok(None);
+    }
+
+    let samples = records.len() as u64;
+    let (values, ratings): (Vec<f64>, Vec<Option<String>>) =
+        records.into_iter().map(value_and_rating).unzip();
+    let poor = ratings
+        .iter()
+        .filter(|rating| rating.as_deref() == Some("poor"))
+        .count();
+    Ok(Some(WebVitalSummary {
+        name: T::metric_table_name()
+            .trim_start_matches("browser_web_vital_")
+            .to_uppercase(),
+        samples,
+        values: DurationStats::from_values(values),
+        poor_rate: poor as f64 / samples as f64,
+    }))
+}
+
+fn count_passed(checks: &[ChecksMetric]) -> u64 {
+    checks.iter().filter(|check| check.value > 0.0).count() as u64
+}
+
+pub fn endpoint_method(record: &HttpReqDurationMetric) -> String {
+    record.method.clone().unwrap_or_default()
+}
+
+pub fn endpoint_name(record: &HttpReqDurationMetric) -> String {
+    record.name.clone().unwrap_or_else(|| record.url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::RunMetadata;
+    use crate::manifest::{Manifest, MetricEntry, Source, TimeWindow};
+    use crate::test_support::test_directory;
+
+    const HEADER: &str =
+        "time,expected_response,group,method,name,proto,scenario,status,tls_version,url,value\n";
+
+    fn write_metric(directory: &Path, measurement: &str, rows: &[(&str, bool, &str, f64)]) {
+        let mut content = String::from(HEADER);
+        for (time, expected_response, url, value) in rows {
+            content.push_str(&format!(
+                "{0},{1},,GET,{2},HTTP/1.1,default,200,,{2},{3}\n",
+                time, expected_response, url, value
+            ));
+        }
+        std::fs::write(directory.join(format!("{}.csv", measurement)), content).unwrap();
+    }
+
+    async fn save_manifest(directory: &Path, kind: &str) {
+        let mut manifest = Manifest::new(
+            RunMetadata::new(Utc::now(), &None, &None),
+            Source {
+                kind: kind.to_string(),
+                url: String::from("http://localhost"),
+                database: None,
+                retention_policy: None,
+            },
+            TimeWindow {
+                from: None,
+                to: Utc::now(),
+                trimmed: None,
+            },
+            vec![],
+        );
+        manifest.metrics = ["checks", "http_req_duration", "http_reqs"]
+            .iter()
+            .map(|measurement| MetricEntry {
+                measurement: measurement.to_string(),
+                file: format!("{}.csv", measurement),
+                query: String::new(),
+                rows: 3,
+                first_timestamp: None,
+                last_timestamp: None,
+                sha256: String::new(),
+                extra_files: vec![],
+            })
+            .collect();
+        manifest.save(directory).await.unwrap();
+    }
+
+    fn write_run(directory: &Path) {
+        write_metric(
+            directory,
+            "http_req_duration",
+            &[
+                ("2024-05-01T12:00:00Z", true, "/a", 100.0),
+                ("2024-05-01T12:00:05Z", false, "/a", 300.0),
+                ("2024-05-01T12:00:10Z", true, "/b", 200.0),
+            ],
+        );
+        write_metric(
+            directory,
+            "http_reqs",
+            &[
+                ("2024-05-01T12:00:05Z", true, "/a", 40.0),
+                ("2024-05-01T12:00:05Z", false, "/a", 10.0),
+                ("2024-05-01T12:00:10Z", true, "/b", 30.0),
+            ],
+        );
+        // Prometheus keeps the pass rate of the checks instead of the results
+        std::fs::write(
+            directory.join("checks.csv"),
+            "time,check,group,method,scenario,value\n\
+             2024-05-01T12:00:05Z,status is 200,,GET,default,1\n\
+             2024-05-01T12:00:10Z,status is 200,,GET,default,0.25\n",
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn counts_requests_by_their_durations() {
+        let directory = test_directory("summary", "raw");
+        write_run(&directory);
+        save_manifest(&directory, "influxdb").await;
+
+        let summary = Summary::from_directory(&directory).await.unwrap();
+        assert_eq!(summary.requests, 3);
+        assert_eq!(summary.failed_requests, 1);
+        assert_eq!(summary.requests_per_second, 0.3);
+        assert_eq!(summary.endpoints[0].requests, 2);
+        assert_eq!(summary.endpoints[0].error_rate, 0.5);
+        assert_eq!((summary.checks_passed, summary.checks_failed), (2, 0));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn counts_aggregated_requests_by_http_reqs() {
+        let directory = test_directory("summary", "aggregated");
+        write_run(&directory);
+        save_manifest(&directory, "prometheus").await;
+
+        let summary = Summary::from_directory(&directory).await.unwrap();
+        assert_eq!(summary.requests, 80);
+        assert_eq!(summary.failed_requests, 10);
+        assert_eq!(summary.error_rate, 0.125);
+        assert_eq!(summary.requests_per_second, 8.0);
+        assert_eq!(summary.http_req_duration.max, 300.0);
+
+        let endpoints: Vec<(&str, u64, u64)> = summary
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                (
+                    endpoint.name.as_str(),
+                    endpoint.requests,
+                    endpoint.failed_requests,
+                )
+            })
+            .collect();
+        assert_eq!(endpoints, [("/a", 50, 10), ("/b", 30, 0)]);
+        assert_eq!(summary.scenarios[0].requests, 80);
+        assert_eq!((summary.checks_passed, summary.checks_failed), (0, 0));
+        assert_eq!(summary.scenarios[0].checks_passed, 0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+/// Plain table rendered for terminals and Markdown documents.
+pub struct TextTable {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl TextTable {
+    pub fn new(headers: &[&str]) -> Self {
+        TextTable {
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            rows: vec![],
+        }
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Renders the table with columns aligned by the widest value.
+    pub fn to_console(&self) -> String {
+        let widths = self.column_widths();
+        let mut result = format_line(&self.headers, &widths, "  ");
+        let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        result.push_str(&format_line(&separator, &widths, "  "));
+        for row in &self.rows {
+            result.push_str(&format_line(row, &widths, "  "));
+        }
+        result
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let escape = |value: &String| value.replace('|', "\\|");
+        let mut result = format!(
+            "| {} |\n",
+            self.headers
+                .iter()
+                .map(escape)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        );
+        result.push_str(&format!("|{}\n", " --- |".repeat(self.headers.len())));
+        for row in &self.rows {
+            result.push_str(&format!(
+                "| {} |\n",
+                row.iter().map(escape).collect::<Vec<_>>().join(" | ")
+            ));
+        }
+        result
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .headers
+            .iter()
+            .map(|header| header.chars().count())
+            .collect();
+        for row in &self.rows {
+            for (index, value) in row.iter().enumerate() {
+                if index < widths.len() {
+                    widths[index] = widths[index].max(value.chars().count());
+                }
+            }
+        }
+        widths
+    }
+}
+
+fn format_line(values: &[String], widths: &[usize], separator: &str) -> String {
+    let cells: Vec<String> = widths
+        .iter()
+        .enumerate()
+        .map(|(index, width)| {
+            let value = values.get(index).map(String::as_str).unwrap_or("");
+            format!("{:<width$}", value, width = width)
+        })
+        .collect();
+    format!("{}\n", cells.join(separator).trim_end())
+}
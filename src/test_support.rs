@@ -0,0 +1,16 @@
+//! Helpers shared by the unit tests of several modules.
+
+use std::path::PathBuf;
+
+/// Creates a directory for the test under the temporary directory. The
+/// module and test names keep apart the tests running at the same time.
+pub fn test_directory(module: &str, name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!(
+        "k6-reports-{}-{}-{}",
+        module,
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
@@ -0,0 +1,307 @@
+use std::path::Path;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::html::{format_duration, format_rate, render_timeline};
+use crate::metrics::{DataReceivedMetric, DataSentMetric, HttpReqDurationMetric, VusMetric};
+use crate::output::OutputFormat;
+use crate::statistics::{percentile, sort_values};
+use crate::table::TextTable;
+
+/// Metrics of a run split into fixed intervals. The data points are bucketed
+/// by the tool itself, so it doesn't depend on aggregations of the data
+/// source.
+#[derive(Serialize, Debug, Clone)]
+pub struct Timeline {
+    /// Length of each bucket in seconds.
+    pub interval: u64,
+    pub buckets: Vec<Bucket>,
+}
+
+/// Fixed time intervals covering the given timestamps. The intervals are
+/// aligned to multiples of their length, so they don't depend on the first
+/// data point.
+pub struct Intervals {
+    start: i64,
+    length: i64,
+    pub count: usize,
+}
+
+impl Intervals {
+    /// Returns nothing when there are no timestamps.
+    pub fn new(
+        timestamps: impl Iterator<Item = DateTime<Utc>> + Clone,
+        seconds: u64,
+    ) -> Option<Self> {
+        let first = timestamps.clone().min()?;
+        let last = timestamps.max()?;
+        let length = seconds.max(1) as i64 * 1000;
+        let mut intervals = Intervals {
+            start: first.timestamp_millis() - first.timestamp_millis().rem_euclid(length),
+            length,
+            count: 0,
+        };
+        intervals.count = intervals.index(last) + 1;
+        Some(intervals)
+    }
+
+    pub fn index(&self, time: DateTime<Utc>) -> usize {
+        ((time.timestamp_millis() - self.start) / self.length) as usize
+    }
+
+    pub fn start(&self, index: usize) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.start).unwrap()
+            + Duration::milliseconds(index as i64 * self.length)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub requests: u64,
+    pub requests_per_second: f64,
+    pub failed_requests: u64,
+    pub error_rate: f64,
+    /// Bytes per second.
+    pub data_sent: f64,
+    /// Bytes per second.
+    pub data_received: f64,
+    /// Request durations in milliseconds. Not defined without requests.
+    pub p50_duration: Option<f64>,
+    pub p95_duration: Option<f64>,
+    pub p99_duration: Option<f64>,
+    /// The highest number of active VUs during the interval.
+    pub vus: Option<f64>,
+}
+
+impl Timeline {
+    pub async fn from_directory(directory: &Path, interval: u64) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the requests")?;
+        let durations = dataset.load_metric::<HttpReqDurationMetric>().await?;
+        let data_sent = dataset.load_metric::<DataSentMetric>().await?;
+        let data_received = dataset.load_metric::<DataReceivedMetric>().await?;
+        let vus = dataset.load_metric::<VusMetric>().await?;
+
+        let interval = interval.max(1);
+        let timestamps = durations
+            .iter()
+            .map(|record| record.time)
+            .chain(data_sent.iter().map(|record| record.time))
+            .chain(data_received.iter().map(|record| record.time))
+            .chain(vus.iter().map(|record| record.time));
+        let intervals = match Intervals::new(timestamps, interval) {
+            Some(intervals) => intervals,
+            None => {
+                return Ok(Timeline {
+                    interval,
+                    buckets: vec![],
+                })
+            }
+        };
+        let bucket_index = |time: DateTime<Utc>| intervals.index(time);
+
+        let mut bucket_durations: Vec<Vec<f64>> = vec![vec![]; intervals.count];
+        let mut buckets: Vec<Bucket> = (0..intervals.count)
+            .map(|index| Bucket {
+                start: intervals.start(index),
+                ..Bucket::default()
+            })
+            .collect();
+        for record in &durations {
+            let index = bucket_index(record.time);
+            buckets[index].requests += 1;
+            if !record.expected_response {
+                buckets[index].failed_requests += 1;
+            }
+            bucket_durations[index].push(record.value);
+        }
+        for record in &data_sent {
+            buckets[bucket_index(record.time)].data_sent += record.value;
+        }
+        for record in &data_received {
+            buckets[bucket_index(record.time)].data_received += record.value;
+        }
+        for record in &vus {
+            let bucket = &mut buckets[bucket_index(record.time)];
+            bucket.vus = Some(bucket.vus.unwrap_or(0.0).max(record.value));
+        }
+
+        for (bucket, mut values) in buckets.iter_mut().zip(bucket_durations) {
+            bucket.requests_per_second = bucket.requests as f64 / interval as f64;
+            bucket.data_sent /= interval as f64;
+            bucket.data_received /= interval as f64;
+            if bucket.requests > 0 {
+                bucket.error_rate = bucket.failed_requests as f64 / bucket.requests as f64;
+                sort_values(&mut values);
+                bucket.p50_duration = Some(percentile(&values, 50.0));
+                bucket.p95_duration = Some(percentile(&values, 95.0));
+                bucket.p99_duration = Some(percentile(&values, 99.0));
+            }
+        }
+
+        Ok(Timeline { interval, buckets })
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_timeline(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut table = TextTable::new(&[
+            "Time",
+            "Requests/s",
+            "Error rate",
+            "p50 (ms)",
+            "p95 (ms)",
+            "p99 (ms)",
+            "Sent (KB/s)",
+            "Received (KB/s)",
+            "VUs",
+        ]);
+        let optional = |value: Option<f64>, format: fn(f64) -> String| {
+            value.map(format).unwrap_or_else(|| String::from("-"))
+        };
+        for bucket in &self.buckets {
+            table.add_row(vec![
+                bucket.start.format("%Y-%m-%d %H:%M:%S").to_string(),
+                format!("{:.2}", bucket.requests_per_second),
+                format_rate(bucket.error_rate),
+                optional(bucket.p50_duration, format_duration),
+                optional(bucket.p95_duration, format_duration),
+                optional(bucket.p99_duration, format_duration),
+                format!("{:.2}", bucket.data_sent / 1024.0),
+                format!("{:.2}", bucket.data_received / 1024.0),
+                optional(bucket.vus, |value| format!("{:.0}", value)),
+            ]);
+        }
+
+        let title = format!("Timeline ({} s intervals)", self.interval);
+        match format {
+            OutputFormat::Markdown => format!("## {}\n\n{}", title, table.to_markdown()),
+            _ => format!("{}\n{}", title, table.to_console()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::layout::RunMetadata;
+    use crate::manifest::{Manifest, Source, TimeWindow};
+    use crate::test_support::test_directory;
+
+    const HEADER: &str =
+        "time,expected_response,group,method,name,proto,scenario,status,tls_version,url,value\n";
+
+    fn write_run(directory: &Path) {
+        let mut durations = String::from(HEADER);
+        for (time, expected_response, value) in [
+            ("2024-05-01T12:00:01Z", true, 100.0),
+            ("2024-05-01T12:00:04Z", false, 300.0),
+            ("2024-05-01T12:00:12Z", true, 200.0),
+        ] {
+            durations.push_str(&format!(
+                "{0},{1},,GET,/a,HTTP/1.1,default,200,,/a,{2}\n",
+                time, expected_response, value
+            ));
+        }
+        std::fs::write(directory.join("http_req_duration.csv"), durations).unwrap();
+        std::fs::write(
+            directory.join("data_sent.csv"),
+            "time,group,scenario,url,value\n\
+             2024-05-01T12:00:01Z,,default,/a,2048\n\
+             2024-05-01T12:00:04Z,,default,/a,3072\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("vus.csv"),
+            "time,value\n\
+             2024-05-01T12:00:00Z,5\n\
+             2024-05-01T12:00:05Z,10\n\
+             2024-05-01T12:00:25Z,0\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn intervals_are_aligned_to_their_length() {
+        let timestamps = [
+            Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 23).unwrap(),
+            Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 7).unwrap(),
+        ];
+        let intervals = Intervals::new(timestamps.iter().copied(), 10).unwrap();
+        assert_eq!(intervals.count, 3);
+        assert_eq!(
+            intervals.start(0),
+            Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()
+        );
+        assert_eq!(intervals.index(timestamps[0]), 2);
+        assert!(Intervals::new([].iter().copied(), 10).is_none());
+    }
+
+    #[tokio::test]
+    async fn data_points_are_bucketed() {
+        let directory = test_directory("timeline", "buckets");
+        write_run(&directory);
+
+        let timeline = Timeline::from_directory(&directory, 10).await.unwrap();
+        let buckets = &timeline.buckets;
+        assert_eq!(buckets.len(), 3);
+
+        assert_eq!((buckets[0].requests, buckets[0].failed_requests), (2, 1));
+        assert_eq!(buckets[0].requests_per_second, 0.2);
+        assert_eq!(buckets[0].error_rate, 0.5);
+        assert_eq!(buckets[0].p50_duration, Some(200.0));
+        assert_eq!(buckets[0].data_sent, 512.0);
+        assert_eq!(buckets[0].vus, Some(10.0));
+
+        assert_eq!(buckets[1].requests, 1);
+        assert_eq!(buckets[1].vus, None);
+
+        // Intervals without requests have no durations
+        assert_eq!(buckets[2].requests, 0);
+        assert_eq!(buckets[2].error_rate, 0.0);
+        assert_eq!(buckets[2].p95_duration, None);
+        assert_eq!(buckets[2].vus, Some(0.0));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn aggregated_runs_are_refused() {
+        let directory = test_directory("timeline", "aggregated");
+        write_run(&directory);
+        Manifest::new(
+            RunMetadata::new(Utc::now(), &None, &None),
+            Source {
+                kind: String::from("prometheus"),
+                url: String::from("http://localhost:9090"),
+                database: None,
+                retention_policy: None,
+            },
+            TimeWindow {
+                from: None,
+                to: Utc::now(),
+                trimmed: None,
+            },
+            vec![],
+        )
+        .save(&directory)
+        .await
+        .unwrap();
+
+        let result = Timeline::from_directory(&directory, 10).await;
+        assert!(matches!(result, Err(Error::AggregatedData(_))));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::fs::remove_dir_all;
+
+use crate::error::Result;
+use crate::html::{format_duration, format_rate, render_trend};
+use crate::influx::{ConnectionOptions, InfluxSource};
+use crate::layout::{discover_runs, ROOT_RUN_NAME};
+use crate::output::OutputFormat;
+use crate::postgres::PostgresHistory;
+use crate::report::K6Report;
+use crate::statistics::z_score;
+use crate::summary::Summary;
+use crate::table::TextTable;
+
+/// A value is significantly different from the previous runs when it's at
+/// least this many standard deviations away from their mean.
+pub const DEFAULT_SIGNIFICANT_Z_SCORE: f64 = 3.0;
+
+/// Minimal number of previous runs required for detecting changes.
+pub const DEFAULT_MIN_HISTORY_SIZE: usize = 3;
+
+/// How changes between the runs are detected.
+#[derive(Debug, Clone, Copy)]
+pub struct TrendOptions {
+    /// Values at least this many standard deviations away from the mean of
+    /// the previous runs are significant.
+    pub significant_z_score: f64,
+    /// Number of previous runs needed before changes are detected.
+    pub min_history_size: usize,
+}
+
+impl Default for TrendOptions {
+    fn default() -> Self {
+        TrendOptions {
+            significant_z_score: DEFAULT_SIGNIFICANT_Z_SCORE,
+            min_history_size: DEFAULT_MIN_HISTORY_SIZE,
+        }
+    }
+}
+
+/// Time window of a run in InfluxDB written as `FROM/TO` in RFC 3339, e.g.
+/// `2024-05-01T10:00:00Z/2024-05-01T11:00:00Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunWindow {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl FromStr for RunWindow {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let (from, to) = value
+            .split_once('/')
+            .ok_or_else(|| format!("expected FROM/TO, got `{}`", value))?;
+        let parse = |time: &str| {
+            DateTime::parse_from_rfc3339(time)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|error| format!("invalid time `{}`: {}", time, error))
+        };
+        let window = RunWindow {
+            from: parse(from)?,
+            to: parse(to)?,
+        };
+        match window.from < window.to {
+            true => Ok(window),
+            false => Err(format!("the window `{}` ends before it starts", value)),
+        }
+    }
+}
+
+/// Key metrics of many runs, ordered by the start time of the runs.
+#[derive(Serialize, Debug, Clone)]
+pub struct Trend {
+    pub runs: Vec<TrendRun>,
+    pub series: Vec<TrendSeries>,
+    pub significant_z_score: f64,
+    pub min_history_size: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TrendRun {
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesKind {
+    Overall,
+    Endpoint,
+    Scenario,
+}
+
+/// Values of the metrics per run. Each list has a value for every run.
+#[derive(Serialize, Debug, Clone)]
+pub struct TrendSeries {
+    pub kind: SeriesKind,
+    pub name: String,
+    pub p95_duration: Vec<TrendValue>,
+    pub error_rate: Vec<TrendValue>,
+    pub requests_per_second: Vec<TrendValue>,
+    pub check_pass_rate: Vec<TrendValue>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct TrendValue {
+    pub value: Option<f64>,
+    pub z_score: Option<f64>,
+    pub significant: bool,
+}
+
+#[derive(Default)]
+struct SeriesValues {
+    p95_duration: Vec<Option<f64>>,
+    error_rate: Vec<Option<f64>>,
+    requests_per_second: Vec<Option<f64>>,
+    check_pass_rate: Vec<Option<f64>>,
+}
+
+impl SeriesValues {
+    fn push(
+        &mut self,
+        run_index: usize,
+        p95_duration: f64,
+        error_rate: f64,
+        requests_per_second: f64,
+        check_pass_rate: Option<f64>,
+    ) {
+        for values in [
+            &mut self.p95_duration,
+            &mut self.error_rate,
+            &mut self.requests_per_second,
+            &mut self.check_pass_rate,
+        ] {
+            values.resize(run_index, None);
+        }
+        self.p95_duration.push(Some(p95_duration));
+        self.error_rate.push(Some(error_rate));
+        self.requests_per_second.push(Some(requests_per_second));
+        self.check_pass_rate.push(check_pass_rate);
+    }
+}
+
+impl Trend {
+    /// Loads the runs from the given directories. Each directory is either an
+    /// exported run or a root directory with many runs. Runs exported from
+    /// Prometheus are refused, since their durations are aggregates.
+    pub async fn load_directories(directories: &[String]) -> Result<Vec<(TrendRun, Summary)>> {
+        let mut runs = vec![];
+        for directory in directories {
+            for run in discover_runs(Path::new(directory)).await? {
+                run.manifest
+                    .source
+                    .ensure_not_aggregated("the request durations")?;
+                let summary = Summary::load(&run.path).await?;
+                let name = match run.name == ROOT_RUN_NAME {
+                    true => directory.clone(),
+                    false => run.name,
+                };
+                let started_at = run.manifest.run.started_at;
+                runs.push((TrendRun { name, started_at }, summary));
+            }
+        }
+        Ok(runs)
+    }
+
+    /// Loads the runs stored in the PostgreSQL history with `store`.
+    pub async fn load_history(history: &PostgresHistory) -> Result<Vec<(TrendRun, Summary)>> {
+        Ok(history
+            .load_summaries()
+            .await?
+            .into_iter()
+            .map(|(name, started_at, summary)| (TrendRun { name, started_at }, summary))
+            .collect())
+    }
+
+    /// Summarizes the data points of each time window in InfluxDB as a run.
+    /// The data is exported into a temporary directory, which is removed
+    /// once the summary is computed.
+    pub async fn load_influx_windows(
+        connection: &ConnectionOptions,
+        windows: &[RunWindow],
+        testid: &Option<String>,
+    ) -> Result<Vec<(TrendRun, Summary)>> {
+        let mut runs = vec![];
+        for (index, window) in windows.iter().enumerate() {
+            let directory = std::env::temp_dir().join(format!(
+                "k6-reports-trend-{}-{}",
+                std::process::id(),
+                index
+            ));
+            let summary = K6Report::builder(InfluxSource::new(connection))
+                .output_directory(&directory.to_string_lossy())
+                .testid(testid.clone())
+                .window(window.from, window.to)
+                .force(true)
+                .quiet(true)
+                .build()?
+                .extract_metrics()
+                .await;
+            if directory.exists() {
+                remove_dir_all(&directory).await?;
+            }
+
+            let name = format!("{}/{}", window.from.to_rfc3339(), window.to.to_rfc3339());
+            runs.push((
+                TrendRun {
+                    name,
+                    started_at: window.from,
+                },
+                summary?,
+            ));
+        }
+        Ok(runs)
+    }
+
+    /// Orders the runs by the start time and keeps only the last ones.
+    pub fn from_runs(
+        mut runs: Vec<(TrendRun, Summary)>,
+        last: &Option<usize>,
+        options: &TrendOptions,
+    ) -> Self {
+        runs.sort_by_key(|(run, _)| run.started_at);
+        if let Some(last) = last {
+            runs = runs.split_off(runs.len().saturating_sub(*last));
+        }
+        Trend::new(&runs, options)
+    }
+
+    pub fn new(runs: &[(TrendRun, Summary)], options: &TrendOptions) -> Self {
+        let mut series: BTreeMap<(SeriesKind, String), SeriesValues> = BTreeMap::new();
+        for (run_index, (_, summary)) in runs.iter().enumerate() {
+            series
+                .entry((SeriesKind::Overall, String::from("all requests")))
+                .or_default()
+                .push(
+                    run_index,
+                    summary.http_req_duration.p95,
+                    summary.error_rate,
+                    summary.requests_per_second,
+                    pass_rate(summary.checks_passed, summary.checks_failed),
+                );
+
+            for endpoint in &summary.endpoints {
+                let name = format!("{} {}", endpoint.method, endpoint.name);
+                series
+                    .entry((SeriesKind::Endpoint, name))
+                    .or_default()
+                    .push(
+                        run_index,
+                        endpoint.http_req_duration.p95,
+                        endpoint.error_rate,
+                        endpoint.requests_per_second,
+                        None,
+                    );
+            }
+
+            for scenario in &summary.scenarios {
+                series
+                    .entry((SeriesKind::Scenario, scenario.name.clone()))
+                    .or_default()
+                    .push(
+                        run_index,
+                        scenario.http_req_duration.p95,
+                        scenario.error_rate,
+                        scenario.requests_per_second,
+                        pass_rate(scenario.checks_passed, scenario.checks_failed),
+                    );
+            }
+        }
+
+        let detect_changes = |values| detect_changes(values, runs.len(), options);
+        Trend {
+            runs: runs.iter().map(|(run, _)| run.clone()).collect(),
+            series: series
+                .into_iter()
+                .map(|((kind, name), values)| TrendSeries {
+                    kind,
+                    name,
+                    p95_duration: detect_changes(values.p95_duration),
+                    error_rate: detect_changes(values.error_rate),
+                    requests_per_second: detect_changes(values.requests_per_second),
+                    check_pass_rate: detect_changes(values.check_pass_rate),
+                })
+                .collect(),
+            significant_z_score: options.significant_z_score,
+            min_history_size: options.min_history_size,
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_trend(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut result = String::new();
+        for series in &self.series {
+            let title = format!("{:?}: {}", series.kind, series.name);
+            let mut table = TextTable::new(&[
+                "Run",
+                "Started at",
+                "p95 (ms)",
+                "Error rate",
+                "Requests/s",
+                "Checks passed",
+            ]);
+            for (index, run) in self.runs.iter().enumerate() {
+                table.add_row(vec![
+                    run.name.clone(),
+                    run.started_at.format("%Y-%m-%d %H:%M").to_string(),
+                    format_value(&series.p95_duration[index], format_duration),
+                    format_value(&series.error_rate[index], format_rate),
+                    format_value(&series.requests_per_second[index], |value| {
+                        format!("{:.2}", value)
+                    }),
+                    format_value(&series.check_pass_rate[index], format_rate),
+                ]);
+            }
+
+            match format {
+                OutputFormat::Markdown => {
+                    result.push_str(&format!("## {}\n\n{}\n", title, table.to_markdown()))
+                }
+                _ => result.push_str(&format!("{}\n{}\n", title, table.to_console())),
+            }
+        }
+
+        result.push_str(&format!(
+            "* significant change: at least {} standard deviations away from the mean of the previous runs, \
+             after at least {} previous runs\n",
+            self.significant_z_score, self.min_history_size
+        ));
+        result
+    }
+}
+
+/// Compares each value with all previous values of the series.
+fn detect_changes(
+    mut values: Vec<Option<f64>>,
+    runs_count: usize,
+    options: &TrendOptions,
+) -> Vec<TrendValue> {
+    values.resize(runs_count, None);
+
+    let mut history: Vec<f64> = vec![];
+    let mut result = vec![];
+    for value in values {
+        let score = match (value, history.len() >= options.min_history_size) {
+            (Some(value), true) => z_score(value, &history),
+            _ => None,
+        };
+        result.push(TrendValue {
+            value,
+            z_score: score,
+            significant: score
+                .map(|score| score.abs() >= options.significant_z_score)
+                .unwrap_or(false),
+        });
+        history.extend(value);
+    }
+    result
+}
+
+fn pass_rate(passed: u64, failed: u64) -> Option<f64> {
+    match passed + failed {
+        0 => None,
+        total => Some(passed as f64 / total as f64),
+    }
+}
+
+fn format_value(value: &TrendValue, format: impl Fn(f64) -> String) -> String {
+    match (value.value, value.significant) {
+        (Some(number), true) => format!("{} *", format(number)),
+        (Some(number), false) => format(number),
+        (None, _) => String::from("-"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn significant(values: &[TrendValue]) -> Vec<bool> {
+        values.iter().map(|value| value.significant).collect()
+    }
+
+    #[test]
+    fn detects_changes_after_the_minimal_history() {
+        let values = vec![Some(10.0), Some(11.0), Some(10.0), Some(11.0), Some(20.0)];
+
+        let changes = detect_changes(values.clone(), 5, &TrendOptions::default());
+        assert_eq!(significant(&changes), [false, false, false, false, true]);
+        assert!(changes[2].z_score.is_none());
+        assert!(changes[3].z_score.is_some());
+
+        let options = TrendOptions {
+            significant_z_score: 3.0,
+            min_history_size: 5,
+        };
+        let changes = detect_changes(values, 5, &options);
+        assert!(changes.iter().all(|value| value.z_score.is_none()));
+    }
+
+    #[test]
+    fn uses_the_configured_z_score() {
+        let values = vec![Some(10.0), Some(11.0), Some(10.0), Some(11.0), Some(12.0)];
+
+        let changes = detect_changes(values.clone(), 5, &TrendOptions::default());
+        assert!(!changes[4].significant);
+
+        let options = TrendOptions {
+            significant_z_score: 1.0,
+            min_history_size: 3,
+        };
+        let changes = detect_changes(values, 5, &options);
+        assert!(changes[4].significant);
+    }
+
+    #[test]
+    fn skips_missing_values() {
+        let values = vec![Some(10.0), None, Some(11.0)];
+        let changes = detect_changes(values, 4, &TrendOptions::default());
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[1].value, None);
+        assert_eq!(changes[3].value, None);
+    }
+
+    #[test]
+    fn parses_run_windows() {
+        let window: RunWindow = "2024-05-01T10:00:00Z/2024-05-01T12:30:00+01:00"
+            .parse()
+            .unwrap();
+        assert_eq!(window.from.to_rfc3339(), "2024-05-01T10:00:00+00:00");
+        assert_eq!(window.to.to_rfc3339(), "2024-05-01T11:30:00+00:00");
+
+        assert!("2024-05-01T10:00:00Z".parse::<RunWindow>().is_err());
+        assert!("2024-05-01T10:00:00Z/yesterday"
+            .parse::<RunWindow>()
+            .is_err());
+        assert!("2024-05-01T10:00:00Z/2024-05-01T11:00:00+01:00"
+            .parse::<RunWindow>()
+            .is_err());
+    }
+}
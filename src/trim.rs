@@ -0,0 +1,178 @@
+use chrono::{DateTime, Duration, Utc};
+use structopt::StructOpt;
+
+use crate::error::{Error, Result};
+use crate::manifest::TrimmedWindow;
+use crate::metrics::VusMetric;
+
+/// Cuts the warm-up and ramp-down phases off the exported data. Trimming needs
+/// the whole test, so the follow mode trims the data once the test stops.
+#[derive(StructOpt, Debug, Clone, Default)]
+pub struct TrimOptions {
+    #[structopt(
+        long = "--trim-start",
+        help = "Skip the given duration from the start of the test (in seconds)"
+    )]
+    pub trim_start: Option<u64>,
+
+    #[structopt(
+        long = "--trim-end",
+        help = "Skip the given duration from the end of the test (in seconds)"
+    )]
+    pub trim_end: Option<u64>,
+
+    #[structopt(
+        long = "--steady-state",
+        help = "Export only the plateau where the number of active VUs is close to its peak"
+    )]
+    pub steady_state: bool,
+}
+
+/// The test is considered to be in the steady state while the number of
+/// active VUs stays at least at this share of the peak.
+const STEADY_STATE_VUS_RATIO: f64 = 0.95;
+
+pub fn is_trimmed(options: &TrimOptions) -> bool {
+    options.trim_start.is_some() || options.trim_end.is_some() || options.steady_state
+}
+
+/// Finds the part of the test used for reports. The test boundaries are taken
+/// from the `vus` metric, because k6 reports it for the whole test duration.
+pub fn trimmed_window(options: &TrimOptions, vus: &[VusMetric]) -> Result<TrimmedWindow> {
+    let bounds = match options.steady_state {
+        true => steady_state(vus),
+        false => vus
+            .iter()
+            .map(|point| point.time)
+            .min()
+            .zip(vus.iter().map(|point| point.time).max()),
+    };
+    let (from, to) = match bounds {
+        Some(bounds) => bounds,
+        None => {
+            return Err(Error::Trim(String::from(
+                "no data points of the `vus` metric found",
+            )))
+        }
+    };
+
+    let from = from + Duration::seconds(options.trim_start.unwrap_or(0) as i64);
+    let to = to - Duration::seconds(options.trim_end.unwrap_or(0) as i64);
+    if from >= to {
+        return Err(Error::Trim(format!(
+            "nothing is left of the test after trimming (from {} to {})",
+            from.to_rfc3339(),
+            to.to_rfc3339()
+        )));
+    }
+
+    let method = match options.steady_state {
+        true => "steady-state",
+        false => "fixed",
+    };
+    Ok(TrimmedWindow {
+        method: method.to_string(),
+        from,
+        to,
+    })
+}
+
+/// Finds the longest stretch of consecutive data points where the number of
+/// active VUs stays close to its peak, so a dip in the middle of the test
+/// ends the plateau instead of being included in it.
+fn steady_state(vus: &[VusMetric]) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let peak_vus = vus.iter().map(|point| point.value).fold(0.0, f64::max);
+    let mut points: Vec<&VusMetric> = vus.iter().collect();
+    points.sort_by_key(|point| point.time);
+
+    let mut longest: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+    let mut current: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+    for point in points {
+        if point.value > 0.0 && point.value >= peak_vus * STEADY_STATE_VUS_RATIO {
+            current = Some(match current {
+                Some((from, _)) => (from, point.time),
+                None => (point.time, point.time),
+            });
+        } else {
+            current = None;
+        }
+
+        if let Some((from, to)) = current {
+            if longest
+                .is_none_or(|(longest_from, longest_to)| to - from > longest_to - longest_from)
+            {
+                longest = Some((from, to));
+            }
+        }
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// One data point per second with the given numbers of VUs.
+    fn vus(values: &[f64]) -> Vec<VusMetric> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(second, value)| VusMetric {
+                time: second_of_test(second as i64),
+                value: *value,
+            })
+            .collect()
+    }
+
+    fn second_of_test(second: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap() + Duration::seconds(second)
+    }
+
+    #[test]
+    fn fixed_durations_are_cut_off() {
+        let options = TrimOptions {
+            trim_start: Some(2),
+            trim_end: Some(3),
+            steady_state: false,
+        };
+        let window = trimmed_window(&options, &vus(&[0.0; 10])).unwrap();
+        assert_eq!(window.method, "fixed");
+        assert_eq!(window.from, second_of_test(2));
+        assert_eq!(window.to, second_of_test(6));
+    }
+
+    #[test]
+    fn steady_state_is_the_longest_plateau() {
+        let options = TrimOptions {
+            steady_state: true,
+            ..TrimOptions::default()
+        };
+        // A short plateau, a dip and a longer plateau before the ramp-down
+        let mut points = vus(&[
+            10.0, 50.0, 100.0, 100.0, 40.0, 98.0, 100.0, 99.0, 100.0, 30.0, 0.0,
+        ]);
+        // Data points don't have to be ordered by time
+        points.reverse();
+
+        let window = trimmed_window(&options, &points).unwrap();
+        assert_eq!(window.method, "steady-state");
+        assert_eq!(window.from, second_of_test(5));
+        assert_eq!(window.to, second_of_test(8));
+    }
+
+    #[test]
+    fn tests_without_vus_are_not_trimmed() {
+        let error = trimmed_window(&TrimOptions::default(), &[]).unwrap_err();
+        assert!(matches!(error, Error::Trim(_)));
+
+        let options = TrimOptions {
+            trim_start: Some(5),
+            trim_end: Some(5),
+            steady_state: false,
+        };
+        let error = trimmed_window(&options, &vus(&[1.0; 10])).unwrap_err();
+        assert!(error.to_string().contains("nothing is left"), "{}", error);
+    }
+}
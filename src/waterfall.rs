@@ -0,0 +1,411 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::dataset::Dataset;
+use crate::error::Result;
+use crate::html::{format_duration, format_rate, render_waterfall};
+use crate::metrics::{
+    HttpReqBlockedMetric, HttpReqConnectingMetric, HttpReqReceivingMetric, HttpReqSendingMetric,
+    HttpReqTlsHandshakingMetric, HttpReqWaitingMetric, K6Metric,
+};
+use crate::output::OutputFormat;
+use crate::statistics::{mean, percentile, sort_values};
+use crate::table::TextTable;
+
+/// Phases of an HTTP request in the order they happen.
+pub const PHASES: [&str; 6] = [
+    "blocked",
+    "connecting",
+    "tls_handshaking",
+    "sending",
+    "waiting",
+    "receiving",
+];
+
+/// Tags identifying points of the same request across the phase metrics.
+type RequestKey = (
+    DateTime<Utc>,
+    Option<String>,
+    String,
+    Option<String>,
+    u16,
+    Option<String>,
+    Option<String>,
+);
+
+/// Common fields of the `http_req_*` phase metrics.
+trait PhaseRecord {
+    fn key(&self) -> RequestKey;
+    fn endpoint(&self) -> (String, String);
+    fn value(&self) -> f64;
+}
+
+macro_rules! impl_phase_record {
+    ($($metric:ty),*) => {
+        $(
+            impl PhaseRecord for $metric {
+                fn key(&self) -> RequestKey {
+                    (
+                        self.time,
+                        self.method.clone(),
+                        self.url.clone(),
+                        self.name.clone(),
+                        self.status,
+                        self.scenario.clone(),
+                        self.group.clone(),
+                    )
+                }
+
+                fn endpoint(&self) -> (String, String) {
+                    (
+                        self.method.clone().unwrap_or_default(),
+                        self.name.clone().unwrap_or_else(|| self.url.clone()),
+                    )
+                }
+
+                fn value(&self) -> f64 {
+                    self.value
+                }
+            }
+        )*
+    };
+}
+
+impl_phase_record!(
+    HttpReqBlockedMetric,
+    HttpReqConnectingMetric,
+    HttpReqTlsHandshakingMetric,
+    HttpReqSendingMetric,
+    HttpReqWaitingMetric,
+    HttpReqReceivingMetric
+);
+
+/// How the time of requests is split between the phases, per endpoint.
+#[derive(Serialize, Debug, Clone)]
+pub struct Waterfall {
+    pub phases: Vec<String>,
+    pub overall: EndpointWaterfall,
+    pub endpoints: Vec<EndpointWaterfall>,
+    /// Requests left out because other requests have the same tags and
+    /// timestamp, so their phases can't be told apart.
+    pub ambiguous_requests: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EndpointWaterfall {
+    pub method: String,
+    pub name: String,
+    /// Requests with data points of all phases.
+    pub requests: u64,
+    /// Mean sum of all phases in milliseconds.
+    pub mean_total: f64,
+    pub phases: Vec<PhaseStats>,
+}
+
+/// Durations are in milliseconds, shares are relative to the sum of all
+/// phases of each request.
+#[derive(Serialize, Debug, Clone)]
+pub struct PhaseStats {
+    pub phase: String,
+    pub mean: f64,
+    pub p95: f64,
+    pub mean_share: f64,
+    pub p95_share: f64,
+}
+
+/// Phase durations of a single request, in the order of `PHASES`.
+type RequestPhases = [f64; 6];
+
+impl Waterfall {
+    /// Joins the phases of each request, which needs a data point per request,
+    /// so runs exported from Prometheus are refused.
+    pub async fn from_directory(directory: &Path) -> Result<Self> {
+        let dataset = Dataset::open(directory).await?;
+        dataset.ensure_not_aggregated("the phases")?;
+        let mut requests: HashMap<RequestKey, Vec<[Option<f64>; 6]>> = HashMap::new();
+        let mut endpoints: HashMap<RequestKey, (String, String)> = HashMap::new();
+        join_phase::<HttpReqBlockedMetric>(&dataset, 0, &mut requests, &mut endpoints).await?;
+        join_phase::<HttpReqConnectingMetric>(&dataset, 1, &mut requests, &mut endpoints).await?;
+        join_phase::<HttpReqTlsHandshakingMetric>(&dataset, 2, &mut requests, &mut endpoints)
+            .await?;
+        join_phase::<HttpReqSendingMetric>(&dataset, 3, &mut requests, &mut endpoints).await?;
+        join_phase::<HttpReqWaitingMetric>(&dataset, 4, &mut requests, &mut endpoints).await?;
+        join_phase::<HttpReqReceivingMetric>(&dataset, 5, &mut requests, &mut endpoints).await?;
+
+        // Points of a request missing in any phase metric can't be split, and
+        // neither can requests sharing the key, since the points of different
+        // phases aren't guaranteed to be stored in the same order
+        let mut all_requests: Vec<RequestPhases> = vec![];
+        let mut by_endpoint: BTreeMap<(String, String), Vec<RequestPhases>> = BTreeMap::new();
+        let mut ambiguous_requests = 0;
+        for (key, occurrences) in requests {
+            if occurrences.len() > 1 {
+                ambiguous_requests += occurrences.len() as u64;
+                continue;
+            }
+            for phases in occurrences {
+                let phases: Option<Vec<f64>> = phases.into_iter().collect();
+                if let Some(phases) = phases {
+                    let phases: RequestPhases = phases.try_into().unwrap();
+                    all_requests.push(phases);
+                    by_endpoint
+                        .entry(endpoints[&key].clone())
+                        .or_default()
+                        .push(phases);
+                }
+            }
+        }
+
+        Ok(Waterfall {
+            phases: PHASES.iter().map(|phase| phase.to_string()).collect(),
+            overall: summarize_phases("", "all requests", &all_requests),
+            endpoints: by_endpoint
+                .into_iter()
+                .map(|((method, name), requests)| summarize_phases(&method, &name, &requests))
+                .collect(),
+            ambiguous_requests,
+        })
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        match format {
+            OutputFormat::Console | OutputFormat::Markdown => Ok(self.render_text(format)),
+            OutputFormat::Html => Ok(render_waterfall(self)),
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// Tells how many requests were left out as ambiguous, if any.
+    pub fn ambiguous_note(&self) -> Option<String> {
+        match self.ambiguous_requests {
+            0 => None,
+            requests => Some(format!(
+                "{} requests were left out because other requests have the same tags and timestamp",
+                requests
+            )),
+        }
+    }
+
+    /// The overall row goes first.
+    pub fn rows(&self) -> impl Iterator<Item = &EndpointWaterfall> {
+        std::iter::once(&self.overall).chain(self.endpoints.iter())
+    }
+
+    fn render_text(&self, format: OutputFormat) -> String {
+        let mut headers = vec!["Endpoint", "Requests", "Total (ms)"];
+        headers.extend(PHASES);
+        let mut shares = TextTable::new(&headers);
+        let mut durations = TextTable::new(&headers);
+        for endpoint in self.rows() {
+            let label = format!("{} {}", endpoint.method, endpoint.name)
+                .trim()
+                .to_string();
+            let mut share_row = vec![
+                label.clone(),
+                endpoint.requests.to_string(),
+                format_duration(endpoint.mean_total),
+            ];
+            let mut duration_row = share_row.clone();
+            for phase in &endpoint.phases {
+                share_row.push(format!(
+                    "{} / {}",
+                    format_rate(phase.mean_share),
+                    format_rate(phase.p95_share)
+                ));
+                duration_row.push(format!(
+                    "{} / {}",
+                    format_duration(phase.mean),
+                    format_duration(phase.p95)
+                ));
+            }
+            shares.add_row(share_row);
+            durations.add_row(duration_row);
+        }
+
+        let sections = [
+            ("Share of the total time (mean / p95)", shares),
+            ("Phase durations in ms (mean / p95)", durations),
+        ];
+        let mut result = String::new();
+        for (title, table) in sections {
+            match format {
+                OutputFormat::Markdown => {
+                    result.push_str(&format!("## {}\n\n{}\n", title, table.to_markdown()))
+                }
+                _ => result.push_str(&format!("{}\n{}\n", title, table.to_console())),
+            }
+        }
+        if let Some(note) = self.ambiguous_note() {
+            result.push_str(&format!("* {}\n", note));
+        }
+        result
+    }
+}
+
+/// Matches points of the phase metric with the points of other phases by their
+/// tags and timestamp. Points sharing them are kept apart in the order they're
+/// stored, so the ambiguous requests can be counted.
+async fn join_phase<T>(
+    dataset: &Dataset,
+    phase_index: usize,
+    requests: &mut HashMap<RequestKey, Vec<[Option<f64>; 6]>>,
+    endpoints: &mut HashMap<RequestKey, (String, String)>,
+) -> Result<()>
+where
+    T: K6Metric + PhaseRecord + DeserializeOwned + 'static,
+{
+    let mut occurrences: HashMap<RequestKey, usize> = HashMap::new();
+    for record in dataset.load_metric::<T>().await? {
+        let key = record.key();
+        let occurrence = occurrences.entry(key.clone()).or_default();
+        let request_phases = requests.entry(key.clone()).or_default();
+        if request_phases.len() <= *occurrence {
+            request_phases.resize(*occurrence + 1, [None; 6]);
+        }
+        request_phases[*occurrence][phase_index] = Some(record.value());
+        *occurrence += 1;
+        endpoints.entry(key).or_insert_with(|| record.endpoint());
+    }
+
+    Ok(())
+}
+
+fn summarize_phases(method: &str, name: &str, requests: &[RequestPhases]) -> EndpointWaterfall {
+    let totals: Vec<f64> = requests.iter().map(|phases| phases.iter().sum()).collect();
+    let phases = PHASES
+        .iter()
+        .enumerate()
+        .map(|(index, phase)| {
+            let mut durations: Vec<f64> = requests.iter().map(|phases| phases[index]).collect();
+            let mut shares: Vec<f64> = requests
+                .iter()
+                .zip(&totals)
+                .filter(|(_, total)| **total > 0.0)
+                .map(|(phases, total)| phases[index] / total)
+                .collect();
+            sort_values(&mut durations);
+            sort_values(&mut shares);
+            PhaseStats {
+                phase: phase.to_string(),
+                mean: mean(&durations),
+                p95: percentile(&durations, 95.0),
+                mean_share: mean(&shares),
+                p95_share: percentile(&shares, 95.0),
+            }
+        })
+        .collect();
+
+    EndpointWaterfall {
+        method: method.to_string(),
+        name: name.to_string(),
+        requests: requests.len() as u64,
+        mean_total: mean(&totals),
+        phases,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::layout::RunMetadata;
+    use crate::manifest::{Manifest, Source, TimeWindow};
+    use crate::test_support::test_directory;
+
+    const HEADER: &str =
+        "time,expected_response,group,method,name,proto,scenario,status,tls_version,url,value\n";
+
+    /// Writes every phase with one point per request and the given value.
+    fn write_phases(directory: &Path, requests: &[(&str, &str)]) {
+        for (index, phase) in PHASES.iter().enumerate() {
+            let mut content = String::from(HEADER);
+            for (time, url) in requests {
+                content.push_str(&format!(
+                    "{0},true,,GET,{1},HTTP/1.1,default,200,,{1},{2}\n",
+                    time,
+                    url,
+                    index + 1
+                ));
+            }
+            std::fs::write(directory.join(format!("http_req_{}.csv", phase)), content).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn joins_the_phases_of_each_request() {
+        let directory = test_directory("waterfall", "join");
+        write_phases(
+            &directory,
+            &[
+                ("2024-05-01T12:00:00Z", "/a"),
+                ("2024-05-01T12:00:01Z", "/a"),
+                ("2024-05-01T12:00:01Z", "/b"),
+            ],
+        );
+
+        let waterfall = Waterfall::from_directory(&directory).await.unwrap();
+        assert_eq!(waterfall.overall.requests, 3);
+        assert_eq!(waterfall.overall.mean_total, 21.0);
+        assert_eq!(waterfall.overall.phases[5].mean, 6.0);
+        assert_eq!(waterfall.endpoints.len(), 2);
+        assert_eq!(waterfall.ambiguous_requests, 0);
+        assert!(waterfall.ambiguous_note().is_none());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn leaves_out_requests_with_the_same_key() {
+        let directory = test_directory("waterfall", "ambiguous");
+        write_phases(
+            &directory,
+            &[
+                ("2024-05-01T12:00:00Z", "/a"),
+                ("2024-05-01T12:00:00Z", "/a"),
+                ("2024-05-01T12:00:01Z", "/a"),
+            ],
+        );
+
+        let waterfall = Waterfall::from_directory(&directory).await.unwrap();
+        assert_eq!(waterfall.overall.requests, 1);
+        assert_eq!(waterfall.ambiguous_requests, 2);
+        assert!(waterfall.render(OutputFormat::Console).unwrap().contains(
+            "2 requests were left out because other requests have the same tags and timestamp"
+        ));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_aggregated_sources() {
+        let directory = test_directory("waterfall", "prometheus");
+        write_phases(&directory, &[("2024-05-01T12:00:00Z", "/a")]);
+        let manifest = Manifest::new(
+            RunMetadata::new(Utc::now(), &None, &None),
+            Source {
+                kind: String::from("prometheus"),
+                url: String::from("http://localhost:9090"),
+                database: None,
+                retention_policy: None,
+            },
+            TimeWindow {
+                from: None,
+                to: Utc::now(),
+                trimmed: None,
+            },
+            vec![],
+        );
+        manifest.save(&directory).await.unwrap();
+
+        let error = Waterfall::from_directory(&directory).await.unwrap_err();
+        assert!(matches!(error, Error::AggregatedData(_)), "{}", error);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
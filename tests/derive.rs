@@ -0,0 +1,6 @@
+#[test]
+fn derive_k6_metric() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass_*.rs");
+    cases.compile_fail("tests/ui/fail_*.rs");
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+use serde::Serialize;
+
+#[derive(Serialize, K6Metric)]
+#[k6(measurement = "http_reqs")]
+struct HttpReqsMetric {
+    time: DateTime<Utc>,
+    #[k6(tag)]
+    name: String,
+    #[k6(tag)]
+    #[serde(rename = "name")]
+    url: String,
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {}
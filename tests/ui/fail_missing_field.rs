@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+
+#[derive(K6Metric)]
+#[k6(measurement = "checks")]
+struct ChecksMetric {
+    time: DateTime<Utc>,
+    #[k6(tag)]
+    check: String,
+}
+
+fn main() {}
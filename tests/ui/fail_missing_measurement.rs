@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+
+#[derive(K6Metric)]
+struct VusMetric {
+    time: DateTime<Utc>,
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {}
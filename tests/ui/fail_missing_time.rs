@@ -0,0 +1,10 @@
+use k6_reports::metrics::K6Metric;
+
+#[derive(K6Metric)]
+#[k6(measurement = "vus")]
+struct VusMetric {
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {}
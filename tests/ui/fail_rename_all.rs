@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+use serde::Serialize;
+
+#[derive(Serialize, K6Metric)]
+#[k6(measurement = "vus")]
+#[serde(rename_all = "camelCase")]
+struct VusMetric {
+    time: DateTime<Utc>,
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {}
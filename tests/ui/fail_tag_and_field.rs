@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+
+#[derive(K6Metric)]
+#[k6(measurement = "vus")]
+struct VusMetric {
+    time: DateTime<Utc>,
+    #[k6(tag, field)]
+    value: f64,
+}
+
+fn main() {}
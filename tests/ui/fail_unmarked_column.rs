@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+
+#[derive(K6Metric)]
+#[k6(measurement = "http_req_duration")]
+struct HttpReqDurationMetric {
+    time: DateTime<Utc>,
+    url: String,
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {}
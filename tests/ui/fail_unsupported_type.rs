@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::K6Metric;
+
+#[derive(K6Metric)]
+#[k6(measurement = "vus")]
+struct VusMetric {
+    time: DateTime<Utc>,
+    #[k6(tag)]
+    scenarios: Vec<String>,
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use k6_reports::metrics::{ColumnKind, DataType, K6Metric};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, K6Metric)]
+#[k6(measurement = "checkout_duration")]
+struct CheckoutDurationMetric {
+    time: DateTime<Utc>,
+    #[k6(tag)]
+    #[serde(rename = "payment_provider")]
+    provider: Option<String>,
+    #[k6(tag)]
+    #[serde(default)]
+    retried: bool,
+    #[k6(field)]
+    value: f64,
+}
+
+fn main() {
+    assert_eq!(CheckoutDurationMetric::metric_table_name(), "checkout_duration");
+    assert_eq!(
+        CheckoutDurationMetric::query_fields(),
+        &["time", "\"payment_provider\"", "\"retried\"", "value"]
+    );
+
+    let schema = CheckoutDurationMetric::schema();
+    assert_eq!(schema[1].name, "payment_provider");
+    assert_eq!(schema[1].kind, ColumnKind::Tag);
+    assert_eq!(schema[1].data_type, DataType::String);
+    assert!(schema[1].nullable);
+    assert_eq!(schema[2].data_type, DataType::Boolean);
+    assert!(!schema[2].nullable);
+    assert_eq!(schema[3].kind, ColumnKind::Field);
+}